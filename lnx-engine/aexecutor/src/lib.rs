@@ -1,12 +1,127 @@
 mod reader_executor;
+mod thread_options;
 
 use std::borrow::Borrow;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{Error, Result};
-use tantivy::{LeasedItem, Searcher};
+use once_cell::sync::OnceCell;
+use tantivy::{Executor, LeasedItem, Searcher};
 use tokio::sync::{oneshot, Semaphore};
 
-use crate::reader_executor::TantivyExecutorPool;
+use crate::reader_executor::{ExecutorHandle, TantivyExecutorPool};
+pub use crate::thread_options::ThreadOptions;
+
+static SHARED_POOL: OnceCell<SharedPool> = OnceCell::new();
+
+/// Returned when no concurrency permit became available within the pool's
+/// acquire timeout.
+///
+/// Every permit was held by a running task for the whole timeout, which
+/// means the pool is at it's `max_concurrency`.
+#[derive(Debug)]
+pub struct PoolSaturated;
+
+impl fmt::Display for PoolSaturated {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the executor pool is saturated, try again later")
+    }
+}
+
+impl std::error::Error for PoolSaturated {}
+
+/// A tantivy executor shared across every `SearcherExecutorPool`.
+///
+/// Searches are spawned onto the executor's own thread pool, so the same
+/// threads run both the searches and their per segment tasks.
+struct SharedPool {
+    executor: Arc<Executor>,
+}
+
+impl SharedPool {
+    fn thread_pool(&self) -> &rayon::ThreadPool {
+        match self.executor.as_ref() {
+            Executor::ThreadPool(pool) => pool,
+            Executor::SingleThread => {
+                unreachable!("the shared executor is always built on a thread pool")
+            },
+        }
+    }
+}
+
+/// Initialises a single search thread pool shared across all indexes.
+///
+/// Any `SearcherExecutorPool` created after this is called will run its
+/// searches on the shared pool rather than spawning its own set of threads,
+/// the per-pool concurrency limit still applies.
+///
+/// This can only be called once, any subsequent calls will return an error.
+pub fn init_shared_executor_pool(num_threads: usize) -> Result<()> {
+    if num_threads == 0 {
+        return Err(Error::msg("the shared pool must have at least 1 thread."));
+    }
+
+    let thread_pool = rayon::ThreadPoolBuilder::new()
+        .thread_name(|n| format!("shared-executor-pool-worker-{}", n))
+        .num_threads(num_threads)
+        .build()?;
+
+    SHARED_POOL
+        .set(SharedPool {
+            executor: Arc::new(Executor::ThreadPool(thread_pool)),
+        })
+        .map_err(|_| Error::msg("the shared executor pool is already initialised."))
+}
+
+/// Builds a pool's threads applying the given options to each thread.
+///
+/// This waits for every thread to start so any errors applying the
+/// options can be returned.
+fn build_thread_pool(
+    num_threads: usize,
+    thread_opts: ThreadOptions,
+) -> Result<rayon::ThreadPool> {
+    let builder = rayon::ThreadPoolBuilder::new()
+        .thread_name(|n| format!("executor-pool-worker-{}", n))
+        .num_threads(num_threads);
+
+    if !thread_opts.is_set() {
+        return Ok(builder.build()?);
+    }
+
+    let (tx, rx) = crossbeam::channel::bounded(num_threads);
+    let thread_pool = builder
+        .start_handler(move |_| {
+            let _ = tx.send(thread_opts.apply_to_current());
+        })
+        .build()?;
+
+    for result in rx.iter().take(num_threads) {
+        result?;
+    }
+
+    Ok(thread_pool)
+}
+
+/// The executor a given search is ran with.
+enum SearchExecutor {
+    /// An executor leased from the pool's own set of executors.
+    Pooled(ExecutorHandle),
+
+    /// The globally shared executor.
+    Shared(Arc<Executor>),
+}
+
+impl Borrow<Executor> for SearchExecutor {
+    fn borrow(&self) -> &Executor {
+        match self {
+            Self::Pooled(handle) => handle.borrow(),
+            Self::Shared(executor) => executor.as_ref(),
+        }
+    }
+}
 
 /// A thread pool that waits for a given task to complete
 /// before resolving the future.
@@ -61,52 +176,111 @@ impl ExecutorPool {
 /// scheduler. Basically tokio's spawn_blocking but with a set pool.
 pub struct SearcherExecutorPool {
     reader: tantivy::IndexReader,
-    reader_executors: reader_executor::TantivyExecutorPool,
+    reader_executors: Option<reader_executor::TantivyExecutorPool>,
     limiter: Semaphore,
-    thread_pool: rayon::ThreadPool,
+    acquire_timeout: Option<Duration>,
+
+    /// The pool's own threads, `None` if the shared pool is used.
+    thread_pool: Option<rayon::ThreadPool>,
 }
 
 impl SearcherExecutorPool {
     /// Creates a new thread pool with a set concurrency.
     ///
     /// The set concurrency determines the number of threads spawned.
+    ///
+    /// If the shared executor pool has been initialised no new threads
+    /// are spawned and the shared pool is used instead.
+    ///
+    /// The thread options are applied to each of the pool's threads, these
+    /// cannot be used with the shared pool.
     pub async fn create(
         reader: tantivy::IndexReader,
         threads_per_reader: usize,
         max_concurrency: usize,
+        thread_opts: ThreadOptions,
     ) -> Result<Self> {
         let limiter = Semaphore::new(max_concurrency);
-        let thread_pool = rayon::ThreadPoolBuilder::new()
-            .thread_name(|n| format!("executor-pool-worker-{}", n))
-            .num_threads(max_concurrency)
-            .build()?;
+
+        if SHARED_POOL.get().is_some() {
+            if thread_opts.is_set() {
+                return Err(Error::msg(
+                    "thread options cannot be used with the shared executor pool.",
+                ));
+            }
+
+            return Ok(Self {
+                reader,
+                reader_executors: None,
+                limiter,
+                acquire_timeout: None,
+                thread_pool: None,
+            });
+        }
+
+        let thread_pool = build_thread_pool(max_concurrency, thread_opts)?;
 
         let reader_executors =
             TantivyExecutorPool::create(max_concurrency, threads_per_reader).await?;
 
         Ok(Self {
             reader,
-            reader_executors,
+            reader_executors: Some(reader_executors),
             limiter,
-            thread_pool,
+            acquire_timeout: None,
+            thread_pool: Some(thread_pool),
         })
     }
 
+    /// Sets the maximum time a task waits for a concurrency permit before
+    /// a `PoolSaturated` error is returned.
+    ///
+    /// Tasks wait indefinitely if no timeout is set.
+    pub fn with_acquire_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.acquire_timeout = timeout;
+        self
+    }
+
     /// Spawns a new function into the pool and returns
     /// the results once complete.
+    ///
+    /// If the pool has an acquire timeout and no permit becomes available
+    /// within it, a `PoolSaturated` error is returned instead.
     pub async fn spawn<F, T>(&self, func: F) -> Result<T>
     where
         F: FnOnce(LeasedItem<Searcher>, &tantivy::Executor) -> T + Send + 'static,
         T: Sync + Send + 'static,
     {
-        let _permit = self.limiter.acquire().await?;
-        let executor = self.reader_executors.get().await?;
+        let permit = match self.acquire_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.limiter.acquire())
+                .await
+                .map_err(|_| Error::new(PoolSaturated))?,
+            None => self.limiter.acquire().await,
+        };
+        let _permit =
+            permit.map_err(|_| Error::msg("the executor pool has been shutdown."))?;
+        let executor = match self.reader_executors {
+            Some(ref pool) => SearchExecutor::Pooled(pool.get().await?),
+            None => {
+                let shared = SHARED_POOL.get().expect("get shared pool");
+                SearchExecutor::Shared(shared.executor.clone())
+            },
+        };
         let searcher = self.reader.searcher();
         let (tx, rx) = oneshot::channel();
-        self.thread_pool.spawn(move || {
+        let task = move || {
             let result = func(searcher, executor.borrow());
             let _ = tx.send(result);
-        });
+        };
+
+        match self.thread_pool {
+            Some(ref pool) => pool.spawn(task),
+            None => SHARED_POOL
+                .get()
+                .expect("get shared pool")
+                .thread_pool()
+                .spawn(task),
+        }
 
         Ok(rx.await?)
     }
@@ -116,6 +290,18 @@ impl SearcherExecutorPool {
         self.reader.reload().map_err(Error::from)
     }
 
+    /// Stops the pool from accepting any new tasks and drops the pool's
+    /// tantivy executors, stopping their threads.
+    ///
+    /// Tasks already running are left to complete, the pool's own
+    /// thread pool is stopped once the pool is dropped.
+    pub fn shutdown(&self) {
+        self.limiter.close();
+        if let Some(ref pool) = self.reader_executors {
+            pool.shutdown();
+        }
+    }
+
     #[inline]
     pub fn searcher(&self) -> LeasedItem<Searcher> {
         self.reader.searcher()
@@ -1,7 +1,7 @@
 use std::borrow::Borrow;
 
 use anyhow::{Error, Result};
-use async_channel::{Receiver, Sender};
+use async_channel::{Receiver, Sender, TrySendError};
 use tantivy::Executor;
 
 pub(crate) struct TantivyExecutorPool {
@@ -39,6 +39,15 @@ impl TantivyExecutorPool {
                 .context("all executors have been taken at the time of acquiring"))
         }
     }
+
+    /// Closes the pool dropping all of the idle executors.
+    ///
+    /// Any executors currently in use are dropped once their handle is
+    /// released rather than being returned to the pool.
+    pub(crate) fn shutdown(&self) {
+        self.executors.close();
+        while self.executors.try_recv().is_ok() {}
+    }
 }
 
 /// A wrapper around a tantivy executor.
@@ -69,7 +78,7 @@ impl Borrow<Executor> for ExecutorHandle {
 impl Drop for ExecutorHandle {
     fn drop(&mut self) {
         if let Some(inner) = self.inner.take() {
-            if self.returner.try_send(inner).is_err() {
+            if let Err(TrySendError::Full(_)) = self.returner.try_send(inner) {
                 panic!("failed to return executor to pool")
             }
         }
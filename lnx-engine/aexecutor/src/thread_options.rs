@@ -0,0 +1,98 @@
+use anyhow::{anyhow, Error, Result};
+
+/// The scheduling options applied to every thread of a pool.
+///
+/// This is useful when several indexes share a host, a low priority index
+/// can be deprioritised or pinned to a set of cores so it does not compete
+/// with a latency critical one.
+#[derive(Debug, Clone, Default)]
+pub struct ThreadOptions {
+    /// The nice value of each thread, between -20 and 19.
+    ///
+    /// Higher values lower the thread's priority, negative values generally
+    /// require elevated privileges.
+    pub niceness: Option<i32>,
+
+    /// The CPU cores each thread is pinned to.
+    pub cpu_affinity: Option<Vec<usize>>,
+}
+
+impl ThreadOptions {
+    /// If any of the options differ from the OS defaults.
+    pub fn is_set(&self) -> bool {
+        self.niceness.is_some() || self.cpu_affinity.is_some()
+    }
+
+    /// Applies the options to the calling thread.
+    pub(crate) fn apply_to_current(&self) -> Result<()> {
+        if let Some(niceness) = self.niceness {
+            set_niceness(niceness)?;
+        }
+
+        if let Some(ref cores) = self.cpu_affinity {
+            set_affinity(cores)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_niceness(niceness: i32) -> Result<()> {
+    if !(-20..=19).contains(&niceness) {
+        return Err(anyhow!(
+            "thread niceness must be between -20 and 19, got {}",
+            niceness
+        ));
+    }
+
+    // On linux a `who` of 0 only applies to the calling thread
+    // rather than the whole process.
+    let res = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, niceness) };
+    if res != 0 {
+        return Err(Error::from(std::io::Error::last_os_error())
+            .context("failed to set the thread niceness"));
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn set_affinity(cores: &[usize]) -> Result<()> {
+    if cores.is_empty() {
+        return Err(Error::msg("thread affinity must contain at least 1 core."));
+    }
+
+    let mut set = unsafe { std::mem::zeroed::<libc::cpu_set_t>() };
+    for core in cores {
+        if *core >= libc::CPU_SETSIZE as usize {
+            return Err(anyhow!("cpu core {} is out of range", core));
+        }
+
+        unsafe { libc::CPU_SET(*core, &mut set) };
+    }
+
+    let res = unsafe {
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+    if res != 0 {
+        return Err(Error::from(std::io::Error::last_os_error())
+            .context("failed to set the thread affinity"));
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_niceness(_niceness: i32) -> Result<()> {
+    Err(Error::msg(
+        "setting the thread niceness is only supported on linux.",
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_affinity(_cores: &[usize]) -> Result<()> {
+    Err(Error::msg(
+        "setting the thread affinity is only supported on linux.",
+    ))
+}
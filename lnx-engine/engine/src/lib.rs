@@ -1,24 +1,71 @@
+use std::fmt;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{Error, Result};
 use arc_swap::ArcSwap;
 use hashbrown::HashMap;
 use parking_lot::Mutex;
-use search_index::structures::IndexDeclaration;
+use search_index::structures::{IndexDeclaration, StorageType};
 pub use search_index::{
+    init_shared_executor_pool,
     structures,
     DocumentId,
+    ExportLimits,
     Index,
+    IndexReadOnly,
+    PoolSaturated,
     QueryPayload,
     QueryResults,
     StorageBackend,
+    WriterQueueFull,
 };
+use tracing::error;
+
+/// Returned when creating an index with the same name as an existing index
+/// but a different declaration.
+#[derive(Debug)]
+pub struct IndexConflict;
+
+impl fmt::Display for IndexConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(
+            "an index with the same name but a different declaration already exists",
+        )
+    }
+}
+
+impl std::error::Error for IndexConflict {}
+
+/// Returned when creating an index would exceed the engine's maximum
+/// number of indexes.
+#[derive(Debug)]
+pub struct IndexLimitReached(pub usize);
+
+impl fmt::Display for IndexLimitReached {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the maximum number of indexes ({}) has been reached",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for IndexLimitReached {}
 
 /// A manager around a set of indexes.
 #[derive(Clone)]
 pub struct Engine {
     declarations: Arc<Mutex<HashMap<String, IndexDeclaration>>>,
     indexes: Arc<ArcSwap<HashMap<String, Index>>>,
+
+    /// Held while an index is added so checking if the index exists and
+    /// adding it happen as a single step.
+    insert_lock: Arc<tokio::sync::Mutex<()>>,
+    default_storage_type: Option<StorageType>,
+    default_storage_path: Option<String>,
+    max_indexes: Option<usize>,
 }
 
 /// Creates a new unpopulated engine.
@@ -27,20 +74,103 @@ impl Default for Engine {
         Self {
             declarations: Arc::new(Mutex::new(HashMap::new())),
             indexes: Arc::new(ArcSwap::from_pointee(HashMap::new())),
+            insert_lock: Arc::new(tokio::sync::Mutex::new(())),
+            default_storage_type: None,
+            default_storage_path: None,
+            max_indexes: None,
         }
     }
 }
 
 impl Engine {
+    /// Creates a new unpopulated engine using the given storage settings
+    /// for any index declarations which do not specify their own.
+    pub fn with_storage_defaults(
+        default_storage_type: Option<StorageType>,
+        default_storage_path: Option<String>,
+    ) -> Self {
+        Self {
+            default_storage_type,
+            default_storage_path,
+            ..Default::default()
+        }
+    }
+
+    /// Limits the number of indexes the engine can have at once.
+    ///
+    /// Adding an index beyond the limit returns an `IndexLimitReached`
+    /// error, replacing an existing index is always allowed.
+    pub fn with_max_indexes(mut self, max_indexes: Option<usize>) -> Self {
+        self.max_indexes = max_indexes;
+        self
+    }
+
+    /// The maximum number of indexes the engine can have at once, if any.
+    pub fn max_indexes(&self) -> Option<usize> {
+        self.max_indexes
+    }
+
+    /// The number of indexes currently loaded.
+    pub fn num_indexes(&self) -> usize {
+        self.indexes.load().len()
+    }
+
     /// Adds an index to the index from a given declaration.
     ///
     /// This duplicates the current indexes and swaps the clone, in general
     /// this is a very heavy operation and shouldn't be ran often / arbitrarily.
+    ///
+    /// If `override_if_exists` is true any existing index with the same name
+    /// is removed, including it's persisted data, before the new index is
+    /// created. Otherwise an error is returned if the name is already in use.
+    ///
+    /// Any storage settings the declaration leaves unspecified are resolved
+    /// with the engine's defaults before the index is created.
     pub async fn add_index(
         &self,
         index: IndexDeclaration,
         override_if_exists: bool,
     ) -> Result<()> {
+        self.insert_index(index, override_if_exists, true).await
+    }
+
+    /// Adds a previously persisted index, replacing any existing index with
+    /// the same name.
+    ///
+    /// Unlike `add_index` this ignores the engine's maximum number of
+    /// indexes so existing indexes are never dropped when loading.
+    ///
+    /// Declarations persisted before the storage path was configurable
+    /// have no storage path, these keep using the original storage
+    /// location rather than the engine's default path.
+    pub async fn load_index(&self, mut index: IndexDeclaration) -> Result<()> {
+        index.resolve_storage(self.default_storage_type, None);
+        self.insert_index(index, true, false).await
+    }
+
+    async fn insert_index(
+        &self,
+        index: IndexDeclaration,
+        override_if_exists: bool,
+        enforce_limit: bool,
+    ) -> Result<()> {
+        let _guard = self.insert_lock.lock().await;
+        self.insert_index_locked(index, override_if_exists, enforce_limit)
+            .await
+    }
+
+    /// Adds the index, the caller must hold the `insert_lock`.
+    async fn insert_index_locked(
+        &self,
+        mut index: IndexDeclaration,
+        override_if_exists: bool,
+        enforce_limit: bool,
+    ) -> Result<()> {
+        index.resolve_storage(
+            self.default_storage_type,
+            self.default_storage_path.as_deref(),
+        );
+
         let mut indexes;
         {
             let guard = self.indexes.load();
@@ -51,6 +181,28 @@ impl Engine {
             return Err(Error::msg("index already exists."));
         }
 
+        {
+            let declarations = self.declarations.lock();
+            let sharing = declarations.values().find(|existing| {
+                existing.name() != index.name() && existing.shares_storage_with(&index)
+            });
+
+            if let Some(existing) = sharing {
+                return Err(anyhow::anyhow!(
+                    "the storage of the name {:?} is still used by the index {:?}, choose a different name.",
+                    index.name(),
+                    existing.name(),
+                ));
+            }
+        }
+
+        if let Some(max) = self.max_indexes {
+            let is_new = indexes.get(index.name()).is_none();
+            if enforce_limit && is_new && indexes.len() >= max {
+                return Err(IndexLimitReached(max).into());
+            }
+        }
+
         // remove the index if it exists
         self.remove_index(index.name()).await?;
 
@@ -70,16 +222,66 @@ impl Engine {
         Ok(())
     }
 
+    /// Adds an index from the given declaration unless an index with the
+    /// same name already exists.
+    ///
+    /// Adding an identical declaration again is a no-op returning `false`,
+    /// while a differing declaration returns an `IndexConflict` error.
+    pub async fn add_index_if_not_exists(
+        &self,
+        mut index: IndexDeclaration,
+    ) -> Result<bool> {
+        index.resolve_storage(
+            self.default_storage_type,
+            self.default_storage_path.as_deref(),
+        );
+
+        // The lock is held until the index is added so a concurrent request
+        // for the same name sees it rather than also adding it.
+        let _guard = self.insert_lock.lock().await;
+
+        let existing = { self.declarations.lock().get(index.name()).cloned() };
+        if let Some(existing) = existing {
+            if serde_json::to_value(&existing)? == serde_json::to_value(&index)? {
+                return Ok(false);
+            }
+
+            return Err(IndexConflict.into());
+        }
+
+        self.insert_index_locked(index, false, true).await?;
+
+        Ok(true)
+    }
+
     /// Removes an index to the index from the engine with a given name.
     ///
-    /// This internally calls `Index.destroy()` to cleanup writers.
+    /// This internally calls `Index.destroy()` to cleanup writers and
+    /// any persisted index data.
     pub async fn remove_index(&self, name: &str) -> Result<()> {
+        self.take_index(name, true).await
+    }
+
+    /// Detaches an index from the engine with a given name.
+    ///
+    /// This internally calls `Index.shutdown()` to cleanup writers but leaves
+    /// any persisted data in place, re-creating the index with the same
+    /// name will recover it.
+    pub async fn detach_index(&self, name: &str) -> Result<()> {
+        self.take_index(name, false).await
+    }
+
+    async fn take_index(&self, name: &str, purge: bool) -> Result<()> {
         let indexes = {
             let indexes = self.indexes.load();
 
             let mut indexes = indexes.as_ref().clone();
             if let Some(old) = indexes.remove(name) {
-                old.destroy().await?;
+                if purge {
+                    old.destroy().await?;
+                } else {
+                    old.shutdown().await?;
+                }
             };
 
             indexes
@@ -94,6 +296,156 @@ impl Engine {
         Ok(())
     }
 
+    /// Re-creates an existing index with a new declaration, copying over
+    /// all of it's documents.
+    ///
+    /// The existing index is read only while it's documents are copied,
+    /// once copied the new index takes it's place under the same name.
+    /// If the copy fails the existing index is left as is.
+    ///
+    /// The replaced index is returned without removing it's data, it should
+    /// only be destroyed once the new declaration has been persisted.
+    pub async fn recreate_index(&self, declaration: IndexDeclaration) -> Result<Index> {
+        let name = declaration.name().to_string();
+        let existing = self
+            .get_index(&name)
+            .ok_or_else(|| Error::msg("index does not exist."))?;
+
+        let was_read_only = existing.is_read_only();
+        existing.freeze().await?;
+
+        // The new index is created under a temporary name so it does not
+        // share the existing index's storage.
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let temp_name = format!("{}-recreated-{}", name, created);
+
+        let res = self
+            .copy_into_new(&existing, declaration.with_name(&temp_name))
+            .await;
+
+        if let Err(e) = res {
+            let _ = self.remove_index(&temp_name).await;
+            existing.set_read_only(was_read_only);
+            return Err(e);
+        }
+
+        {
+            let mut declarations = self.declarations.lock();
+            let recreated = declarations
+                .remove(&temp_name)
+                .ok_or_else(|| Error::msg("index does not exist."))?;
+
+            self.indexes.rcu(|indexes| {
+                let mut indexes = indexes.as_ref().clone();
+                if let Some(index) = indexes.remove(&temp_name) {
+                    indexes.insert(name.clone(), index);
+                }
+
+                indexes
+            });
+
+            declarations.insert(name.clone(), recreated.renamed(&name));
+        }
+
+        if !was_read_only {
+            if let Some(index) = self.get_index(&name) {
+                index.set_read_only(false);
+            }
+        }
+
+        Ok(existing)
+    }
+
+    /// Creates a new index from the declaration and copies every document
+    /// of the source index into it.
+    async fn copy_into_new(
+        &self,
+        source: &Index,
+        declaration: IndexDeclaration,
+    ) -> Result<()> {
+        let name = declaration.name().to_string();
+
+        // The copy only briefly exists alongside the source index so
+        // it does not count towards the index limit.
+        self.insert_index(declaration, false, false).await?;
+
+        let index = self
+            .get_index(&name)
+            .ok_or_else(|| Error::msg("index does not exist."))?;
+        let report = index.reindex_from(source, HashMap::new()).await?;
+
+        if report.failed() > 0 {
+            return Err(anyhow::anyhow!(
+                "{} documents could not be copied into the re-created index.",
+                report.failed(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Renames an index without re-creating it.
+    ///
+    /// The index keeps the storage derived from it's original name, this
+    /// mapping is kept by the declaration so the data is not migrated.
+    /// While the renamed index exists no other index can be created under
+    /// the old name, as it would open the same storage.
+    /// Logs from the running index will continue to use the old name until
+    /// it is next loaded.
+    pub fn rename_index(&self, old: &str, new: &str) -> Result<()> {
+        let mut declarations = self.declarations.lock();
+
+        if declarations.contains_key(new) || self.has_index(new) {
+            return Err(Error::msg("an index with the new name already exists."));
+        }
+
+        let declaration = declarations
+            .remove(old)
+            .ok_or_else(|| Error::msg("index does not exist."))?;
+
+        self.indexes.rcu(|indexes| {
+            let mut indexes = indexes.as_ref().clone();
+            if let Some(index) = indexes.remove(old) {
+                indexes.insert(new.to_string(), index);
+            }
+
+            indexes
+        });
+
+        declarations.insert(new.to_string(), declaration.renamed(new));
+
+        Ok(())
+    }
+
+    /// Replaces the field boosts of an index without re-creating it.
+    ///
+    /// The running index's queries use the new boosts straight away, the
+    /// previous boosts are returned so the change can be reverted.
+    pub fn update_boost_fields(
+        &self,
+        name: &str,
+        boost_fields: HashMap<String, f32>,
+    ) -> Result<HashMap<String, f32>> {
+        let mut declarations = self.declarations.lock();
+
+        let declaration = declarations
+            .get_mut(name)
+            .ok_or_else(|| Error::msg("index does not exist."))?;
+        let index = self
+            .get_index(name)
+            .ok_or_else(|| Error::msg("index does not exist."))?;
+
+        let updated = declaration.with_boost_fields(boost_fields.clone())?;
+        index.set_boost_fields(boost_fields)?;
+
+        let previous = std::mem::replace(declaration, updated);
+
+        Ok(previous.boost_fields().clone())
+    }
+
     /// Gets an index from the engine with the a given name.
     ///
     /// An error will be returned if the index does not exist.
@@ -104,11 +456,52 @@ impl Engine {
         Some(index.clone())
     }
 
+    /// Checks if the engine has an index loaded with the given name.
+    pub fn has_index(&self, index: &str) -> bool {
+        self.indexes.load().contains_key(index)
+    }
+
+    /// Gets the declaration of the index with the given name.
+    pub fn get_declaration(&self, name: &str) -> Option<IndexDeclaration> {
+        self.declarations.lock().get(name).cloned()
+    }
+
     pub fn get_all_indexes(&self) -> Vec<IndexDeclaration> {
         let guard = self.declarations.lock();
         guard.values().cloned().collect()
     }
 
+    /// Commits any pending changes of every index which is not read only.
+    ///
+    /// An index failing to commit does not stop the remaining indexes from
+    /// being committed, each failure is logged and returned together once
+    /// every index has been attempted.
+    pub async fn commit_all(&self) -> Result<()> {
+        let guard = self.indexes.load();
+
+        let mut failed = vec![];
+        for (name, index) in guard.iter() {
+            if index.is_read_only() {
+                continue;
+            }
+
+            if let Err(e) = index.commit().await {
+                error!(index = %name, "failed to commit index: {:?}", e);
+                failed.push(format!("{}: {}", name, e));
+            }
+        }
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::msg(format!(
+                "failed to commit {} indexes: {}",
+                failed.len(),
+                failed.join(", ")
+            )))
+        }
+    }
+
     pub async fn shutdown(&self) -> Result<()> {
         let guard = self.indexes.load();
         for (_, index) in guard.iter() {
@@ -118,3 +511,139 @@ impl Engine {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use search_index::structures::DocumentOptions;
+
+    use super::*;
+
+    fn declaration(fields: serde_json::Value) -> Result<IndexDeclaration> {
+        let declaration = serde_json::from_value(serde_json::json!({
+            "name": "test_recreate_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": fields,
+            "search_fields": ["title"],
+        }))?;
+
+        Ok(declaration)
+    }
+
+    async fn count_documents(index: &Index) -> Result<u64> {
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "*"},
+            },
+        }))?;
+        let results = serde_json::to_value(index.search(query).await?)?;
+
+        Ok(results["count"].as_u64().unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn test_load_legacy_index_expect_ok() -> Result<()> {
+        let engine = Engine::with_storage_defaults(
+            Some(StorageType::FileSystem),
+            Some("./index/test-storage".to_string()),
+        );
+
+        let mut legacy = serde_json::to_value(declaration(serde_json::json!({
+            "title": {"type": "text", "stored": true},
+        }))?)?;
+        legacy["name"] = serde_json::json!("test_load_legacy_index");
+        legacy["storage_type"] = serde_json::json!("filesystem");
+        legacy
+            .as_object_mut()
+            .expect("object")
+            .remove("storage_path");
+
+        engine.load_index(serde_json::from_value(legacy)?).await?;
+
+        let declaration = engine
+            .get_declaration("test_load_legacy_index")
+            .expect("get declaration");
+        assert_eq!(
+            serde_json::to_value(&declaration)?["storage_path"],
+            serde_json::json!("./index/index-storage"),
+        );
+
+        engine.remove_index("test_load_legacy_index").await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recreate_index_expect_ok() -> Result<()> {
+        let engine = Engine::default();
+        engine
+            .add_index(
+                declaration(serde_json::json!({
+                    "title": {"type": "text", "stored": true},
+                }))?,
+                false,
+            )
+            .await?;
+
+        let index = engine.get_index("test_recreate_index").expect("get index");
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title": "The Old Man and the Sea"},
+            {"title": "The Old Man and the Sea 2"},
+        ]))?;
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let recreated = declaration(serde_json::json!({
+            "title": {"type": "text", "stored": true},
+            "count": {"type": "f64", "stored": true},
+        }))?;
+        let old = engine.recreate_index(recreated).await?;
+
+        // The new declaration is in place before the old index is removed.
+        let declaration = engine
+            .get_declaration("test_recreate_index")
+            .expect("get declaration");
+        assert!(serde_json::to_value(&declaration)?["fields"]["count"].is_object());
+        assert_eq!(engine.num_indexes(), 1);
+        old.destroy().await?;
+
+        let index = engine.get_index("test_recreate_index").expect("get index");
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+        assert_eq!(count_documents(&index).await?, 2);
+        assert!(!index.is_read_only());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_index_if_not_exists_concurrently_expect_ok() -> Result<()> {
+        let engine = Engine::default();
+        let fields = serde_json::json!({
+            "title": {"type": "text", "stored": true},
+        });
+
+        let (first, second) = tokio::join!(
+            engine.add_index_if_not_exists(declaration(fields.clone())?),
+            engine.add_index_if_not_exists(declaration(fields)?),
+        );
+
+        // Only one of the requests creates the index.
+        assert!(first? ^ second?);
+        assert_eq!(engine.num_indexes(), 1);
+
+        engine.remove_index("test_recreate_index").await?;
+
+        Ok(())
+    }
+}
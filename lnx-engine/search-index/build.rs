@@ -17,29 +17,36 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn compress_stop_words() -> Result<()> {
-    if !path::Path::new("./datasets/stop_words").exists() {
-        fs::write("./_dist/stop_words", b"")?;
-        return Ok(());
-    }
+/// The languages with a bundled stop word list.
+///
+/// Each language is compressed separately so indexes can select which
+/// lists they use, any missing lists are left empty.
+const STOP_WORD_LANGUAGES: &[&str] = &["de", "en", "es", "fr", "he", "it", "ru", "zh"];
 
-    let mut data = vec![];
-    for entry in fs::read_dir("./datasets/stop_words")? {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_dir() {
+fn compress_stop_words() -> Result<()> {
+    // Older builds wrote every list into a single `_dist/stop_words` file,
+    // a new directory is used so existing checkouts don't clash with it.
+    let target_dir = path::Path::new("./_dist/stop_word_lists");
+    fs::create_dir_all(target_dir)?;
+
+    for language in STOP_WORD_LANGUAGES {
+        let source =
+            path::Path::new("./datasets/stop_words").join(format!("{}.txt", language));
+        let target = target_dir.join(language);
+
+        if !source.exists() {
+            fs::write(target, b"")?;
             continue;
         }
 
-        let mut file = fs::read(path)?;
-        data.append(&mut file);
-    }
+        let data = fs::read(source)?;
 
-    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
-    encoder.write_all(&data)?;
-    let data = encoder.finish()?;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&data)?;
+        let data = encoder.finish()?;
 
-    fs::write("./_dist/stop_words", &data)?;
+        fs::write(target, &data)?;
+    }
 
     Ok(())
 }
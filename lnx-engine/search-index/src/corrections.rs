@@ -1,4 +1,5 @@
 use std::fmt::{Debug, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use arc_swap::ArcSwap;
@@ -10,13 +11,25 @@ pub(crate) type SymSpellCorrectionManager = Arc<SymSpellManager>;
 /// The manager around the sym spell fuzzy searching system.
 pub(crate) struct SymSpellManager {
     sym: Arc<ArcSwap<SymSpell<AsciiStringStrategy>>>,
+    num_words: AtomicUsize,
 }
 
 impl SymSpellManager {
     pub(crate) fn new() -> Self {
         let sym = SymSpell::default();
         let sym = Arc::new(ArcSwap::from_pointee(sym));
-        Self { sym }
+        Self {
+            sym,
+            num_words: AtomicUsize::new(0),
+        }
+    }
+
+    /// If the frequency dictionary does not contain any words.
+    ///
+    /// This is the case until the frequencies are first adjusted, or if the
+    /// fuzzy search fields do not contain any terms.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.num_words.load(Ordering::Relaxed) == 0
     }
 
     /// Corrects the sentence with an edit distance of 1.
@@ -42,7 +55,8 @@ impl SymSpellManager {
                 .collect(),
         );
 
-        self.sym.store(Arc::from(symspell))
+        self.sym.store(Arc::from(symspell));
+        self.num_words.store(frequencies.len(), Ordering::Relaxed);
     }
 }
 
@@ -51,3 +65,15 @@ impl Debug for SymSpellManager {
         f.write_str("SymSpellManager")
     }
 }
+
+/// Gets the maximum edit distance a correction of the given word can have.
+///
+/// Short words are only allowed a single edit as two edits would match
+/// almost any other short word.
+pub(crate) fn max_edit_distance(word: &str) -> usize {
+    if word.chars().count() <= 4 {
+        1
+    } else {
+        2
+    }
+}
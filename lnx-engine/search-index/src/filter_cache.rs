@@ -0,0 +1,314 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use hashbrown::HashMap;
+use tantivy::query::{Explanation, Query, Scorer, Weight};
+use tantivy::{
+    DocId,
+    DocSet,
+    Score,
+    Searcher,
+    SegmentId,
+    SegmentReader,
+    TantivyError,
+    Term,
+    TERMINATED,
+};
+
+/// A size bounded cache of the documents matching filter queries.
+///
+/// Each filter is cached as a bitset per segment keyed on the filter's
+/// clause in the request, the whole cache is invalidated whenever a commit
+/// changes the index's set of segments.
+pub(crate) struct FilterCache {
+    /// The maximum total size of the cached bitsets in bytes.
+    capacity: usize,
+
+    state: Mutex<CacheState>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    /// The segments of the searcher the cached entries belong to.
+    segments: Vec<SegmentId>,
+
+    entries: HashMap<(SegmentId, Arc<str>), Arc<[u64]>>,
+
+    /// The cached entries, oldest first.
+    order: VecDeque<(SegmentId, Arc<str>)>,
+
+    /// The total size of the cached bitsets in bytes.
+    size: usize,
+}
+
+impl FilterCache {
+    /// Creates a new cache holding up to `capacity` bytes of bitsets.
+    ///
+    /// A capacity of `0` disables caching, filters are then evaluated
+    /// on every search.
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// Checks if the cache can hold any filters.
+    #[inline]
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Clears the cache if the searcher's segments differ from those the
+    /// cached entries belong to.
+    fn sync_segments(&self, searcher: &Searcher) {
+        let mut state = self.state.lock().unwrap();
+
+        let matches = state.segments.len() == searcher.segment_readers().len()
+            && searcher
+                .segment_readers()
+                .iter()
+                .zip(state.segments.iter())
+                .all(|(reader, segment)| reader.segment_id() == *segment);
+
+        if !matches {
+            *state = CacheState {
+                segments: searcher
+                    .segment_readers()
+                    .iter()
+                    .map(|reader| reader.segment_id())
+                    .collect(),
+                ..CacheState::default()
+            };
+        }
+    }
+
+    /// Removes every cached entry.
+    ///
+    /// This is used when the index's stop words or synonyms change as the
+    /// same filter clause may then match different documents.
+    pub(crate) fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        *state = CacheState {
+            segments: std::mem::take(&mut state.segments),
+            ..CacheState::default()
+        };
+    }
+
+    fn get(&self, key: &(SegmentId, Arc<str>)) -> Option<Arc<[u64]>> {
+        self.state.lock().unwrap().entries.get(key).cloned()
+    }
+
+    /// Caches the bitset, evicting the oldest entries to make room.
+    ///
+    /// Bitsets from segments no longer searched or larger than the whole
+    /// cache are not stored.
+    fn insert(&self, key: (SegmentId, Arc<str>), bits: Arc<[u64]>) {
+        let size = bits.len() * std::mem::size_of::<u64>();
+        if size > self.capacity {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if !state.segments.contains(&key.0) || state.entries.contains_key(&key) {
+            return;
+        }
+
+        while state.size + size > self.capacity {
+            let oldest = match state.order.pop_front() {
+                Some(oldest) => oldest,
+                None => break,
+            };
+
+            if let Some(evicted) = state.entries.remove(&oldest) {
+                state.size -= evicted.len() * std::mem::size_of::<u64>();
+            }
+        }
+
+        state.size += size;
+        state.order.push_back(key.clone());
+        state.entries.insert(key, bits);
+    }
+}
+
+/// A query matching the same documents as the inner query without
+/// contributing to their score.
+///
+/// The matching documents of each segment are read from the filter cache,
+/// only being evaluated by the inner query when not already cached.
+pub(crate) struct CachedFilterQuery {
+    /// The normalized filter clause the cache is keyed on.
+    key: Arc<str>,
+    inner: Box<dyn Query>,
+    cache: Arc<FilterCache>,
+}
+
+impl CachedFilterQuery {
+    pub(crate) fn new(
+        key: Arc<str>,
+        inner: Box<dyn Query>,
+        cache: Arc<FilterCache>,
+    ) -> Self {
+        Self { key, inner, cache }
+    }
+}
+
+impl Clone for CachedFilterQuery {
+    fn clone(&self) -> Self {
+        Self {
+            key: self.key.clone(),
+            inner: self.inner.box_clone(),
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+impl std::fmt::Debug for CachedFilterQuery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CachedFilter({})", self.key)
+    }
+}
+
+impl Query for CachedFilterQuery {
+    fn weight(
+        &self,
+        searcher: &Searcher,
+        _scoring_enabled: bool,
+    ) -> tantivy::Result<Box<dyn Weight>> {
+        self.cache.sync_segments(searcher);
+
+        Ok(Box::new(CachedFilterWeight {
+            key: self.key.clone(),
+            inner: self.inner.weight(searcher, false)?,
+            cache: self.cache.clone(),
+        }))
+    }
+
+    fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
+        self.inner.query_terms(terms)
+    }
+}
+
+struct CachedFilterWeight {
+    key: Arc<str>,
+    inner: Box<dyn Weight>,
+    cache: Arc<FilterCache>,
+}
+
+impl CachedFilterWeight {
+    /// Gets the bitset of the segment's documents matching the filter.
+    fn matching_docs(&self, reader: &SegmentReader) -> tantivy::Result<Arc<[u64]>> {
+        let key = (reader.segment_id(), self.key.clone());
+        if let Some(bits) = self.cache.get(&key) {
+            return Ok(bits);
+        }
+
+        let mut bits = vec![0u64; (reader.max_doc() as usize + 63) / 64];
+        let mut scorer = self.inner.scorer(reader, 1.0)?;
+        let mut doc = scorer.doc();
+        while doc != TERMINATED {
+            bits[doc as usize / 64] |= 1 << (doc % 64);
+            doc = scorer.advance();
+        }
+
+        let bits: Arc<[u64]> = Arc::from(bits);
+        self.cache.insert(key, bits.clone());
+
+        Ok(bits)
+    }
+}
+
+impl Weight for CachedFilterWeight {
+    fn scorer(
+        &self,
+        reader: &SegmentReader,
+        _boost: Score,
+    ) -> tantivy::Result<Box<dyn Scorer>> {
+        let bits = self.matching_docs(reader)?;
+        Ok(Box::new(BitSetScorer::new(bits)))
+    }
+
+    fn explain(
+        &self,
+        reader: &SegmentReader,
+        doc: DocId,
+    ) -> tantivy::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Document #({}) does not match",
+                doc
+            )));
+        }
+
+        Ok(Explanation::new("CachedFilter", 0.0))
+    }
+}
+
+/// Iterates over the documents set in a bitset, scoring each with `0`.
+struct BitSetScorer {
+    bits: Arc<[u64]>,
+    doc: DocId,
+}
+
+impl BitSetScorer {
+    fn new(bits: Arc<[u64]>) -> Self {
+        let mut scorer = Self { bits, doc: 0 };
+        scorer.doc = scorer.next_from(0);
+        scorer
+    }
+
+    /// Gets the first set document at or after `target`.
+    fn next_from(&self, target: DocId) -> DocId {
+        let mut word = target as usize / 64;
+        if word >= self.bits.len() {
+            return TERMINATED;
+        }
+
+        let mut current = self.bits[word] & (!0u64 << (target % 64));
+        loop {
+            if current != 0 {
+                return (word * 64) as DocId + current.trailing_zeros();
+            }
+
+            word += 1;
+            if word >= self.bits.len() {
+                return TERMINATED;
+            }
+
+            current = self.bits[word];
+        }
+    }
+}
+
+impl DocSet for BitSetScorer {
+    fn advance(&mut self) -> DocId {
+        if self.doc != TERMINATED {
+            self.doc = self.next_from(self.doc + 1);
+        }
+
+        self.doc
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        if self.doc != TERMINATED && self.doc < target {
+            self.doc = self.next_from(target);
+        }
+
+        self.doc
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.bits.iter().map(|word| word.count_ones()).sum()
+    }
+}
+
+impl Scorer for BitSetScorer {
+    fn score(&mut self) -> Score {
+        0.0
+    }
+}
@@ -0,0 +1,333 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::convert::TryInto;
+
+use anyhow::{anyhow, Error, Result};
+use serde::Deserialize;
+use tantivy::collector::{Collector, SegmentCollector};
+use tantivy::fastfield::MultiValuedFastFieldReader;
+use tantivy::schema::{Field, Schema};
+use tantivy::{DocAddress, DocId, Score, SegmentOrdinal, SegmentReader};
+
+use crate::schema::{FieldDeclaration, SchemaContext};
+use crate::structures::{DocumentValue, DocumentValueOptions};
+
+/// The mean radius of the earth in metres.
+const EARTH_RADIUS_METRES: f64 = 6_371_008.8;
+
+/// Filters results to documents within a given distance of a point.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct GeoFilter {
+    /// The geo field to filter by.
+    pub(crate) field: String,
+
+    /// The latitude of the centre point.
+    pub(crate) lat: f64,
+
+    /// The longitude of the centre point.
+    pub(crate) lon: f64,
+
+    /// The maximum distance from the centre point in metres.
+    pub(crate) radius: f64,
+}
+
+/// Checks that the given latitude and longitude are within range.
+pub(crate) fn validate_point(lat: f64, lon: f64) -> Result<()> {
+    if !(-90.0..=90.0).contains(&lat) {
+        return Err(anyhow!("latitude must be between -90 and 90, got {}", lat));
+    }
+
+    if !(-180.0..=180.0).contains(&lon) {
+        return Err(anyhow!(
+            "longitude must be between -180 and 180, got {}",
+            lon
+        ));
+    }
+
+    Ok(())
+}
+
+/// Parses a `[lat, lon]` pair from the given document values.
+pub(crate) fn parse_point(key: &str, data: DocumentValueOptions) -> Result<(f64, f64)> {
+    let mut values = match data {
+        DocumentValueOptions::Many(values) if values.len() == 2 => values.into_iter(),
+        _ => {
+            return Err(anyhow!(
+                "geo field {:?} must be given as a `[lat, lon]` pair",
+                key
+            ))
+        },
+    };
+
+    let lat = value_as_coordinate(values.next().expect("get lat"))?;
+    let lon = value_as_coordinate(values.next().expect("get lon"))?;
+    validate_point(lat, lon)?;
+
+    Ok((lat, lon))
+}
+
+/// Coordinates are commonly given as whole numbers so these are accepted
+/// alongside floats.
+fn value_as_coordinate(value: DocumentValue) -> Result<f64> {
+    match value {
+        DocumentValue::I64(v) => Ok(v as f64),
+        DocumentValue::U64(v) => Ok(v as f64),
+        other => other.try_into(),
+    }
+}
+
+/// Gets the distance between two points in metres using the haversine formula.
+fn haversine_distance((lat1, lon1): (f64, f64), (lat2, lon2): (f64, f64)) -> f64 {
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lon = (lon2 - lon1).to_radians();
+
+    let a = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos()
+            * lat2.to_radians().cos()
+            * (d_lon / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_METRES * a.sqrt().asin()
+}
+
+fn cmp_distance(a: &(f64, DocAddress), b: &(f64, DocAddress)) -> Ordering {
+    a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal)
+}
+
+/// The latitude and longitude bounds containing every point within the
+/// radius of a centre point.
+///
+/// This is used to skip the distance calculation for points which are
+/// clearly outside of the radius.
+#[derive(Debug, Copy, Clone)]
+struct BoundingBox {
+    /// The maximum difference in latitude from the centre in degrees.
+    lat_delta: f64,
+
+    /// The maximum difference in longitude from the centre in degrees.
+    ///
+    /// This is `None` if the radius covers a pole as then every longitude
+    /// is within the radius.
+    lon_delta: Option<f64>,
+}
+
+impl BoundingBox {
+    fn new((lat, _): (f64, f64), radius: f64) -> Self {
+        let angular_radius = radius / EARTH_RADIUS_METRES;
+        let lat_delta = angular_radius.to_degrees();
+
+        let lon_delta = if lat.abs() + lat_delta >= 90.0 {
+            None
+        } else {
+            let delta = (angular_radius.sin() / lat.to_radians().cos()).asin();
+            Some(delta.to_degrees())
+        };
+
+        Self {
+            lat_delta,
+            lon_delta,
+        }
+    }
+
+    fn may_contain(&self, (lat1, lon1): (f64, f64), (lat2, lon2): (f64, f64)) -> bool {
+        if (lat1 - lat2).abs() > self.lat_delta {
+            return false;
+        }
+
+        match self.lon_delta {
+            None => true,
+            Some(delta) => {
+                // The difference wraps around the antimeridian.
+                let diff = (lon1 - lon2).abs();
+                diff.min(360.0 - diff) <= delta
+            },
+        }
+    }
+}
+
+/// A hit ordered by its distance from the centre point.
+struct DistanceHit(f64, DocAddress);
+
+impl PartialEq for DistanceHit {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for DistanceHit {}
+
+impl PartialOrd for DistanceHit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DistanceHit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        cmp_distance(&(self.0, self.1), &(other.0, other.1))
+    }
+}
+
+/// Gets the geo field to filter results by.
+fn get_geo_field(ctx: &SchemaContext, schema: &Schema, name: &str) -> Result<Field> {
+    match ctx.fields().get(name) {
+        Some(FieldDeclaration::Geo { .. }) => {},
+        Some(_) => return Err(anyhow!("field {:?} is not a geo field", name)),
+        None => return Err(anyhow!("no field exists with name: {:?}", name)),
+    }
+
+    schema
+        .get_field(name)
+        .ok_or_else(|| anyhow!("no field exists with name: {:?}", name))
+}
+
+/// Collects the documents within the radius of the filter ordered by their
+/// distance from the centre point, closest first.
+///
+/// The fruit is the page of hits described by the offset and limit along
+/// with the total amount of documents within the radius.
+pub(crate) struct GeoDistanceCollector {
+    field: Field,
+    centre: (f64, f64),
+    radius: f64,
+    offset: usize,
+    limit: usize,
+}
+
+impl GeoDistanceCollector {
+    pub(crate) fn create(
+        ctx: &SchemaContext,
+        schema: &Schema,
+        filter: &GeoFilter,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Self> {
+        validate_point(filter.lat, filter.lon)?;
+
+        if filter.radius.is_nan() || filter.radius < 0.0 {
+            return Err(Error::msg("geo filter radius must be a positive distance"));
+        }
+
+        Ok(Self {
+            field: get_geo_field(ctx, schema, &filter.field)?,
+            centre: (filter.lat, filter.lon),
+            radius: filter.radius,
+            offset,
+            limit,
+        })
+    }
+}
+
+impl Collector for GeoDistanceCollector {
+    type Fruit = (Vec<(f64, DocAddress)>, usize);
+    type Child = GeoDistanceSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        let reader = segment.fast_fields().f64s(self.field)?;
+
+        Ok(GeoDistanceSegmentCollector {
+            segment_ord: segment_local_id,
+            reader,
+            centre: self.centre,
+            radius: self.radius,
+            bounds: BoundingBox::new(self.centre, self.radius),
+            keep: self.offset.saturating_add(self.limit),
+            buffer: Vec::with_capacity(2),
+            hits: BinaryHeap::new(),
+            count: 0,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<(Vec<(f64, DocAddress)>, usize)>,
+    ) -> tantivy::Result<Self::Fruit> {
+        let mut count = 0;
+        let mut hits = vec![];
+        for (segment_hits, segment_count) in segment_fruits {
+            count += segment_count;
+            hits.extend(segment_hits);
+        }
+
+        hits.sort_by(cmp_distance);
+
+        let hits = hits
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit)
+            .collect();
+
+        Ok((hits, count))
+    }
+}
+
+pub(crate) struct GeoDistanceSegmentCollector {
+    segment_ord: SegmentOrdinal,
+    reader: MultiValuedFastFieldReader<f64>,
+    centre: (f64, f64),
+    radius: f64,
+    bounds: BoundingBox,
+    keep: usize,
+    buffer: Vec<f64>,
+
+    /// The closest hits of the segment with the furthest hit at the top so
+    /// only `keep` hits are held at a time.
+    hits: BinaryHeap<DistanceHit>,
+    count: usize,
+}
+
+impl SegmentCollector for GeoDistanceSegmentCollector {
+    type Fruit = (Vec<(f64, DocAddress)>, usize);
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        self.buffer.clear();
+        self.reader.get_vals(doc, &mut self.buffer);
+
+        // Geo points are always stored as a `[lat, lon]` pair.
+        let point = match self.buffer.as_slice() {
+            [lat, lon] => (*lat, *lon),
+            _ => return,
+        };
+
+        if !self.bounds.may_contain(self.centre, point) {
+            return;
+        }
+
+        let distance = haversine_distance(self.centre, point);
+        if distance > self.radius {
+            return;
+        }
+
+        self.count += 1;
+
+        if self.hits.len() < self.keep {
+            self.hits.push(DistanceHit(
+                distance,
+                DocAddress::new(self.segment_ord, doc),
+            ));
+        } else if let Some(mut furthest) = self.hits.peek_mut() {
+            if distance < furthest.0 {
+                *furthest =
+                    DistanceHit(distance, DocAddress::new(self.segment_ord, doc));
+            }
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        let hits = self
+            .hits
+            .into_sorted_vec()
+            .into_iter()
+            .map(|DistanceHit(distance, address)| (distance, address))
+            .collect();
+
+        (hits, self.count)
+    }
+}
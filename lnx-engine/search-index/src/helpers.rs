@@ -47,3 +47,16 @@ pub fn cr32_hash(v: impl Hash) -> u64 {
 
     hasher.finish()
 }
+
+/// Hashes the given text into a document id.
+///
+/// This uses FNV-1a so ids stay the same between releases, unlike the
+/// standard library's hasher.
+pub(crate) fn id_hash(v: &str) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    v.bytes().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(PRIME)
+    })
+}
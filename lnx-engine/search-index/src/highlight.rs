@@ -0,0 +1,145 @@
+use anyhow::{anyhow, Error, Result};
+use hashbrown::HashMap;
+use tantivy::query::Query;
+use tantivy::schema::{Field, FieldType, Schema, Value};
+use tantivy::{Searcher, Snippet, SnippetGenerator};
+
+use crate::schema::SchemaContext;
+use crate::structures::{CompliantDocumentValue, DocumentHit};
+
+/// The tag inserted before each highlighted term by default.
+pub(crate) const DEFAULT_PRE_TAG: &str = "<em>";
+
+/// The tag inserted after each highlighted term by default.
+pub(crate) const DEFAULT_POST_TAG: &str = "</em>";
+
+/// Produces highlighted snippets of the terms matching a query.
+#[derive(Debug, Clone)]
+pub(crate) struct Highlighter {
+    /// The stored text fields to highlight.
+    pub(crate) fields: Vec<String>,
+
+    /// The tag inserted before each highlighted term.
+    pub(crate) pre_tag: String,
+
+    /// The tag inserted after each highlighted term.
+    pub(crate) post_tag: String,
+
+    /// The maximum amount of snippets returned for each field.
+    ///
+    /// Each value of a multi-value field produces its own snippet.
+    pub(crate) max_snippets_per_field: usize,
+}
+
+impl Highlighter {
+    /// Adds the highlighted snippets of each field to the given hits.
+    ///
+    /// Only values which contain a matching term produce a snippet.
+    pub(crate) fn apply(
+        &self,
+        ctx: &SchemaContext,
+        schema: &Schema,
+        searcher: &Searcher,
+        query: &dyn Query,
+        hits: &mut [DocumentHit],
+    ) -> Result<()> {
+        if self.max_snippets_per_field == 0 {
+            return Err(Error::msg("max snippets per field must be at least 1."));
+        }
+
+        let mut generators = Vec::with_capacity(self.fields.len());
+        for name in self.fields.iter() {
+            let name = ctx.resolve_field(name);
+            let field = get_highlight_field(schema, name)?;
+            let generator = SnippetGenerator::create(searcher, query, field)?;
+
+            generators.push((name, generator));
+        }
+
+        for hit in hits.iter_mut() {
+            let mut highlights = HashMap::with_capacity(generators.len());
+            for (name, generator) in generators.iter() {
+                let values = match hit.doc.get(*name) {
+                    Some(Some(CompliantDocumentValue::Single(value))) => {
+                        std::slice::from_ref(value)
+                    },
+                    Some(Some(CompliantDocumentValue::Multi(values))) => {
+                        values.as_slice()
+                    },
+                    _ => continue,
+                };
+
+                let snippets: Vec<String> = values
+                    .iter()
+                    .filter_map(|value| match value {
+                        Value::Str(text) => Some(generator.snippet(text)),
+                        _ => None,
+                    })
+                    .filter(|snippet| !snippet.highlighted().is_empty())
+                    .take(self.max_snippets_per_field)
+                    .map(|snippet| self.render(&snippet))
+                    .collect();
+
+                if !snippets.is_empty() {
+                    highlights.insert(name.to_string(), snippets);
+                }
+            }
+
+            hit.highlights = Some(highlights);
+        }
+
+        Ok(())
+    }
+
+    /// Renders the snippet wrapping each highlighted term in the tags.
+    ///
+    /// The rest of the fragment is html escaped like tantivy's own renderer.
+    fn render(&self, snippet: &Snippet) -> String {
+        let fragment = snippet.fragment();
+
+        let mut rendered = String::with_capacity(fragment.len());
+        let mut start = 0;
+        for section in snippet.highlighted() {
+            let (from, to) = section.bounds();
+
+            escape_into(&fragment[start..from], &mut rendered);
+            rendered.push_str(&self.pre_tag);
+            escape_into(&fragment[from..to], &mut rendered);
+            rendered.push_str(&self.post_tag);
+
+            start = to;
+        }
+        escape_into(&fragment[start..], &mut rendered);
+
+        rendered
+    }
+}
+
+/// Gets the field to highlight, this must be a stored text field.
+fn get_highlight_field(schema: &Schema, name: &str) -> Result<Field> {
+    let field = schema
+        .get_field(name)
+        .ok_or_else(|| anyhow!("no field exists with name: {:?}", name))?;
+
+    let entry = schema.get_field_entry(field);
+    match entry.field_type() {
+        FieldType::Str(_) if entry.is_stored() => Ok(field),
+        _ => Err(anyhow!(
+            "highlight field {:?} must be a stored text or string field",
+            name
+        )),
+    }
+}
+
+fn escape_into(text: &str, buffer: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => buffer.push_str("&amp;"),
+            '<' => buffer.push_str("&lt;"),
+            '>' => buffer.push_str("&gt;"),
+            '"' => buffer.push_str("&quot;"),
+            '\'' => buffer.push_str("&#x27;"),
+            _ => buffer.push(c),
+        }
+    }
+}
@@ -1,20 +1,50 @@
 use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use hashbrown::HashMap;
+use tantivy::Score;
+use tokio::sync::mpsc;
 
-use crate::query::{DocumentId, Occur, QueryData, QuerySelector};
-use crate::reader::{QueryPayload, QueryResults};
+use crate::query::{DocumentId, Occur, QueryData, QueryExplanation, QuerySelector};
+use crate::reader::{ExportLimits, QueryPayload, QueryResults};
 use crate::structures::{
+    get_boosted_search_fields,
     DocumentHit,
     DocumentOptions,
+    DocumentPayload,
     DocumentValueOptions,
     IndexContext,
+    IndexDiagnostics,
+    RankingReport,
+    ReindexFailure,
+    ReindexReport,
+    ReplacedEntries,
+    MAX_REPORTED_REINDEX_FAILURES,
 };
-use crate::writer::WriterOp;
+use crate::suggest::{SpellCheck, Suggestion};
+use crate::writer::{DocumentNotFound, WriterOp};
 use crate::{reader, writer};
 
+/// Returned when a write operation is attempted on an index which has been
+/// marked as read only.
+///
+/// Searches are unaffected, writes succeed again once the index is no
+/// longer read only.
+#[derive(Debug)]
+pub struct IndexReadOnly;
+
+impl fmt::Display for IndexReadOnly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the index is currently read only")
+    }
+}
+
+impl std::error::Error for IndexReadOnly {}
+
 #[derive(Clone)]
 pub struct Index(Arc<InternalIndex>);
 
@@ -25,6 +55,33 @@ impl Index {
         Ok(Self(Arc::new(index)))
     }
 
+    /// Marks the index as read only or writable.
+    ///
+    /// While read only, any operations which add, delete or commit documents
+    /// return an `IndexReadOnly` error without being submitted to the writer.
+    /// Searches are unaffected.
+    pub fn set_read_only(&self, read_only: bool) {
+        self.0.set_read_only(read_only)
+    }
+
+    /// Is the index currently read only.
+    pub fn is_read_only(&self) -> bool {
+        self.0.is_read_only()
+    }
+
+    /// If searches of the index should be logged.
+    ///
+    /// Returns `None` if the index uses the server's default.
+    pub fn log_searches(&self) -> Option<bool> {
+        self.0.log_searches()
+    }
+
+    /// Marks the index as read only and commits any changes made before
+    /// it was marked.
+    pub async fn freeze(&self) -> Result<()> {
+        self.0.freeze().await
+    }
+
     /// Commits any changes to the index since the last commit.
     pub async fn commit(&self) -> Result<()> {
         self.0.commit().await
@@ -35,11 +92,38 @@ impl Index {
         self.0.rollback().await
     }
 
+    /// Replaces the boost factor of the index's search fields.
+    ///
+    /// Only text and string fields can be boosted, fields which are left
+    /// out are no longer boosted. This only applies to the running index,
+    /// the declaration must be updated separately to persist the change.
+    pub fn set_boost_fields(&self, boost_fields: HashMap<String, Score>) -> Result<()> {
+        self.0.set_boost_fields(boost_fields)
+    }
+
+    /// Reloads the index's searchers so they see the latest committed data.
+    ///
+    /// Searchers only reload on the index's own commits, this should be
+    /// called after the index data has been changed externally, e.g. when
+    /// segments are copied in by another process, otherwise searches will
+    /// continue to return stale results.
+    pub fn reload(&self) -> Result<()> {
+        self.0.reload()
+    }
+
     /// Gets a list of suggested corrections based off of the index corpus.
     pub fn get_corrected_query_hint(&self, query: &str) -> String {
         self.0.get_corrected_query_hint(query)
     }
 
+    /// Builds the query of the given payload without executing it.
+    ///
+    /// This returns the built query's representation and the fields it
+    /// searches, useful for debugging how a query has been interpreted.
+    pub async fn explain_query(&self, qry: QueryPayload) -> Result<QueryExplanation> {
+        self.0.explain_query(qry).await
+    }
+
     /// Search the index for the given query.
     ///
     /// This returns a set of results ordered by their relevance according to
@@ -48,18 +132,109 @@ impl Index {
         self.0.search(qry).await
     }
 
+    /// Search the index for the given query streaming the hits in chunks
+    /// as they are retrieved.
+    ///
+    /// Hits are always ordered by relevance, dropping the returned receiver
+    /// stops the search.
+    pub async fn search_stream(
+        &self,
+        qry: QueryPayload,
+    ) -> Result<mpsc::Receiver<Result<Vec<DocumentHit>>>> {
+        self.0.search_stream(qry).await
+    }
+
     /// Get a single document via it's given id.
-    pub async fn get_document(&self, doc_id: DocumentId) -> Result<DocumentHit> {
+    ///
+    /// If no document exists with the given id `None` is returned.
+    pub async fn get_document(&self, doc_id: DocumentId) -> Result<Option<DocumentHit>> {
         self.0.get_document(doc_id).await
     }
 
+    /// Get a set of documents via their given ids.
+    ///
+    /// The documents are returned in the order of the given ids with `None`
+    /// in place of any documents which do not exist.
+    pub async fn get_documents(
+        &self,
+        doc_ids: Vec<DocumentId>,
+    ) -> Result<Vec<Option<DocumentHit>>> {
+        self.0.get_documents(doc_ids).await
+    }
+
+    /// Gets up to `limit` terms of the given field starting with the prefix,
+    /// ranked by the amount of documents containing them.
+    pub async fn suggest(
+        &self,
+        field: String,
+        prefix: String,
+        limit: usize,
+    ) -> Result<Vec<Suggestion>> {
+        self.0.suggest(field, prefix, limit).await
+    }
+
+    /// Corrects each word of the query with the closest indexed term of the
+    /// given field, weighted by the amount of documents containing it.
+    pub async fn spell_check(&self, field: String, query: String) -> Result<SpellCheck> {
+        self.0.spell_check(field, query).await
+    }
+
+    /// Exports every document in the index in chunks of `chunk_size`,
+    /// optionally limited to the segment with the given id.
+    ///
+    /// Dropping the returned receiver stops the export, the export also
+    /// stops early closing the receiver once any of the limits are reached.
+    pub fn export_documents(
+        &self,
+        chunk_size: usize,
+        segment: Option<String>,
+        limits: ExportLimits,
+    ) -> mpsc::Receiver<Result<Vec<DocumentHit>>> {
+        self.0.export_documents(chunk_size, segment, limits)
+    }
+
+    /// Gets the segment level details of the index and the depth of the
+    /// writer's queue.
+    ///
+    /// This is useful for tracking fragmentation and the amount of pending
+    /// writes.
+    pub async fn diagnostics(&self) -> Result<IndexDiagnostics> {
+        self.0.diagnostics().await
+    }
+
+    /// Gets the fields searched by queries along with their boosts and
+    /// capabilities.
+    ///
+    /// This reflects the boosts the running index is using, including any
+    /// changes made since it was created.
+    pub fn ranking(&self) -> RankingReport {
+        self.0.ranking()
+    }
+
     /// Adds one or more documents to the index.
     ///
     /// This function is semi-asynchronous in the sense that there is a buffer of
     /// 20 tasks that can be submitted to the writer before the extra pending tasks
     /// must wait in order to then submit their operation to the queue.
-    pub async fn add_documents(&self, doc_opts: DocumentOptions) -> Result<()> {
-        self.0.add_documents(doc_opts).await
+    ///
+    /// The amount of values too large to be indexed as a single term, which
+    /// were truncated or skipped according to the index's `oversized_terms`,
+    /// is returned.
+    pub async fn add_documents(&self, doc_opts: DocumentOptions) -> Result<usize> {
+        self.0.add_documents(doc_opts, None).await
+    }
+
+    /// Adds one or more documents to the index.
+    ///
+    /// This behaves the same as `Index::add_documents` except if the writer queue
+    /// remains full for longer than `block_timeout` a `WriterQueueFull` error is
+    /// returned rather than waiting indefinitely.
+    pub async fn add_documents_with_timeout(
+        &self,
+        doc_opts: DocumentOptions,
+        block_timeout: Option<Duration>,
+    ) -> Result<usize> {
+        self.0.add_documents(doc_opts, block_timeout).await
     }
 
     /// Deletes all documents from the index matching a given term(s).
@@ -75,6 +250,19 @@ impl Index {
         self.0.delete_document(document_id).await
     }
 
+    /// Updates some of the fields of an existing document.
+    ///
+    /// The latest version of the document, including any changes since the
+    /// last commit, is merged with the given fields and re-indexed under the
+    /// same id. Returns `false` if no document exists with the given id.
+    pub async fn update_document(
+        &self,
+        document_id: DocumentId,
+        fields: DocumentPayload,
+    ) -> Result<bool> {
+        self.0.update_document(document_id, fields).await
+    }
+
     /// Deletes all documents from the index matching a given term(s).
     pub async fn delete_documents_by_query(&self, qry: QueryPayload) -> Result<usize> {
         self.0.delete_by_query(qry).await
@@ -85,6 +273,20 @@ impl Index {
         self.0.clear_documents().await
     }
 
+    /// Copies every document of the source index into this index, renaming
+    /// any fields with a mapping.
+    ///
+    /// Documents which do not match this index's schema are skipped and
+    /// reported rather than stopping the copy, the copied documents are
+    /// committed once the whole source has been read.
+    pub async fn reindex_from(
+        &self,
+        source: &Index,
+        field_mappings: HashMap<String, String>,
+    ) -> Result<ReindexReport> {
+        self.0.reindex_from(source, field_mappings).await
+    }
+
     /// Adds a set of stop words to the indexes' stop word manager.
     ///
     /// This function is semi-asynchronous in the sense that there is a buffer of
@@ -113,6 +315,17 @@ impl Index {
         self.0.clear_stop_words().await
     }
 
+    /// Replaces the index's custom stop words with the given set.
+    ///
+    /// The new set is applied in a single writer operation, returning how
+    /// many words were added and removed compared to the previous set.
+    pub async fn replace_stop_words(
+        &self,
+        words: Vec<String>,
+    ) -> Result<ReplacedEntries> {
+        self.0.replace_stop_words(words).await
+    }
+
     /// Adds a set of synonyms to the indexes' stop word manager.
     ///
     /// This function is semi-asynchronous in the sense that there is a buffer of
@@ -141,6 +354,18 @@ impl Index {
         self.0.clear_synonyms().await
     }
 
+    /// Replaces all of the index's synonyms with the given relations.
+    ///
+    /// The new relations are applied in a single writer operation, returning
+    /// how many words gained or lost all of their synonyms compared to the
+    /// previous dictionary.
+    pub async fn replace_synonyms(
+        &self,
+        relations: Vec<String>,
+    ) -> Result<ReplacedEntries> {
+        self.0.replace_synonyms(relations).await
+    }
+
     /// Shuts the index down waiting for all writer threads to finish.
     pub async fn shutdown(&self) -> Result<()> {
         self.0.shutdown().await
@@ -152,6 +377,10 @@ impl Index {
     }
 }
 
+/// The amount of documents read from the source index at a time when
+/// reindexing.
+const REINDEX_CHUNK_SIZE: usize = 500;
+
 struct InternalIndex {
     /// The name of the index.
     _ctx: IndexContext,
@@ -161,6 +390,9 @@ struct InternalIndex {
 
     /// A writer actor to handle the index writer.
     writer: writer::Writer,
+
+    /// If the index should reject any document writes.
+    read_only: AtomicBool,
 }
 
 impl InternalIndex {
@@ -177,11 +409,47 @@ impl InternalIndex {
             _ctx: ctx,
             reader,
             writer,
+            read_only: AtomicBool::new(false),
         })
     }
 
+    /// Marks the index as read only or writable.
+    fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Relaxed);
+    }
+
+    /// Is the index currently read only.
+    fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::Relaxed)
+    }
+
+    /// If searches of the index should be logged.
+    fn log_searches(&self) -> Option<bool> {
+        self._ctx.log_searches
+    }
+
+    /// Returns an `IndexReadOnly` error if the index is read only.
+    fn ensure_writable(&self) -> Result<()> {
+        if self.is_read_only() {
+            return Err(IndexReadOnly.into());
+        }
+
+        Ok(())
+    }
+
     /// Commits any changes to the index since the last commit.
     async fn commit(&self) -> Result<()> {
+        self.ensure_writable()?;
+        self.writer.send_op(WriterOp::Commit).await
+    }
+
+    /// Marks the index as read only and commits any changes made before
+    /// it was marked.
+    ///
+    /// The writer applies operations in order so every write accepted
+    /// before the index was marked is included in the commit.
+    async fn freeze(&self) -> Result<()> {
+        self.set_read_only(true);
         self.writer.send_op(WriterOp::Commit).await
     }
 
@@ -190,59 +458,296 @@ impl InternalIndex {
         self.writer.send_op(WriterOp::Rollback).await
     }
 
+    /// Replaces the boost factor of the index's search fields.
+    fn set_boost_fields(&self, boost_fields: HashMap<String, Score>) -> Result<()> {
+        let mut schema_ctx = self._ctx.schema_ctx.clone();
+        schema_ctx.set_boost_fields(boost_fields)?;
+
+        let (default_fields, fuzzy_fields) =
+            get_boosted_search_fields(&schema_ctx, &self._ctx.schema());
+        self.reader.set_search_fields(default_fields, fuzzy_fields);
+
+        Ok(())
+    }
+
+    /// Reloads the index's searchers so they see the latest committed data.
+    fn reload(&self) -> Result<()> {
+        self.reader.force_reload()
+    }
+
     /// Gets a list of suggested corrections based off of the index corpus.
     pub(crate) fn get_corrected_query_hint(&self, query: &str) -> String {
         self.reader.get_corrected_query_hint(query)
     }
 
+    /// Builds the query of the given payload without executing it.
+    async fn explain_query(&self, qry: QueryPayload) -> Result<QueryExplanation> {
+        self.reader.explain_query(qry).await
+    }
+
     /// Search the index for the given query.
     ///
     /// This returns a set of results ordered by their relevance according to
     /// the order or the score.
+    #[instrument(name = "index-search", skip_all, fields(index = %self._ctx.name))]
     async fn search(&self, qry: QueryPayload) -> Result<QueryResults> {
         self.reader.search(qry).await
     }
 
+    /// Search the index for the given query streaming the hits in chunks.
+    #[instrument(name = "index-search-stream", skip_all, fields(index = %self._ctx.name))]
+    async fn search_stream(
+        &self,
+        qry: QueryPayload,
+    ) -> Result<mpsc::Receiver<Result<Vec<DocumentHit>>>> {
+        self.reader.search_stream(qry).await
+    }
+
+    /// Gets the segment level details of the index and the depth of the
+    /// writer's queue.
+    async fn diagnostics(&self) -> Result<IndexDiagnostics> {
+        Ok(IndexDiagnostics {
+            segments: self.reader.segment_diagnostics().await?,
+            queued_operations: self.writer.queued_operations(),
+            blocked_operations: self.writer.blocked_operations(),
+        })
+    }
+
+    /// Gets the fields searched by queries along with their boosts and
+    /// capabilities.
+    fn ranking(&self) -> RankingReport {
+        let (default_fields, fuzzy_fields) = self.reader.search_fields();
+
+        RankingReport::new(
+            &self._ctx.schema_ctx,
+            &self._ctx.schema(),
+            &default_fields,
+            &fuzzy_fields,
+        )
+    }
+
     /// Get a single document via it's given id.
-    async fn get_document(&self, doc_id: DocumentId) -> Result<DocumentHit> {
+    async fn get_document(&self, doc_id: DocumentId) -> Result<Option<DocumentHit>> {
         self.reader.get_document(doc_id).await
     }
 
+    /// Get a set of documents via their given ids.
+    async fn get_documents(
+        &self,
+        doc_ids: Vec<DocumentId>,
+    ) -> Result<Vec<Option<DocumentHit>>> {
+        self.reader.get_documents(doc_ids).await
+    }
+
+    /// Gets up to `limit` terms of the given field starting with the prefix.
+    async fn suggest(
+        &self,
+        field: String,
+        prefix: String,
+        limit: usize,
+    ) -> Result<Vec<Suggestion>> {
+        self.reader.suggest(field, prefix, limit).await
+    }
+
+    /// Corrects each word of the query with the closest indexed term.
+    async fn spell_check(&self, field: String, query: String) -> Result<SpellCheck> {
+        self.reader.spell_check(field, query).await
+    }
+
+    /// Exports every document in the index in chunks of `chunk_size`.
+    fn export_documents(
+        &self,
+        chunk_size: usize,
+        segment: Option<String>,
+        limits: ExportLimits,
+    ) -> mpsc::Receiver<Result<Vec<DocumentHit>>> {
+        self.reader.export_documents(chunk_size, segment, limits)
+    }
+
+    /// Applies the ingest pipeline to the document and then populates any
+    /// compound fields from their source fields.
+    fn prepare_document(&self, payload: &mut DocumentPayload) {
+        let schema_ctx = &self._ctx.schema_ctx;
+        payload.apply_pipeline(schema_ctx.ingest_pipeline());
+        payload.apply_compound_fields(schema_ctx.compound_fields());
+    }
+
     /// Adds one or more documents to the index.
     ///
     /// This function is semi-asynchronous in the sense that there is a buffer of
     /// 20 tasks that can be submitted to the writer before the extra pending tasks
     /// must wait in order to then submit their operation to the queue.
-    async fn add_documents(&self, doc_opts: DocumentOptions) -> Result<()> {
+    ///
+    /// The amount of values too large to be indexed as a single term, which
+    /// were truncated or skipped, is returned.
+    async fn add_documents(
+        &self,
+        mut doc_opts: DocumentOptions,
+        block_timeout: Option<Duration>,
+    ) -> Result<usize> {
+        self.ensure_writable()?;
+
+        match doc_opts {
+            DocumentOptions::Single(ref mut payload) => self.prepare_document(payload),
+            DocumentOptions::Many(ref mut payloads) => {
+                for payload in payloads.iter_mut() {
+                    self.prepare_document(payload);
+                }
+            },
+        }
+
+        let schema = self._ctx.schema();
+        let mut oversized = 0;
         match doc_opts {
-            DocumentOptions::Single(payload) => {
-                self.writer.send_op(WriterOp::AddDocument(payload)).await
+            DocumentOptions::Single(ref payload) => {
+                oversized += payload.validate(0, &schema, &self._ctx.schema_ctx)?
             },
-            DocumentOptions::Many(payloads) => {
-                self.writer
-                    .send_op(WriterOp::AddManyDocuments(payloads))
-                    .await
+            DocumentOptions::Many(ref payloads) => {
+                for (position, payload) in payloads.iter().enumerate() {
+                    oversized +=
+                        payload.validate(position, &schema, &self._ctx.schema_ctx)?;
+                }
             },
         }
+
+        let op = match doc_opts {
+            DocumentOptions::Single(payload) => WriterOp::AddDocument(payload),
+            DocumentOptions::Many(payloads) => WriterOp::AddManyDocuments(payloads),
+        };
+
+        self.writer.send_op_with_timeout(op, block_timeout).await?;
+
+        Ok(oversized)
     }
 
     /// Deletes all documents from the index.
     async fn clear_documents(&self) -> Result<()> {
+        self.ensure_writable()?;
         self.writer.send_op(WriterOp::DeleteAll).await
     }
 
+    /// Copies every document of the source index into this index.
+    async fn reindex_from(
+        &self,
+        source: &Index,
+        field_mappings: HashMap<String, String>,
+    ) -> Result<ReindexReport> {
+        self.ensure_writable()?;
+
+        let schema = self._ctx.schema();
+        let mut report = ReindexReport::default();
+        let mut chunks =
+            source.export_documents(REINDEX_CHUNK_SIZE, None, ExportLimits::default());
+        while let Some(chunk) = chunks.recv().await {
+            let mut payloads = Vec::with_capacity(REINDEX_CHUNK_SIZE);
+            for hit in chunk? {
+                let document_id = hit.document_id;
+                let payload = hit.into_payload(&field_mappings);
+
+                match payload.validate(report.read, &schema, &self._ctx.schema_ctx) {
+                    Ok(_) => payloads.push(payload),
+                    Err(e) => {
+                        report.failed += 1;
+                        if report.failures.len() < MAX_REPORTED_REINDEX_FAILURES {
+                            report.failures.push(ReindexFailure {
+                                document_id,
+                                reason: e.to_string(),
+                            });
+                        }
+                    },
+                }
+
+                report.read += 1;
+            }
+
+            report.copied += payloads.len();
+            self.writer
+                .send_op(WriterOp::AddManyDocuments(payloads))
+                .await?;
+
+            info!(
+                read = report.read,
+                copied = report.copied,
+                failed = report.failed,
+                "reindexing documents"
+            );
+        }
+
+        self.writer.send_op(WriterOp::Commit).await?;
+
+        Ok(report)
+    }
+
     /// Deletes a specific document
     pub async fn delete_document(&self, document_id: DocumentId) -> Result<()> {
+        self.ensure_writable()?;
         self.writer
             .send_op(WriterOp::DeleteManyDocuments(vec![document_id]))
             .await
     }
 
+    /// Updates some of the fields of an existing document.
+    ///
+    /// Fields which are not stored cannot be fetched, so they must be given
+    /// otherwise their values would be lost.
+    async fn update_document(
+        &self,
+        document_id: DocumentId,
+        fields: DocumentPayload,
+    ) -> Result<bool> {
+        self.ensure_writable()?;
+
+        let schema_ctx = &self._ctx.schema_ctx;
+        if let Some(id_field) = schema_ctx.id_field() {
+            if fields.has_field(id_field) {
+                return Err(anyhow!("the id field {:?} cannot be updated", id_field));
+            }
+        }
+
+        let schema = self._ctx.schema();
+        let mut missing_fields: Vec<&str> = schema_ctx
+            .fields()
+            .keys()
+            .filter(|name| !fields.has_field(name))
+            .filter(|name| !schema_ctx.compound_fields().contains_key(*name))
+            .filter(|name| {
+                schema
+                    .get_field(name)
+                    .map(|field| !schema.get_field_entry(field).is_stored())
+                    .unwrap_or(false)
+            })
+            .map(|name| name.as_str())
+            .collect();
+
+        if !missing_fields.is_empty() {
+            missing_fields.sort_unstable();
+            return Err(anyhow!(
+                "the fields {} are not stored so must be given to update the document",
+                missing_fields.join(", "),
+            ));
+        }
+
+        // The fields are merged by the writer so updates made since the
+        // last commit are not lost.
+        let res = self
+            .writer
+            .send_op(WriterOp::UpdateDocument(document_id, fields))
+            .await;
+
+        match res {
+            Ok(()) => Ok(true),
+            Err(e) if e.is::<DocumentNotFound>() => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Deletes all documents from the index matching a given term(s).
     async fn delete_documents_where(
         &self,
         fields: BTreeMap<String, DocumentValueOptions>,
     ) -> Result<usize> {
+        self.ensure_writable()?;
+
         let mut query_payload = vec![];
         for (field, opts) in fields {
             match opts {
@@ -271,10 +776,27 @@ impl InternalIndex {
         loop {
             let query = QueryPayload {
                 query: QuerySelector::Multi(query_payload.clone()),
-                limit,
-                offset,
+                limit: Some(limit),
+                offset: Some(offset),
                 order_by: None,
                 sort: Default::default(),
+                collapse_field: None,
+                geo_filter: None,
+                default_operator: None,
+                highlight_fields: None,
+                highlight_pre_tag: None,
+                highlight_post_tag: None,
+                max_snippets_per_field: None,
+                empty_as_404: false,
+                rescore_script: None,
+                rescore_window: None,
+                extra_search_fields: None,
+                normalize_scores: false,
+                profile: false,
+                minimum_should_match: None,
+                raw_query: false,
+                parallel: None,
+                unbounded: true,
             };
 
             let results = self.search(query).await?;
@@ -300,6 +822,8 @@ impl InternalIndex {
 
     /// Deletes all returned documents matching the given query.
     async fn delete_by_query(&self, qry: QueryPayload) -> Result<usize> {
+        self.ensure_writable()?;
+
         let results = self.search(qry).await?;
         let docs: Vec<DocumentId> =
             results.hits.into_iter().map(|v| v.document_id).collect();
@@ -341,6 +865,17 @@ impl InternalIndex {
         self.writer.send_op(WriterOp::ClearStopWords).await
     }
 
+    /// Replaces the index's custom stop words with the given set.
+    async fn replace_stop_words(&self, words: Vec<String>) -> Result<ReplacedEntries> {
+        let before = self._ctx.stop_words.get_index_stop_words();
+        self.writer
+            .send_op(WriterOp::ReplaceStopWords(words))
+            .await?;
+        let after = self._ctx.stop_words.get_index_stop_words();
+
+        Ok(ReplacedEntries::between(before.iter(), after.iter()))
+    }
+
     /// Adds a set of synonyms to the indexes' synonym manager.
     ///
     /// This function is semi-asynchronous in the sense that there is a buffer of
@@ -369,23 +904,38 @@ impl InternalIndex {
         self.writer.send_op(WriterOp::ClearSynonyms).await
     }
 
+    /// Replaces all of the index's synonyms with the given relations.
+    async fn replace_synonyms(&self, relations: Vec<String>) -> Result<ReplacedEntries> {
+        let before = self.reader.get_synonyms();
+        self.writer
+            .send_op(WriterOp::ReplaceSynonyms(relations))
+            .await?;
+        let after = self.reader.get_synonyms();
+
+        Ok(ReplacedEntries::between(before.keys(), after.keys()))
+    }
+
     /// Shuts the index down waiting for all writer threads to finish.
     async fn shutdown(&self) -> Result<()> {
-        self.writer.shutdown().await
+        self.writer.shutdown().await?;
+        self.reader.shutdown();
+
+        Ok(())
     }
 
     /// Shuts the index down removing any persistent data along with it.
     async fn destroy(&self) -> Result<()> {
-        self.writer.destroy().await
+        self.writer.destroy().await?;
+        self.reader.shutdown();
+
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::time::Duration;
-
     use super::*;
-    use crate::structures::{DocumentValue, IndexDeclaration};
+    use crate::structures::{DocumentValidationError, DocumentValue, IndexDeclaration};
 
     fn init_state() {
         let _ = std::env::set_var("RUST_LOG", "debug");
@@ -399,6 +949,38 @@ mod tests {
         Index::create(res).await
     }
 
+    /// Creates a single threaded memory index with the given fields.
+    ///
+    /// Any other keys of the declaration, including the name, are set via the
+    /// overrides which replace the defaults.
+    async fn get_memory_index(
+        fields: serde_json::Value,
+        overrides: serde_json::Value,
+    ) -> Result<Index> {
+        let mut value = serde_json::json!({
+            "name": "test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": fields,
+        });
+
+        if let serde_json::Value::Object(overrides) = overrides {
+            for (key, override_value) in overrides {
+                value[key] = override_value;
+            }
+        }
+
+        get_index_with(value).await
+    }
+
     #[tokio::test]
     async fn memory_lifecycle_expect_ok() -> Result<()> {
         init_state();
@@ -1340,97 +1922,282 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn add_docs_expect_ok() -> Result<()> {
+    async fn replace_stop_words_expect_ok() -> Result<()> {
         init_state();
 
         let index = get_basic_index(false).await?;
 
-        let document: DocumentOptions = serde_json::from_value(serde_json::json!({
-            "title": "The Old Man and the Sea",
-            "description": "He was an old man who fished alone in a skiff in \
-            the Gulf Stream and he had gone eighty-four days \
-            now without taking a fish.",
-        }))?;
+        index
+            .add_stop_words(vec!["the".into(), "quick".into(), "fox".into()])
+            .await?;
 
-        let res = index.add_documents(document).await;
-        assert!(res.is_ok());
+        let words = vec!["The".into(), "fox".into(), "jumped".into(), "fox".into()];
+        let replaced = index.replace_stop_words(words).await?;
+        assert_eq!(replaced.added, 1);
+        assert_eq!(replaced.removed, 1);
+
+        let stop_words = index.get_stop_words();
+        assert_eq!(stop_words, vec!["the", "fox", "jumped"]);
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn add_docs_with_valid_non_required_field_expect_ok() -> Result<()> {
+    async fn add_synonyms_direction_expect_ok() -> Result<()> {
         init_state();
 
         let index = get_basic_index(false).await?;
 
-        let document: DocumentOptions = serde_json::from_value(serde_json::json!({
-            "title": "hello",
-        }))?;
+        index
+            .add_synonyms(vec!["nyc:new york city".into(), "car,automobile".into()])
+            .await?;
 
-        let res = index.add_documents(document).await;
-        assert!(res.is_ok());
+        let synonyms = index.get_synonyms();
+        assert_eq!(synonyms["nyc"].to_vec(), vec!["new york city"]);
+        assert!(!synonyms.contains_key("new york city"));
+        assert_eq!(synonyms["car"].to_vec(), vec!["automobile"]);
+        assert_eq!(synonyms["automobile"].to_vec(), vec!["car"]);
+
+        assert!(index.add_synonyms(vec!["car".into()]).await.is_err());
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn add_docs_with_required_field_expect_ok() -> Result<()> {
+    async fn replace_synonyms_expect_ok() -> Result<()> {
         init_state();
 
-        let index = get_index_with_required_title(false).await?;
+        let index = get_basic_index(false).await?;
 
-        let document: DocumentOptions = serde_json::from_value(serde_json::json!({
-            "title": "hello",
-        }))?;
+        index
+            .add_synonyms(vec!["iphone,apple:phone".into()])
+            .await?;
 
-        let res = index.add_documents(document).await;
-        assert!(res.is_ok());
+        let relations = vec!["apple:fruit".into(), "car:vehicle".into()];
+        let replaced = index.replace_synonyms(relations).await?;
+        assert_eq!(replaced.added, 1);
+        assert_eq!(replaced.removed, 1);
+
+        let synonyms = index.get_synonyms();
+        assert_eq!(synonyms.len(), 2);
+        assert_eq!(synonyms["apple"].to_vec(), vec!["fruit"]);
+        assert!(!synonyms.contains_key("iphone"));
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn add_docs_valid_doc_missing_required_expect_ok() -> Result<()> {
+    async fn reload_expect_ok() -> Result<()> {
         init_state();
 
-        let index = get_index_with_required_title(false).await?;
+        let index = get_basic_index(false).await?;
+        add_documents(&index).await?;
 
-        let document: DocumentOptions = serde_json::from_value(serde_json::json!({
-            "description": "hello",
-        }))?;
+        index.reload()?;
 
-        let res = index.add_documents(document).await;
-        assert!(res.is_err());
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "*"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), NUM_DOCS);
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn add_bulk_docs_expect_ok() -> Result<()> {
+    async fn warm_on_reload_expect_ok() -> Result<()> {
         init_state();
 
-        let index = get_basic_index(false).await?;
+        let index = get_index_with(serde_json::json!({
+            "name": "warm_on_reload_test_index",
 
-        let document: DocumentOptions = serde_json::from_value(serde_json::json!(
-            [
-                {
-                    "title": "The Old Man and the Sea",
-                    "description": "He was an old man who fished alone in a skiff in \
-                    the Gulf Stream and he had gone eighty-four days \
-                    now without taking a fish.",
-                },
-                {
-                    "title": "The Old Man and the Sea 2",
-                    "description": "He was an old man who fished alone in a skiff in \
-                    the Gulf Stream and he had gone eighty-four days \
-                    now without taking a fish.",
-                    "count": 3
-                },
-                {
-                    "title": "The Old Man and the Sea 3",
-                },
-            ]
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+            "warm_on_reload": true,
+            "warmup_query": "title:old",
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true
+                },
+            },
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title": "The Old Man and the Sea"},
+            {"title": "Of Mice and Men"},
+        ]))?;
+        index.add_documents(documents).await?;
+        index.commit().await?;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "*"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn invalid_warmup_query_expect_err() -> Result<()> {
+        init_state();
+
+        for (warm_on_reload, warmup_query) in
+            [(true, "title:("), (true, " "), (false, "title:old")]
+        {
+            let res = get_index_with(serde_json::json!({
+                "name": "invalid_warmup_query_test_index",
+
+                // Reader context
+                "reader_threads": 1,
+                "max_concurrency": 1,
+                "warm_on_reload": warm_on_reload,
+                "warmup_query": warmup_query,
+
+                // Writer context
+                "writer_buffer": 3_000_000,
+                "writer_threads": 1,
+
+                "storage_type": "memory",
+                "fields": {
+                    "title": {
+                        "type": "text",
+                        "stored": true
+                    },
+                },
+            }))
+            .await;
+
+            assert!(res.is_err(), "expected {:?} to be rejected", warmup_query);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_after_shutdown_expect_err() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+        add_documents(&index).await?;
+
+        index.shutdown().await?;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "*"},
+            },
+        }))?;
+        assert!(index.search(query).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn add_docs_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+
+        let document: DocumentOptions = serde_json::from_value(serde_json::json!({
+            "title": "The Old Man and the Sea",
+            "description": "He was an old man who fished alone in a skiff in \
+            the Gulf Stream and he had gone eighty-four days \
+            now without taking a fish.",
+        }))?;
+
+        let res = index.add_documents(document).await;
+        assert!(res.is_ok());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn add_docs_with_valid_non_required_field_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+
+        let document: DocumentOptions = serde_json::from_value(serde_json::json!({
+            "title": "hello",
+        }))?;
+
+        let res = index.add_documents(document).await;
+        assert!(res.is_ok());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn add_docs_with_required_field_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with_required_title(false).await?;
+
+        let document: DocumentOptions = serde_json::from_value(serde_json::json!({
+            "title": "hello",
+        }))?;
+
+        let res = index.add_documents(document).await;
+        assert!(res.is_ok());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn add_docs_valid_doc_missing_required_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with_required_title(false).await?;
+
+        let document: DocumentOptions = serde_json::from_value(serde_json::json!({
+            "description": "hello",
+        }))?;
+
+        let res = index.add_documents(document).await;
+        assert!(res.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn add_bulk_docs_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+
+        let document: DocumentOptions = serde_json::from_value(serde_json::json!(
+            [
+                {
+                    "title": "The Old Man and the Sea",
+                    "description": "He was an old man who fished alone in a skiff in \
+                    the Gulf Stream and he had gone eighty-four days \
+                    now without taking a fish.",
+                },
+                {
+                    "title": "The Old Man and the Sea 2",
+                    "description": "He was an old man who fished alone in a skiff in \
+                    the Gulf Stream and he had gone eighty-four days \
+                    now without taking a fish.",
+                    "count": 3
+                },
+                {
+                    "title": "The Old Man and the Sea 3",
+                },
+            ]
         ))?;
 
         let res = index.add_documents(document).await;
@@ -1472,6 +2239,159 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn relaxed_durability_lifecycle_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "relaxed_durability_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "tempdir",
+            "durability": "relaxed",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true
+                },
+            },
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!({
+            "title": "The Old Man and the Sea",
+        }))?;
+
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "sea"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        index.destroy().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reader_thread_options_expect_err() -> Result<()> {
+        init_state();
+
+        let invalid = [
+            serde_json::json!({"thread_niceness": 20}),
+            serde_json::json!({"thread_niceness": -21}),
+            serde_json::json!({"thread_affinity": []}),
+        ];
+
+        for opts in invalid {
+            let mut value = serde_json::json!({
+                "name": "reader_thread_options_index",
+
+                // Reader context
+                "reader_threads": 1,
+                "max_concurrency": 1,
+
+                // Writer context
+                "writer_buffer": 3_000_000,
+                "writer_threads": 1,
+
+                "storage_type": "memory",
+                "fields": {
+                    "title": {
+                        "type": "text",
+                    },
+                },
+            });
+            value
+                .as_object_mut()
+                .expect("get declaration")
+                .extend(opts.as_object().expect("get options").clone());
+
+            let dec: IndexDeclaration = serde_json::from_value(value)?;
+            assert!(dec.create_context().is_err());
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn multi_value_fields_round_trip_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "multi_value_round_trip_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "tags": {
+                    "type": "string",
+                    "multi": true,
+                },
+                "counts": {
+                    "type": "u64",
+                    "indexed": true,
+                    "multi": true,
+                },
+            },
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!({
+            "tags": ["a", "b", "c"],
+            "counts": [1, 2, 3],
+        }))?;
+
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        // Every value is indexed, not just the first or last.
+        for (ctx, field) in [("b", "tags"), ("a", "tags"), ("c", "tags")] {
+            let query: QueryPayload = serde_json::from_value(serde_json::json!({
+                "query": {
+                    "term": {"ctx": ctx, "fields": field},
+                },
+            }))?;
+            let results = index.search(query).await?;
+            assert_eq!(results.hits.len(), 1);
+        }
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "term": {"ctx": 2, "fields": "counts"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        let hit = serde_json::to_value(&results.hits[0])?;
+        assert_eq!(hit["doc"]["tags"], serde_json::json!(["a", "b", "c"]));
+        assert_eq!(hit["doc"]["counts"], serde_json::json!([1, 2, 3]));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn add_bulk_docs_empty_multi_field_expect_err() -> Result<()> {
         init_state();
@@ -1924,22 +2844,108 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn search_fast_fuzzy_expect_ok() -> Result<()> {
+    async fn search_fuzzy_require_all_terms_expect_ok() -> Result<()> {
         init_state();
 
-        let index = get_basic_index(true).await?;
-        add_documents(&index).await?;
+        for &(require_all_terms, expected_hits) in [(false, 3), (true, 1)].iter() {
+            let index = get_index_with(serde_json::json!({
+                "name": "fuzzy_require_all_terms_test_index",
 
-        let query: QueryPayload = serde_json::from_value(serde_json::json!({
-            "query": {
-                "fuzzy": {"ctx": "ol"},
-            },
-        }))?;
+                // Reader context
+                "reader_threads": 1,
+                "max_concurrency": 1,
 
-        let results = index.search(query).await?;
-        assert_eq!(results.hits.len(), NUM_DOCS);
+                // Writer context
+                "writer_buffer": 3_000_000,
+                "writer_threads": 1,
 
-        Ok(())
+                "storage_type": "memory",
+                "fields": {
+                    "title": {
+                        "type": "text",
+                        "stored": true
+                    },
+                },
+
+                "fuzzy_require_all_terms": require_all_terms,
+            }))
+            .await?;
+
+            let documents: DocumentOptions =
+                serde_json::from_value(serde_json::json!([
+                    {"title": "The Old Man and the Sea"},
+                    {"title": "An old boat"},
+                    {"title": "A young man"},
+                ]))?;
+            index.add_documents(documents).await?;
+            index.commit().await?;
+            tokio::time::sleep(Duration::from_millis(1000)).await;
+
+            let query: QueryPayload = serde_json::from_value(serde_json::json!({
+                "query": {
+                    "fuzzy": {"ctx": "old man"},
+                },
+            }))?;
+            let results = index.search(query).await?;
+            assert_eq!(results.hits.len(), expected_hits);
+
+            let query: QueryPayload = serde_json::from_value(serde_json::json!({
+                "query": {
+                    "fuzzy": {"ctx": "old man"},
+                },
+                "minimum_should_match": 1,
+            }))?;
+            assert_eq!(index.search(query).await.is_err(), require_all_terms);
+
+            index.destroy().await?;
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_fast_fuzzy_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(true).await?;
+        add_documents(&index).await?;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "fuzzy": {"ctx": "ol"},
+            },
+        }))?;
+
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), NUM_DOCS);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_fast_fuzzy_empty_dictionary_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(true).await?;
+        add_documents(&index).await?;
+
+        // Resets the dictionary to the state it is in before being generated.
+        index
+            .0
+            ._ctx
+            .correction_manager
+            .adjust_index_frequencies(&HashMap::new());
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "fuzzy": {"ctx": "ol"},
+            },
+        }))?;
+
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), NUM_DOCS);
+
+        Ok(())
     }
 
     #[tokio::test]
@@ -2120,4 +3126,2753 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn search_cached_filter_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "cached_filter_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+            "filter_cache_size": 1_000_000,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true,
+                },
+                "status": {
+                    "type": "string",
+                    "stored": true,
+                },
+            },
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title": "red apple", "status": "active"},
+            {"title": "green apple", "status": "archived"},
+            {"title": "red pear", "status": "active"},
+        ]))?;
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(2000)).await;
+
+        // Repeated filters reuse the cached documents while filters on other
+        // values are cached separately.
+        for (ctx, status, expected) in [
+            ("apple", "active", 1),
+            ("red", "active", 2),
+            ("apple", "archived", 1),
+            ("red", "archived", 0),
+            ("apple", "active", 1),
+            ("red", "active", 2),
+        ] {
+            let query: QueryPayload = serde_json::from_value(serde_json::json!({
+                "query": [
+                    {
+                        "normal": {"ctx": ctx},
+                        "occur": "must",
+                    },
+                    {
+                        "term": {"ctx": status, "fields": "status"},
+                        "occur": "must",
+                        "filter": true,
+                    },
+                ],
+            }))?;
+
+            let results = index.search(query).await?;
+            assert_eq!(results.hits.len(), expected);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_collapse_field_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+        add_documents(&index).await?;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "*"},
+            },
+            "collapse_field": "count",
+        }))?;
+
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 2);
+        assert_eq!(results.collapsed, 1);
+        assert_eq!(results.total_after_collapse, Some(2));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_normalize_scores_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+        add_documents(&index).await?;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "old man fish"},
+            },
+            "normalize_scores": true,
+        }))?;
+
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), NUM_DOCS);
+        assert_eq!(results.hits[0].score, Some(1.0));
+        for hit in results.hits.iter() {
+            assert!(matches!(hit.score, Some(score) if (0.0..=1.0).contains(&score)));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_profile_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+        add_documents(&index).await?;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "old man fish"},
+            },
+            "profile": true,
+        }))?;
+
+        let results = index.search(query).await?;
+        let timings = results.timings.expect("timings to be set when profiling");
+        for phase in ["parse", "collect", "retrieve", "rescore", "highlight"] {
+            assert!(timings.contains_key(phase), "missing phase {:?}", phase);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn diagnostics_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+        add_documents(&index).await?;
+
+        let diagnostics = serde_json::to_value(&index.diagnostics().await?)?;
+        let segments = diagnostics["segments"].as_array().expect("segments");
+        assert!(!segments.is_empty());
+
+        let num_docs: u64 = segments
+            .iter()
+            .filter_map(|segment| segment["num_docs"].as_u64())
+            .sum();
+        assert_eq!(num_docs, NUM_DOCS as u64);
+        assert!(segments
+            .iter()
+            .all(|segment| segment["size_bytes"].as_u64() > Some(0)));
+        assert_eq!(diagnostics["queued_operations"], 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn auto_merge_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "auto_merge_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+            "auto_merge": {
+                "target_segments": 1,
+                "idle_secs": 1,
+            },
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true,
+                },
+            },
+            "search_fields": ["title"],
+        }))
+        .await?;
+
+        for title in ["hello", "world", "foo"] {
+            let documents: DocumentOptions =
+                serde_json::from_value(serde_json::json!({ "title": title }))?;
+            index.add_documents(documents).await?;
+            index.commit().await?;
+        }
+
+        tokio::time::sleep(Duration::from_millis(3000)).await;
+
+        let diagnostics = serde_json::to_value(&index.diagnostics().await?)?;
+        let segments = diagnostics["segments"].as_array().expect("segments");
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0]["num_docs"], 3);
+
+        index.destroy().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn ranking_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+
+        let mut boost_fields = HashMap::new();
+        boost_fields.insert("title".to_string(), 2.0);
+        index.set_boost_fields(boost_fields)?;
+
+        let ranking = serde_json::to_value(&index.ranking())?;
+        assert_eq!(ranking["boost_fields"], serde_json::json!({"title": 2.0}));
+        let search_fields = ranking["search_fields"].as_array().expect("fields");
+        assert_eq!(search_fields.len(), 2);
+        assert!(search_fields.contains(&serde_json::json!("title")));
+        assert!(search_fields.contains(&serde_json::json!("description")));
+        assert_eq!(ranking["fields"]["title"]["type"], "text");
+        assert_eq!(ranking["fields"]["title"]["stored"], true);
+        assert_eq!(ranking["fields"]["description"]["type"], "string");
+        assert_eq!(ranking["fields"]["description"]["stored"], false);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_many_documents_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+        add_documents(&index).await?;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "*"},
+            },
+        }))?;
+
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), NUM_DOCS);
+
+        let first = results.hits[0].document_id;
+        let last = results.hits[NUM_DOCS - 1].document_id;
+
+        let documents = index.get_documents(vec![last, 0, first]).await?;
+        assert_eq!(documents.len(), 3);
+        assert_eq!(documents[0].as_ref().map(|doc| doc.document_id), Some(last));
+        assert!(documents[1].is_none());
+        assert_eq!(
+            documents[2].as_ref().map(|doc| doc.document_id),
+            Some(first)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn suggest_terms_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+        add_documents(&index).await?;
+
+        let suggestions = index
+            .suggest("title".to_string(), "O".to_string(), 5)
+            .await?;
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].term, "old");
+        assert_eq!(suggestions[0].doc_freq, 3);
+
+        let suggestions = index
+            .suggest("title".to_string(), "".to_string(), 2)
+            .await?;
+        assert_eq!(suggestions.len(), 2);
+        assert!(suggestions[0].doc_freq >= suggestions[1].doc_freq);
+
+        assert!(index
+            .suggest("count".to_string(), "1".to_string(), 5)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn spell_check_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+        add_documents(&index).await?;
+
+        let spell_check = index
+            .spell_check("title".to_string(), "tha Olde man".to_string())
+            .await?;
+        assert_eq!(spell_check.query, "the old man");
+        assert_eq!(spell_check.corrections.len(), 2);
+        assert_eq!(spell_check.corrections[0].word, "tha");
+        assert_eq!(spell_check.corrections[0].suggestion, "the");
+
+        let spell_check = index
+            .spell_check("title".to_string(), "zzzzzz".to_string())
+            .await?;
+        assert_eq!(spell_check.query, "zzzzzz");
+        assert!(spell_check.corrections.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn export_documents_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+        add_documents(&index).await?;
+
+        let mut total = 0;
+        let mut rx = index.export_documents(7, None, ExportLimits::default());
+        while let Some(chunk) = rx.recv().await {
+            let chunk = chunk?;
+            assert!(!chunk.is_empty() && chunk.len() <= 7);
+            total += chunk.len();
+        }
+
+        assert_eq!(total, NUM_DOCS);
+
+        let mut rx = index.export_documents(
+            7,
+            Some("missing".to_string()),
+            ExportLimits::default(),
+        );
+        assert!(matches!(rx.recv().await, Some(Err(_))));
+
+        let limits = ExportLimits {
+            max_rows: Some(2),
+            max_duration: None,
+        };
+
+        let mut total = 0;
+        let mut rx = index.export_documents(1, None, limits);
+        while let Some(chunk) = rx.recv().await {
+            total += chunk?.len();
+        }
+
+        assert_eq!(total, 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_parallel_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+        add_documents(&index).await?;
+
+        for parallel in [true, false] {
+            let query: QueryPayload = serde_json::from_value(serde_json::json!({
+                "query": {
+                    "normal": {"ctx": "*"},
+                },
+                "parallel": parallel,
+            }))?;
+            let results = index.search(query).await?;
+            assert_eq!(results.hits.len(), NUM_DOCS);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_stream_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+        add_documents(&index).await?;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "*"},
+            },
+            "limit": 2,
+            "offset": 0,
+        }))?;
+
+        let mut total = 0;
+        let mut rx = index.search_stream(query).await?;
+        while let Some(chunk) = rx.recv().await {
+            let chunk = chunk?;
+            assert!(chunk.iter().all(|hit| hit.score.is_some()));
+            total += chunk.len();
+        }
+
+        assert_eq!(total, 2);
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "*"},
+            },
+            "limit": 10,
+            "offset": 1,
+        }))?;
+
+        let mut total = 0;
+        let mut rx = index.search_stream(query).await?;
+        while let Some(chunk) = rx.recv().await {
+            total += chunk?.len();
+        }
+
+        assert_eq!(total, NUM_DOCS - 1);
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "*"},
+            },
+            "order_by": "count",
+        }))?;
+        assert!(index.search_stream(query).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_max_result_window_expect_err() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "max_result_window_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+            "max_result_window": 10,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true,
+                },
+            },
+            "search_fields": ["title"],
+        }))
+        .await?;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "hello"},
+            },
+            "limit": 5,
+            "offset": 5,
+        }))?;
+        assert!(index.search(query).await.is_ok());
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "hello"},
+            },
+            "limit": 5,
+            "offset": 6,
+        }))?;
+        assert!(index.search(query).await.is_err());
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "hello"},
+            },
+            "limit": 11,
+        }))?;
+        assert!(index.search_stream(query).await.is_err());
+
+        index.destroy().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_acquire_timeout_expect_err() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "acquire_timeout_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+            "acquire_timeout": 100,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true,
+                },
+            },
+            "search_fields": ["title"],
+        }))
+        .await?;
+
+        let documents: Vec<serde_json::Value> = (0..500)
+            .map(|n| serde_json::json!({"title": format!("hello {}", n)}))
+            .collect();
+        let documents: DocumentOptions =
+            serde_json::from_value(serde_json::Value::Array(documents))?;
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        // The stream holds the only permit until all of its hits are read.
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "hello"},
+            },
+            "limit": 500,
+        }))?;
+        let rx = index.search_stream(query).await?;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "hello"},
+            },
+        }))?;
+        let err = index.search(query).await.expect_err("pool is saturated");
+        assert!(err.is::<aexecutor::PoolSaturated>());
+
+        drop(rx);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "hello"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 20);
+
+        index.destroy().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn update_document_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "update_document_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true,
+                },
+                "count": {
+                    "type": "u64",
+                    "stored": true,
+                    "indexed": true,
+                },
+            },
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!({
+            "title": "hello world",
+            "count": 1,
+        }))?;
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "hello"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        let document_id = results.hits[0].document_id;
+
+        let fields: DocumentPayload = serde_json::from_value(serde_json::json!({
+            "count": 5,
+        }))?;
+        assert!(index.update_document(document_id, fields).await?);
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let document = index
+            .get_document(document_id)
+            .await?
+            .expect("get document");
+        let document = serde_json::to_value(&document)?;
+        assert_eq!(document["doc"]["title"], "hello world");
+        assert_eq!(document["doc"]["count"], 5);
+
+        let fields: DocumentPayload = serde_json::from_value(serde_json::json!({
+            "count": 5,
+        }))?;
+        assert!(!index.update_document(document_id + 1, fields).await?);
+
+        index.destroy().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn update_document_twice_before_commit_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "update_document_twice_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true,
+                },
+                "count": {
+                    "type": "u64",
+                    "stored": true,
+                    "indexed": true,
+                },
+            },
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!({
+            "title": "hello world",
+            "count": 1,
+        }))?;
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "hello"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        let document_id = results.hits[0].document_id;
+
+        let fields: DocumentPayload = serde_json::from_value(serde_json::json!({
+            "count": 5,
+        }))?;
+        assert!(index.update_document(document_id, fields).await?);
+
+        let fields: DocumentPayload = serde_json::from_value(serde_json::json!({
+            "title": "hello there",
+        }))?;
+        assert!(index.update_document(document_id, fields).await?);
+
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let document = index
+            .get_document(document_id)
+            .await?
+            .expect("get document");
+        let document = serde_json::to_value(&document)?;
+        assert_eq!(document["doc"]["title"], "hello there");
+        assert_eq!(document["doc"]["count"], 5);
+
+        index.delete_document(document_id).await?;
+        let fields: DocumentPayload = serde_json::from_value(serde_json::json!({
+            "count": 6,
+        }))?;
+        assert!(!index.update_document(document_id, fields).await?);
+
+        index.destroy().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn update_document_missing_unstored_fields_expect_err() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+        add_documents(&index).await?;
+
+        let fields: DocumentPayload = serde_json::from_value(serde_json::json!({
+            "title": "The Old Man and the Sea 4",
+        }))?;
+        assert!(index.update_document(0, fields).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn read_only_rejects_writes_expect_err() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+        add_documents(&index).await?;
+
+        index.set_read_only(true);
+        assert!(index.is_read_only());
+
+        let document: DocumentOptions = serde_json::from_value(serde_json::json!({
+            "title": "The Old Man and the Sea 4",
+        }))?;
+
+        let res = index.add_documents(document).await;
+        assert!(res.map_err(|e| e.is::<IndexReadOnly>()).unwrap_err());
+
+        let res = index.commit().await;
+        assert!(res.map_err(|e| e.is::<IndexReadOnly>()).unwrap_err());
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "*"},
+            },
+        }))?;
+
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), NUM_DOCS);
+
+        index.set_read_only(false);
+        index.commit().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_single_value_fields_are_scalars_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+        add_documents(&index).await?;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "*"},
+            },
+        }))?;
+
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), NUM_DOCS);
+
+        let hits = serde_json::to_value(&results.hits)?;
+        for hit in hits.as_array().expect("hits array") {
+            assert!(hit["doc"]["title"].is_string());
+            assert!(!hit["doc"]["count"].is_array());
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_zero_limit_expect_err() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+        add_documents(&index).await?;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "*"},
+            },
+            "limit": 0,
+        }))?;
+
+        let res = index.search(query).await;
+        assert!(res.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_geo_filter_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_memory_index(
+            serde_json::json!({
+                "title": {
+                    "type": "text",
+                    "stored": true
+                },
+                "location": {
+                    "type": "geo",
+                    "stored": true
+                },
+            }),
+            serde_json::json!({
+                "name": "geo_test_index",
+
+                // The query context
+                "search_fields": [
+                    "title",
+                ],
+            }),
+        )
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title": "Cambridge", "location": [52.2053, 0.1218]},
+            {"title": "London", "location": [51.5074, -0.1278]},
+            {"title": "Paris", "location": [48.8566, 2.3522]},
+        ]))?;
+
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "*"},
+            },
+            "geo_filter": {
+                "field": "location",
+                "lat": 51.5,
+                "lon": -0.12,
+                "radius": 100_000,
+            },
+        }))?;
+
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 2);
+
+        let closest = results.hits[0].distance.expect("get distance");
+        let furthest = results.hits[1].distance.expect("get distance");
+        assert!(closest < furthest);
+        assert!(furthest <= 100_000.0);
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "*"},
+            },
+            "geo_filter": {
+                "field": "location",
+                "lat": 51.5,
+                "lon": -0.12,
+                "radius": 100_000,
+            },
+            "limit": 1,
+            "offset": 1,
+        }))?;
+
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+        assert_eq!(results.hits[0].distance, Some(furthest));
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!({
+            "title": "Nowhere",
+            "location": [120.0, 0.0],
+        }))?;
+        assert!(index.add_documents(documents).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_stemmed_field_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "stemming_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true,
+                    "stemming_language": "english"
+                },
+            },
+
+            // The query context
+            "search_fields": [
+                "title",
+            ],
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title": "Running shoes"},
+            {"title": "Walking boots"},
+        ]))?;
+
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "run"},
+            },
+        }))?;
+
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "fuzzy": {"ctx": "runs"},
+            },
+        }))?;
+
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_language_fields_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "language_fields_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title_en": {
+                    "type": "text",
+                    "stored": true,
+                    "language": "en"
+                },
+                "title_fr": {
+                    "type": "text",
+                    "stored": true,
+                    "language": "fr"
+                },
+            },
+
+            // The query context
+            "search_fields": [
+                "title_en",
+                "title_fr",
+            ],
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title_en": "Running shoes", "title_fr": "Chaussures de course"},
+            {"title_en": "Walking boots", "title_fr": "Les chiens marchent"},
+        ]))?;
+
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        for ctx in ["title_en:run", "title_fr:chien", "title_fr:chaussure"] {
+            let query: QueryPayload = serde_json::from_value(serde_json::json!({
+                "query": {
+                    "normal": {"ctx": ctx},
+                },
+            }))?;
+
+            let results = index.search(query).await?;
+            assert_eq!(results.hits.len(), 1, "expected a hit for {:?}", ctx);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_fuzzy_language_stop_words_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_memory_index(
+            serde_json::json!({
+                "title_en": {
+                    "type": "text",
+                    "stored": true,
+                    "language": "en"
+                },
+            }),
+            serde_json::json!({
+                "name": "fuzzy_language_stop_words_test_index",
+                "fuzzy_require_all_terms": true,
+            }),
+        )
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title_en": "Running shoes"},
+            {"title_en": "Walking boots"},
+        ]))?;
+
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        // The stop word is removed by the field's analyzer so is not required.
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "fuzzy": {"ctx": "the running"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_stop_word_languages_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "stop_word_languages_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true
+                },
+            },
+
+            // The query context
+            "search_fields": ["title"],
+            "stop_word_languages": ["en"],
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title": "The Old Man and the Sea"},
+        ]))?;
+
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        for (ctx, expected_hits) in [("title:old", 1), ("title:the", 0)] {
+            let query: QueryPayload = serde_json::from_value(serde_json::json!({
+                "query": {
+                    "normal": {"ctx": ctx},
+                },
+            }))?;
+
+            let results = index.search(query).await?;
+            assert_eq!(results.hits.len(), expected_hits, "query {:?}", ctx);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_raw_query_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "raw_query_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true,
+                    "stemming_language": "english"
+                },
+            },
+
+            // The query context
+            "search_fields": [
+                "title",
+            ],
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title": "Running shoes"},
+            {"title": "Walking boots"},
+        ]))?;
+
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        // The indexed terms are stemmed so only the stem matches literally.
+        for (ctx, raw_query, expected) in [
+            ("running", false, 1),
+            ("running", true, 0),
+            ("run", true, 1),
+        ] {
+            let query: QueryPayload = serde_json::from_value(serde_json::json!({
+                "query": {
+                    "normal": {"ctx": ctx},
+                },
+                "raw_query": raw_query,
+            }))?;
+
+            let results = index.search(query).await?;
+            assert_eq!(results.hits.len(), expected, "query {:?}", ctx);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn id_field_upserts_documents_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "id_field_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "sku": {
+                    "type": "string",
+                    "stored": true
+                },
+                "title": {
+                    "type": "text",
+                    "stored": true
+                },
+            },
+
+            "id_field": "sku",
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"sku": "a-1", "title": "Old title"},
+            {"sku": "b-2", "title": "Other title"},
+        ]))?;
+        index.add_documents(documents).await?;
+        index.commit().await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!({
+            "sku": "a-1",
+            "title": "New title",
+        }))?;
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "*"},
+            },
+        }))?;
+
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 2);
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "old"},
+            },
+        }))?;
+
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 0);
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!({
+            "title": "Missing sku",
+        }))?;
+        assert!(index.add_documents(documents).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn id_field_multi_value_expect_err() -> Result<()> {
+        init_state();
+
+        let res = get_index_with(serde_json::json!({
+            "name": "id_field_invalid_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "tags": {
+                    "type": "string",
+                    "stored": true,
+                    "multi": true
+                },
+            },
+
+            "id_field": "tags",
+        }))
+        .await;
+
+        assert!(res.is_err());
+
+        Ok(())
+    }
+
+    fn field_options_declaration(
+        name: &str,
+        field: serde_json::Value,
+    ) -> Result<IndexDeclaration> {
+        let dec = serde_json::from_value(serde_json::json!({
+            "name": name,
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": field,
+            },
+        }))?;
+
+        Ok(dec)
+    }
+
+    #[tokio::test]
+    async fn field_options_contradictions_expect_err() -> Result<()> {
+        init_state();
+
+        let invalid = [
+            serde_json::json!({"type": "text", "fast": true}),
+            serde_json::json!({"type": "string", "fast": true}),
+            serde_json::json!({"type": "text", "tokenizer": "raw", "stemming_language": "english"}),
+            serde_json::json!({"type": "text", "indexed": false, "tokenizer": "default"}),
+            serde_json::json!({"type": "text", "language": "en", "stemming_language": "english"}),
+            serde_json::json!({"type": "text", "indexed": false, "language": "en"}),
+            serde_json::json!({"type": "u64", "indexed": false, "fieldnorms": true}),
+        ];
+
+        for field in invalid {
+            let dec = field_options_declaration("invalid_field_options_index", field)?;
+            assert!(dec.create_context().is_err());
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_raw_tokenizer_field_expect_ok() -> Result<()> {
+        init_state();
+
+        let dec = field_options_declaration(
+            "raw_tokenizer_test_index",
+            serde_json::json!({"type": "text", "tokenizer": "raw"}),
+        )?;
+        let index = Index::create(dec.create_context()?).await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title": "Running shoes"},
+            {"title": "Walking boots"},
+        ]))?;
+
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "term": {"ctx": "Running shoes", "fields": "title"},
+            },
+        }))?;
+
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "term": {"ctx": "running", "fields": "title"},
+            },
+        }))?;
+
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_default_analyzer_expect_ok() -> Result<()> {
+        init_state();
+
+        let dec: IndexDeclaration = serde_json::from_value(serde_json::json!({
+            "name": "default_analyzer_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "default_analyzer": "keyword",
+            "fields": {
+                "title": {
+                    "type": "text",
+                },
+                "description": {
+                    "type": "text",
+                    "tokenizer": "default",
+                },
+            },
+        }))?;
+        let index = Index::create(dec.create_context()?).await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title": "Running shoes", "description": "Running shoes"},
+        ]))?;
+
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "term": {"ctx": "Running shoes", "fields": "title"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        // The field's own tokenizer overrides the index's default analyzer.
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "term": {"ctx": "running", "fields": "description"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_extra_search_fields_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "extra_search_fields_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true,
+                },
+                "description": {
+                    "type": "text",
+                },
+                "notes": {
+                    "type": "text",
+                    "stored": true,
+                    "indexed": false,
+                },
+            },
+            "search_fields": ["title"],
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {
+                "title": "The Old Man and the Sea",
+                "description": "A fisherman hooks a giant marlin",
+                "notes": "Hemingway",
+            },
+        ]))?;
+
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        for mode in ["normal", "fuzzy"] {
+            let query: QueryPayload = serde_json::from_value(serde_json::json!({
+                "query": {mode: {"ctx": "marlin"}},
+            }))?;
+            let results = index.search(query).await?;
+            assert_eq!(results.hits.len(), 0);
+
+            let query: QueryPayload = serde_json::from_value(serde_json::json!({
+                "query": {mode: {"ctx": "marlin"}},
+                "extra_search_fields": ["description"],
+            }))?;
+            let results = index.search(query).await?;
+            assert_eq!(results.hits.len(), 1);
+        }
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {"normal": {"ctx": "Hemingway"}},
+            "extra_search_fields": ["notes"],
+        }))?;
+        let err = index.search(query).await.unwrap_err();
+        assert!(err.to_string().contains("not indexed"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn add_docs_dedup_field_expect_ok() -> Result<()> {
+        init_state();
+
+        let cases = [
+            ("replace", ["fourth", "second"]),
+            ("skip", ["first", "third"]),
+        ];
+        for (mode, expected) in cases {
+            let index = get_index_with(serde_json::json!({
+                "name": format!("dedup_{}_test_index", mode),
+
+                // Reader context
+                "reader_threads": 1,
+                "max_concurrency": 1,
+
+                // Writer context
+                "writer_buffer": 3_000_000,
+                "writer_threads": 1,
+
+                "storage_type": "memory",
+                "fields": {
+                    "title": {
+                        "type": "text",
+                        "stored": true,
+                    },
+                    "url": {
+                        "type": "string",
+                    },
+                },
+                "dedup_field": "url",
+                "dedup_mode": mode,
+            }))
+            .await?;
+
+            let documents: DocumentOptions =
+                serde_json::from_value(serde_json::json!([
+                    {"title": "first", "url": "a"},
+                    {"title": "second", "url": "a"},
+                    {"title": "third", "url": "b"},
+                ]))?;
+            index.add_documents(documents).await?;
+            index.commit().await?;
+
+            let documents: DocumentOptions =
+                serde_json::from_value(serde_json::json!([
+                    {"title": "fourth", "url": "b"},
+                ]))?;
+            index.add_documents(documents).await?;
+            index.commit().await?;
+            tokio::time::sleep(Duration::from_millis(1000)).await;
+
+            let query: QueryPayload = serde_json::from_value(serde_json::json!({
+                "query": {
+                    "normal": {"ctx": "*"},
+                },
+            }))?;
+            let results = index.search(query).await?;
+
+            let hits = serde_json::to_value(&results.hits)?;
+            let mut titles: Vec<&str> = hits
+                .as_array()
+                .expect("hits array")
+                .iter()
+                .map(|hit| hit["doc"]["title"].as_str().expect("get title"))
+                .collect();
+            titles.sort_unstable();
+            assert_eq!(titles, expected, "dedup mode {}", mode);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reindex_from_expect_ok() -> Result<()> {
+        init_state();
+
+        let source = get_basic_index(false).await?;
+        add_documents(&source).await?;
+
+        let dest = get_index_with(serde_json::json!({
+            "name": "reindex_dest_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "name": {
+                    "type": "text",
+                    "stored": true,
+                },
+                "category": {
+                    "type": "facet",
+                    "stored": true,
+                },
+            },
+            "strict": true,
+        }))
+        .await?;
+
+        let mut field_mappings = HashMap::new();
+        field_mappings.insert("title".to_string(), "name".to_string());
+
+        // Only the second document has a count which the destination rejects.
+        let report = dest.reindex_from(&source, field_mappings).await?;
+        assert_eq!(report.read, NUM_DOCS);
+        assert_eq!(report.copied, 2);
+        assert_eq!(report.failed, 1);
+        assert!(report.failures[0].reason.contains("count"));
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "*"},
+            },
+        }))?;
+        let results = dest.search(query).await?;
+
+        let hits = serde_json::to_value(&results.hits)?;
+        let hits = hits.as_array().expect("hits array");
+        assert_eq!(hits.len(), 2);
+        assert!(hits.iter().all(|hit| hit["doc"]["name"].is_string()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn document_ttl_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "document_ttl_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true,
+                },
+                "created": {
+                    "type": "date",
+                    "indexed": true,
+                },
+            },
+            "document_ttl": 1,
+            "ttl_field": "created",
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title": "expired", "created": "2000-01-01T00:00:00Z"},
+            {"title": "alive", "created": "2100-01-01T00:00:00Z"},
+        ]))?;
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(3000)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "*"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+
+        let hits = serde_json::to_value(&results.hits)?;
+        assert_eq!(hits.as_array().map(|hits| hits.len()), Some(1));
+        assert_eq!(hits[0]["doc"]["title"], "alive");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn oversized_terms_truncate_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "oversized_terms_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true,
+                },
+                "tag": {
+                    "type": "string",
+                    "stored": true,
+                },
+            },
+            "oversized_terms": "truncate",
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title": "oversized", "tag": "a".repeat(70_000)},
+        ]))?;
+        let oversized = index.add_documents(documents).await?;
+        assert_eq!(oversized, 1);
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(2000)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "oversized"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+
+        let hits = serde_json::to_value(&results.hits)?;
+        assert_eq!(hits.as_array().map(|hits| hits.len()), Some(1));
+        assert_eq!(
+            hits[0]["doc"]["tag"].as_str().map(|tag| tag.len()),
+            Some(70_000),
+        );
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "a".repeat(70_000)},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn coalesced_commits_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "coalesced_commits_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+            "commit_coalesce_window": 200,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true,
+                },
+            },
+        }))
+        .await?;
+
+        // Each commit adds a document before committing, if the commits were
+        // not coalesced each would create its own segment.
+        let index = &index;
+        let add_and_commit = move || async move {
+            let document: DocumentOptions =
+                serde_json::from_value(serde_json::json!({"title": "coalesced"}))?;
+            index.add_documents(document).await?;
+            index.commit().await
+        };
+
+        let (first, second) = tokio::join!(add_and_commit(), add_and_commit());
+        first?;
+        second?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let diagnostics = index.diagnostics().await?;
+        assert_eq!(diagnostics.segments.len(), 1);
+        assert_eq!(diagnostics.segments[0].num_docs, 2);
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "coalesced"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn result_field_order_expect_ok() -> Result<()> {
+        init_state();
+
+        let declaration = |order: serde_json::Value| {
+            serde_json::json!({
+                "name": "result_field_order_test_index",
+
+                // Reader context
+                "reader_threads": 1,
+                "max_concurrency": 1,
+
+                // Writer context
+                "writer_buffer": 3_000_000,
+                "writer_threads": 1,
+
+                "storage_type": "memory",
+                "fields": {
+                    "title": {
+                        "type": "text",
+                        "stored": true,
+                    },
+                    "author": {
+                        "type": "string",
+                        "stored": true,
+                    },
+                    "year": {
+                        "type": "u64",
+                        "stored": true,
+                    },
+                },
+                "result_field_order": order,
+            })
+        };
+
+        assert!(get_index_with(declaration(serde_json::json!(["missing"])))
+            .await
+            .is_err());
+
+        let index =
+            get_index_with(declaration(serde_json::json!(["year", "title"]))).await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title": "ordered", "author": "someone", "year": 2021},
+        ]))?;
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "ordered"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        let hit = serde_json::to_string(&results.hits[0])?;
+        let position = |name: &str| hit.find(&format!("\"{}\":", name));
+        assert!(position("year") < position("title"));
+        assert!(position("title") < position("author"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn include_nulls_expect_ok() -> Result<()> {
+        init_state();
+
+        let declaration = |include_nulls: bool| {
+            serde_json::json!({
+                "name": "include_nulls_test_index",
+
+                // Reader context
+                "reader_threads": 1,
+                "max_concurrency": 1,
+
+                // Writer context
+                "writer_buffer": 3_000_000,
+                "writer_threads": 1,
+
+                "storage_type": "memory",
+                "fields": {
+                    "title": {
+                        "type": "text",
+                        "stored": true,
+                    },
+                    "year": {
+                        "type": "u64",
+                        "stored": true,
+                    },
+                },
+                "include_nulls": include_nulls,
+            })
+        };
+
+        for &include_nulls in [true, false].iter() {
+            let index = get_index_with(declaration(include_nulls)).await?;
+
+            let documents: DocumentOptions =
+                serde_json::from_value(serde_json::json!([
+                    {"title": "missing year"},
+                ]))?;
+            index.add_documents(documents).await?;
+            index.commit().await?;
+            tokio::time::sleep(Duration::from_millis(1000)).await;
+
+            let query: QueryPayload = serde_json::from_value(serde_json::json!({
+                "query": {
+                    "normal": {"ctx": "missing"},
+                },
+            }))?;
+            let results = index.search(query).await?;
+            assert_eq!(results.hits.len(), 1);
+
+            let hit = serde_json::to_value(&results.hits[0])?;
+            assert_eq!(hit["doc"].get("year").is_some(), include_nulls);
+            if include_nulls {
+                assert!(hit["doc"]["year"].is_null());
+            }
+
+            index.destroy().await?;
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_compound_field_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "compound_field_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true,
+                },
+                "description": {
+                    "type": "text",
+                    "stored": true,
+                },
+                "all": {
+                    "type": "text",
+                    "stored": false,
+                },
+            },
+            "compound_fields": {
+                "all": ["title", "description"],
+            },
+            "default_search_field": "all",
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title": "hello", "description": "world"},
+            {"title": "goodbye", "description": "moon"},
+        ]))?;
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "world"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        let hit = serde_json::to_value(&results.hits[0])?;
+        assert_eq!(hit["doc"]["title"], "hello");
+
+        index.destroy().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn ingest_pipeline_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "ingest_pipeline_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "body": {
+                    "type": "text",
+                    "stored": true,
+                },
+                "first": {
+                    "type": "string",
+                    "stored": true,
+                },
+                "last": {
+                    "type": "string",
+                    "stored": true,
+                },
+                "full_name": {
+                    "type": "text",
+                    "stored": true,
+                },
+            },
+            "search_fields": ["full_name"],
+            "ingest_pipeline": [
+                {"type": "html_strip", "field": "body"},
+                {"type": "lowercase", "field": "body"},
+                {"type": "trim", "field": "first"},
+                {"type": "concat", "fields": ["first", "last"], "target": "full_name"},
+            ],
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!({
+            "body": "<p>Hello <b>World</b> &amp; Friends</p>",
+            "first": "  Ada ",
+            "last": "Lovelace",
+        }))?;
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "lovelace"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        let hit = serde_json::to_value(&results.hits[0])?;
+        assert_eq!(hit["doc"]["body"], "hello world & friends");
+        assert_eq!(hit["doc"]["first"], "Ada");
+        assert_eq!(hit["doc"]["full_name"], "Ada Lovelace");
+
+        index.destroy().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_default_search_field_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "default_search_field_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true,
+                },
+                "description": {
+                    "type": "text",
+                    "stored": true,
+                },
+            },
+            "default_search_field": "title",
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title": "hello", "description": "world"},
+            {"title": "world", "description": "hello"},
+        ]))?;
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "hello"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        let hit = serde_json::to_value(&results.hits[0])?;
+        assert_eq!(hit["doc"]["title"], "hello");
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "description:hello"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        let hit = serde_json::to_value(&results.hits[0])?;
+        assert_eq!(hit["doc"]["title"], "world");
+
+        index.destroy().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn output_precision_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "output_precision_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true,
+                },
+                "price": {
+                    "type": "f64",
+                    "stored": true,
+                    "indexed": true,
+                    "output_precision": 2,
+                },
+                "weight": {
+                    "type": "f64",
+                    "stored": true,
+                },
+            },
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {
+                "title": "hello world",
+                "price": 19.989999999,
+                "weight": 1.23456,
+            },
+            {
+                "title": "huge price",
+                "price": 1.7e308,
+                "weight": 1.0,
+            },
+        ]))?;
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "hello"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        let hit = serde_json::to_value(&results.hits[0])?;
+        assert_eq!(hit["doc"]["price"], 19.99);
+        assert_eq!(hit["doc"]["weight"], 1.23456);
+
+        // Rounding would overflow so the value is returned as is.
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "huge"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        let hit = serde_json::to_value(&results.hits[0])?;
+        assert_eq!(hit["doc"]["price"], 1.7e308);
+
+        index.destroy().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn add_docs_strict_validation_expect_err() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "strict_validation_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true
+                },
+                "count": {
+                   "type": "u64",
+                   "stored": true
+                },
+            },
+
+            "strict": true,
+        }))
+        .await?;
+
+        let document: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title": "The Old Man and the Sea", "count": 1},
+            {"title": "The Old Man and the Sea 2", "count": "many", "author": "Ernest"},
+        ]))?;
+
+        let err = index
+            .add_documents(document)
+            .await
+            .expect_err("document should be rejected");
+        let err = err
+            .downcast_ref::<DocumentValidationError>()
+            .expect("get validation error");
+        assert_eq!(err.document, 1);
+        assert_eq!(err.unknown_fields, vec!["author".to_string()]);
+        assert!(err.invalid_fields.contains_key("count"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_fuzzy_term_limit_expect_err() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "fuzzy_term_limit_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true
+                },
+                "description": {
+                    "type": "text",
+                    "stored": true
+                },
+            },
+
+            "max_fuzzy_terms": 4,
+        }))
+        .await?;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "fuzzy": {"ctx": "old man"},
+            },
+        }))?;
+        assert!(index.search(query).await.is_ok());
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "fuzzy": {"ctx": "the old man and the sea"},
+            },
+        }))?;
+        assert!(index.search(query).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_field_aliases_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "field_aliases_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true
+                },
+                "count": {
+                   "type": "u64",
+                   "stored": true,
+                   "indexed": true,
+                   "fast": true
+                },
+            },
+
+            "field_aliases": {
+                "name": "title",
+                "total": "count",
+            },
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title": "The Old Man and the Sea", "count": 1},
+            {"title": "Of Mice and Men", "count": 2},
+        ]))?;
+
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "name:sea"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "term": {"ctx": 2, "fields": "total"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "*"},
+            },
+            "order_by": "total",
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_queryable_fields_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "queryable_fields_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true
+                },
+                "_admin_notes": {
+                    "type": "text",
+                    "stored": false
+                },
+                "admin-tags": {
+                    "type": "text",
+                    "stored": false
+                },
+            },
+
+            "search_fields": ["title"],
+            "queryable_fields": ["title"],
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {
+                "title": "The Old Man and the Sea",
+                "_admin_notes": "hidden",
+                "admin-tags": "hidden",
+            },
+        ]))?;
+
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "title:sea"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "sea OR _admin_notes:hidden"},
+            },
+        }))?;
+        assert!(index.search(query).await.is_err());
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "sea OR admin-tags:hidden"},
+            },
+        }))?;
+        assert!(index.search(query).await.is_err());
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "sea -title:man"},
+            },
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 0);
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "term": {"ctx": "hidden", "fields": ["title", "_admin_notes"]},
+            },
+        }))?;
+        assert!(index.search(query).await.is_err());
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "hidden"},
+            },
+            "extra_search_fields": ["_admin_notes"],
+        }))?;
+        assert!(index.search(query).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_default_operator_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+        add_documents(&index).await?;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "old mice"},
+            },
+            "default_operator": "or",
+        }))?;
+        let results = index.search(query).await?;
+        assert!(!results.hits.is_empty());
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "old mice"},
+            },
+            "default_operator": "and",
+        }))?;
+        let results = index.search(query).await?;
+        assert!(results.hits.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_minimum_should_match_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_index_with(serde_json::json!({
+            "name": "minimum_should_match_test_index",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 3_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "memory",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true,
+                },
+            },
+        }))
+        .await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title": "red leather office chair"},
+            {"title": "red office desk"},
+            {"title": "blue chair"},
+            {"title": "green sofa"},
+        ]))?;
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(2000)).await;
+
+        for (minimum, expected) in [
+            (serde_json::json!(1), 3),
+            (serde_json::json!(2), 2),
+            (serde_json::json!(4), 1),
+            (serde_json::json!("50%"), 2),
+            (serde_json::json!("100%"), 1),
+        ] {
+            let query: QueryPayload = serde_json::from_value(serde_json::json!({
+                "query": {
+                    "normal": {"ctx": "red leather office chair"},
+                },
+                "default_operator": "or",
+                "minimum_should_match": minimum,
+            }))?;
+            let results = index.search(query).await?;
+            assert_eq!(results.hits.len(), expected, "minimum {}", minimum);
+        }
+
+        let query = serde_json::from_value::<QueryPayload>(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "red"},
+            },
+            "minimum_should_match": "150%",
+        }));
+        assert!(query.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_fuzzy_minimum_should_match_synonyms_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_memory_index(
+            serde_json::json!({
+                "title": {
+                    "type": "text",
+                    "stored": true,
+                },
+            }),
+            serde_json::json!({
+                "name": "fuzzy_minimum_should_match_synonyms_test_index",
+            }),
+        )
+        .await?;
+
+        index.add_synonyms(vec!["chair,seat".into()]).await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title": "red chair"},
+            {"title": "chair seat"},
+        ]))?;
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        // A synonym counts towards the minimum as the word it replaces.
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "fuzzy": {"ctx": "red chair"},
+            },
+            "minimum_should_match": 2,
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_highlight_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+        add_documents(&index).await?;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "sea"},
+            },
+            "highlight_fields": ["title"],
+            "highlight_pre_tag": "<b>",
+            "highlight_post_tag": "</b>",
+            "max_snippets_per_field": 1,
+        }))?;
+        let results = index.search(query).await?;
+        assert!(!results.hits.is_empty());
+
+        for hit in results.hits.iter() {
+            let highlights = hit.highlights.as_ref().expect("get highlights");
+            let snippets = highlights.get("title").expect("get title snippets");
+            assert_eq!(snippets.len(), 1);
+            assert!(snippets[0].contains("<b>Sea</b>"));
+        }
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "sea"},
+            },
+            "highlight_fields": ["description"],
+        }))?;
+        assert!(index.search(query).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_rescore_script_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+
+        let documents: DocumentOptions = serde_json::from_value(serde_json::json!([
+            {"title": "The Old Man and the Sea", "count": 1},
+            {"title": "The Old Man and the Sea 2", "count": 3},
+            {"title": "The Old Man and the Sea 3", "count": 2},
+        ]))?;
+
+        index.add_documents(documents).await?;
+        index.commit().await?;
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "sea"},
+            },
+            "rescore_script": "count",
+        }))?;
+        let results = index.search(query).await?;
+
+        let scores: Vec<Option<f32>> =
+            results.hits.iter().map(|hit| hit.score).collect();
+        assert_eq!(scores, vec![Some(3.0), Some(2.0), Some(1.0)]);
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "sea"},
+            },
+            "limit": 1,
+            "offset": 1,
+            "rescore_script": "count",
+            "rescore_window": 3,
+        }))?;
+        let results = index.search(query).await?;
+        assert_eq!(results.hits.len(), 1);
+        assert_eq!(results.hits[0].score, Some(2.0));
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "sea"},
+            },
+            "limit": 2,
+            "rescore_script": "count",
+            "rescore_window": 1,
+        }))?;
+        assert!(index.search(query).await.is_err());
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "sea"},
+            },
+            "rescore_script": "score +",
+        }))?;
+        assert!(index.search(query).await.is_err());
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "sea"},
+            },
+            "limit": 1_001,
+            "rescore_script": "score",
+        }))?;
+        assert!(index.search(query).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn explain_query_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "term": {"ctx": 3, "fields": "count"},
+            },
+        }))?;
+        let explanation = index.explain_query(query).await?;
+        assert_eq!(explanation.fields, vec!["count".to_string()]);
+        assert!(explanation.query.contains("TermQuery"));
+
+        let query: QueryPayload = serde_json::from_value(serde_json::json!({
+            "query": {
+                "normal": {"ctx": "title:(old"},
+            },
+        }))?;
+        assert!(index.explain_query(query).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn set_boost_fields_expect_ok() -> Result<()> {
+        init_state();
+
+        let index = get_basic_index(false).await?;
+
+        let query = || -> Result<QueryPayload> {
+            Ok(serde_json::from_value(serde_json::json!({
+                "query": {
+                    "fuzzy": {"ctx": "old"},
+                },
+            }))?)
+        };
+
+        let explanation = index.explain_query(query()?).await?;
+        assert!(!explanation.query.contains("Boost"));
+
+        let mut boosts = HashMap::new();
+        boosts.insert("title".to_string(), 2.0);
+        index.set_boost_fields(boosts)?;
+
+        let explanation = index.explain_query(query()?).await?;
+        assert!(explanation.query.contains("Boost"));
+
+        let mut boosts = HashMap::new();
+        boosts.insert("count".to_string(), 2.0);
+        assert!(index.set_boost_fields(boosts).is_err());
+
+        Ok(())
+    }
 }
@@ -5,22 +5,33 @@ use std::sync::Arc;
 
 use aexecutor::SearcherExecutorPool;
 
+mod cardinality;
 mod corrections;
+mod filter_cache;
+mod geo;
 mod helpers;
+mod highlight;
 mod index;
+mod pipeline;
 mod query;
 mod reader;
+mod rescore;
 mod schema;
+mod should_match;
 mod stop_words;
 mod storage;
 pub mod structures;
+mod suggest;
 mod synonyms;
 mod writer;
 
+pub use aexecutor::{init_shared_executor_pool, PoolSaturated};
 pub use helpers::cr32_hash;
-pub use index::Index;
-pub use query::DocumentId;
-pub use reader::{QueryPayload, QueryResults};
+pub use index::{Index, IndexReadOnly};
+pub use query::{DocumentId, QueryExplanation};
+pub use reader::{ExportLimits, QueryPayload, QueryResults};
 pub use storage::StorageBackend;
+pub use suggest::{SpellCheck, Suggestion};
+pub use writer::WriterQueueFull;
 
 pub(crate) type ReaderExecutor = Arc<SearcherExecutorPool>;
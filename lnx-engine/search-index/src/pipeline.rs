@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::structures::{DocumentValue, DocumentValueOptions};
+
+fn default_separator() -> String {
+    " ".to_string()
+}
+
+/// A transformation applied to the fields of each document added to an
+/// index before it is validated and indexed.
+///
+/// Only text values are transformed, any other values are left as is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum Processor {
+    /// Lowercases the values of the field.
+    Lowercase { field: String },
+
+    /// Removes any HTML tags from the values of the field, decoding the
+    /// common HTML entities e.g. `&amp;`.
+    HtmlStrip { field: String },
+
+    /// Removes any leading and trailing whitespace from the values of
+    /// the field.
+    Trim { field: String },
+
+    /// Joins the values of the fields together into a single value set
+    /// as the target field.
+    ///
+    /// The values are joined in the order the fields are given, separated
+    /// by a single space unless a separator is given.
+    Concat {
+        fields: Vec<String>,
+        target: String,
+
+        #[serde(default = "default_separator")]
+        separator: String,
+    },
+}
+
+impl Processor {
+    /// The names of the fields the processor reads or writes.
+    pub(crate) fn fields(&self) -> Vec<&str> {
+        match self {
+            Self::Lowercase { field }
+            | Self::HtmlStrip { field }
+            | Self::Trim { field } => {
+                vec![field.as_str()]
+            },
+            Self::Concat { fields, target, .. } => fields
+                .iter()
+                .map(|field| field.as_str())
+                .chain(std::iter::once(target.as_str()))
+                .collect(),
+        }
+    }
+
+    /// Applies the processor to the fields of the document.
+    pub(crate) fn apply(&self, document: &mut BTreeMap<String, DocumentValueOptions>) {
+        match self {
+            Self::Lowercase { field } => map_text(document, field, |v| v.to_lowercase()),
+            Self::HtmlStrip { field } => map_text(document, field, |v| strip_html(&v)),
+            Self::Trim { field } => map_text(document, field, |v| v.trim().to_string()),
+            Self::Concat {
+                fields,
+                target,
+                separator,
+            } => concat_fields(document, fields, target, separator),
+        }
+    }
+}
+
+/// Joins the values of the fields together into a single value set as the
+/// target field.
+///
+/// The target is left as is if none of the fields have a value.
+pub(crate) fn concat_fields(
+    document: &mut BTreeMap<String, DocumentValueOptions>,
+    fields: &[String],
+    target: &str,
+    separator: &str,
+) {
+    let values: Vec<String> = fields
+        .iter()
+        .filter_map(|field| document.get(field))
+        .flat_map(|values| match values {
+            DocumentValueOptions::Single(value) => vec![value.as_string()],
+            DocumentValueOptions::Many(values) => {
+                values.iter().map(|value| value.as_string()).collect()
+            },
+        })
+        .collect();
+
+    if values.is_empty() {
+        return;
+    }
+
+    document.insert(
+        target.to_string(),
+        DocumentValueOptions::Single(DocumentValue::Text(values.join(separator))),
+    );
+}
+
+/// Replaces each text value of the field with the result of the function.
+fn map_text(
+    document: &mut BTreeMap<String, DocumentValueOptions>,
+    field: &str,
+    func: impl Fn(String) -> String,
+) {
+    let map = |value: &mut DocumentValue| {
+        if let DocumentValue::Text(text) = value {
+            *text = func(std::mem::take(text));
+        }
+    };
+
+    match document.get_mut(field) {
+        Some(DocumentValueOptions::Single(value)) => map(value),
+        Some(DocumentValueOptions::Many(values)) => values.iter_mut().for_each(map),
+        None => {},
+    }
+}
+
+/// Removes any HTML tags from the text and decodes the common entities.
+fn strip_html(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut in_tag = false;
+
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => stripped.push(c),
+            _ => {},
+        }
+    }
+
+    stripped
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
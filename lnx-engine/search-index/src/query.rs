@@ -1,12 +1,13 @@
 use core::fmt;
+use std::collections::BTreeSet;
 use std::convert::TryInto;
 use std::sync::Arc;
 
 use anyhow::{anyhow, Error, Result};
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
 use serde::de::{MapAccess, SeqAccess, Visitor};
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize};
 use tantivy::collector::TopDocs;
 use tantivy::query::{
     BooleanQuery,
@@ -27,10 +28,19 @@ use tantivy::schema::{
     IndexRecordOption,
     Schema,
 };
-use tantivy::tokenizer::{LowerCaser, SimpleTokenizer, TextAnalyzer};
+use tantivy::tokenizer::{
+    LowerCaser,
+    RawTokenizer,
+    SimpleTokenizer,
+    TextAnalyzer,
+    TokenizerManager,
+};
 use tantivy::{DateTime, Index, Score, Term};
 
 use crate::corrections::SymSpellCorrectionManager;
+use crate::filter_cache::{CachedFilterQuery, FilterCache};
+use crate::schema::is_stemming_tokenizer;
+use crate::should_match::{MinShouldMatchQuery, MinimumShouldMatch};
 use crate::stop_words::StopWordManager;
 use crate::structures::DocumentValue;
 use crate::synonyms::SynonymsManager;
@@ -41,8 +51,14 @@ pub type DocumentId = u64;
 pub(crate) struct QueryContext {
     pub(crate) set_conjunction_by_default: bool,
     pub(crate) use_fast_fuzzy: bool,
+    pub(crate) fast_fuzzy_fallback: bool,
     pub(crate) strip_stop_words: bool,
+    pub(crate) fuzzy_require_all_terms: bool,
+    pub(crate) max_fuzzy_terms: usize,
+    pub(crate) field_aliases: HashMap<String, String>,
+    pub(crate) queryable_fields: Option<HashSet<String>>,
     pub(crate) id_field: Field,
+    pub(crate) default_search_field: Option<Field>,
     pub(crate) default_search_fields: Vec<(Field, Score)>,
     pub(crate) fuzzy_search_fields: Vec<(Field, Score)>,
 }
@@ -62,6 +78,14 @@ pub struct QueryData {
     /// should be present or must be not present.
     #[serde(default)]
     occur: Occur,
+
+    /// If the query only filters the results rather than scoring them.
+    ///
+    /// The documents matching filters are cached by the index's filter
+    /// cache if it is enabled, so repeating the same filter across searches
+    /// avoids re-evaluating it until the next commit.
+    #[serde(default)]
+    filter: bool,
 }
 
 impl QueryData {
@@ -72,6 +96,7 @@ impl QueryData {
                 fields: FieldSelector::Single(field),
             },
             occur,
+            filter: false,
         }
     }
 }
@@ -89,7 +114,7 @@ impl QueryData {
 /// This is only applicable to the non-fast-fuzzy variant of the system.
 /// Due to the nature of fast-fuzzy this is a non-issue/not something we want to leave to the
 /// user.
-#[derive(Debug, Copy, Clone, Deserialize)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct FuzzyConfig {
     #[serde(default = "FuzzyConfig::default_min_length_d1")]
     min_length_distance1: usize,
@@ -121,7 +146,7 @@ impl FuzzyConfig {
     }
 }
 
-#[derive(Debug, Copy, Clone, Deserialize)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct MoreLikeThisConfig {
     #[serde(default = "MoreLikeThisConfig::min_doc_frequency")]
     min_doc_frequency: u64,
@@ -250,6 +275,18 @@ pub enum FieldSelector {
     DefaultFields,
 }
 
+impl QueryKind {
+    /// The name of the query mode, used for tracing.
+    pub(crate) fn mode(&self) -> &'static str {
+        match self {
+            Self::Fuzzy { .. } => "fuzzy",
+            Self::Normal { .. } => "normal",
+            Self::MoreLikeThis { .. } => "more-like-this",
+            Self::Term { .. } => "term",
+        }
+    }
+}
+
 impl Default for FieldSelector {
     fn default() -> Self {
         Self::DefaultFields
@@ -282,6 +319,18 @@ impl Default for Occur {
     }
 }
 
+/// The operator used to combine the terms of a normal query which do not
+/// have an explicit operator.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Operator {
+    /// Every term must match.
+    And,
+
+    /// Any term can match.
+    Or,
+}
+
 impl Occur {
     fn as_tantivy_value(&self) -> tantivy::query::Occur {
         use tantivy::query::Occur;
@@ -294,6 +343,16 @@ impl Occur {
     }
 }
 
+/// A built query which has not been executed.
+#[derive(Debug, Serialize)]
+pub struct QueryExplanation {
+    /// The debug representation of the built tantivy query.
+    pub(crate) query: String,
+
+    /// The names of the fields the query searches.
+    pub(crate) fields: Vec<String>,
+}
+
 /// A helper selector that allows either individual querying or
 /// multi queries.
 #[derive(Debug)]
@@ -309,6 +368,16 @@ pub enum QuerySelector {
 }
 
 impl QuerySelector {
+    /// The name of the query mode, used for tracing.
+    ///
+    /// Multi queries are always reported as `multi` regardless of their parts.
+    pub(crate) fn mode(&self) -> &'static str {
+        match self {
+            Self::Single(query) => query.kind.mode(),
+            Self::Multi(_) => "multi",
+        }
+    }
+
     /// Consumes the selector and returns a list of queries to process.
     fn into_queries(self) -> Vec<QueryData> {
         match self {
@@ -347,6 +416,7 @@ impl<'de> Deserialize<'de> for QuerySelector {
                         cfg: Default::default(),
                     },
                     occur: Occur::default(),
+                    filter: false,
                 }))
             }
 
@@ -357,6 +427,7 @@ impl<'de> Deserialize<'de> for QuerySelector {
                         cfg: Default::default(),
                     },
                     occur: Occur::default(),
+                    filter: false,
                 }))
             }
 
@@ -400,14 +471,35 @@ pub(crate) struct QueryBuilder {
     /// The schema of the index the handler belongs to.
     schema: Schema,
 
-    /// The standard tantivy parser for `QueryKind::Normal` kinds.
-    query_parser: Arc<QueryParser>,
+    /// The standard tantivy parser for `QueryKind::Normal` kinds which
+    /// combines terms using AND.
+    conjunction_parser: Arc<QueryParser>,
+
+    /// The standard tantivy parser for `QueryKind::Normal` kinds which
+    /// combines terms using OR.
+    disjunction_parser: Arc<QueryParser>,
 
     /// A thread pool that produces awaitable futures while executing.
     pool: crate::ReaderExecutor,
 
     /// A basic word tokenizers for fuzzy queries.
     tokenizer: TextAnalyzer,
+
+    /// The stemming analyzers of any stemmed fuzzy search fields.
+    ///
+    /// Fuzzy query terms are stemmed with this so they match the indexed terms.
+    stemmers: HashMap<Field, TextAnalyzer>,
+
+    /// The index's tokenizers, used when building parsers for queries
+    /// searching extra fields.
+    tokenizers: TokenizerManager,
+
+    /// The index's tokenizer names each mapped to the raw tokenizer, used
+    /// when building parsers for raw queries.
+    raw_tokenizers: TokenizerManager,
+
+    /// The cache of the documents matching filter queries.
+    filter_cache: Arc<FilterCache>,
 }
 
 impl QueryBuilder {
@@ -419,19 +511,89 @@ impl QueryBuilder {
         synonyms: SynonymsManager,
         index: &Index,
         pool: crate::ReaderExecutor,
+        filter_cache: Arc<FilterCache>,
     ) -> Self {
-        let parser = get_parser(&ctx, index);
+        let schema = index.schema();
+        let tokenizers = index.tokenizers().clone();
+        let conjunction_parser = get_parser(
+            &ctx.default_search_fields,
+            ctx.default_search_field.map(|field| vec![field]),
+            &schema,
+            &tokenizers,
+            true,
+        );
+        let disjunction_parser = get_parser(
+            &ctx.default_search_fields,
+            ctx.default_search_field.map(|field| vec![field]),
+            &schema,
+            &tokenizers,
+            false,
+        );
         let tokenizer = TextAnalyzer::from(SimpleTokenizer).filter(LowerCaser);
+        let stemmers = get_stemmers(&ctx.fuzzy_search_fields, &schema, &tokenizers);
+        let raw_tokenizers = get_raw_tokenizers(&schema);
 
         Self {
             ctx: Arc::new(ctx),
             corrections,
             stop_words,
             synonyms,
-            query_parser: Arc::new(parser),
+            conjunction_parser: Arc::new(conjunction_parser),
+            disjunction_parser: Arc::new(disjunction_parser),
             pool,
-            schema: index.schema(),
+            schema,
             tokenizer,
+            stemmers,
+            tokenizers,
+            raw_tokenizers,
+            filter_cache,
+        }
+    }
+
+    /// Gets the fields searched by normal and fuzzy queries along with
+    /// their boost factor.
+    pub(crate) fn search_fields(&self) -> (&[(Field, Score)], &[(Field, Score)]) {
+        (
+            &self.ctx.default_search_fields,
+            &self.ctx.fuzzy_search_fields,
+        )
+    }
+
+    /// Creates a copy of the builder searching the given fields.
+    ///
+    /// The query parsers are rebuilt so any new boosts are applied.
+    pub(crate) fn with_search_fields(
+        &self,
+        default_search_fields: Vec<(Field, Score)>,
+        fuzzy_search_fields: Vec<(Field, Score)>,
+    ) -> Self {
+        let mut ctx = self.ctx.as_ref().clone();
+        ctx.default_search_fields = default_search_fields;
+        ctx.fuzzy_search_fields = fuzzy_search_fields;
+
+        let conjunction_parser = get_parser(
+            &ctx.default_search_fields,
+            ctx.default_search_field.map(|field| vec![field]),
+            &self.schema,
+            &self.tokenizers,
+            true,
+        );
+        let disjunction_parser = get_parser(
+            &ctx.default_search_fields,
+            ctx.default_search_field.map(|field| vec![field]),
+            &self.schema,
+            &self.tokenizers,
+            false,
+        );
+        let stemmers =
+            get_stemmers(&ctx.fuzzy_search_fields, &self.schema, &self.tokenizers);
+
+        Self {
+            ctx: Arc::new(ctx),
+            conjunction_parser: Arc::new(conjunction_parser),
+            disjunction_parser: Arc::new(disjunction_parser),
+            stemmers,
+            ..self.clone()
         }
     }
 
@@ -445,18 +607,74 @@ impl QueryBuilder {
         self.synonyms.get_all_synonyms()
     }
 
+    /// Removes the cached documents of every filter.
+    #[inline]
+    pub(crate) fn clear_filter_cache(&self) {
+        self.filter_cache.clear()
+    }
+
     /// Builds a query from the given query selector.
+    ///
+    /// If an operator is given it overrides the index's default operator
+    /// for any normal queries.
+    ///
+    /// If a minimum should match is given, documents must match at least
+    /// that many of the words of fuzzy queries and of the optional clauses
+    /// of normal queries.
+    ///
+    /// If `raw_query` is set the terms of normal queries are matched as
+    /// they are written rather than by each field's tokenizer.
+    ///
+    /// Any extra fields are searched alongside the index's search fields
+    /// by fuzzy, normal and default field term queries.
     // TODO add-back #[instrument(name = "query-builder", level = "trace", skip_all)]
     pub(crate) async fn build_query(
         &self,
         selector: QuerySelector,
+        default_operator: Option<Operator>,
+        minimum_should_match: Option<MinimumShouldMatch>,
+        raw_query: bool,
+        extra_fields: &[String],
     ) -> Result<Box<dyn Query>> {
+        let extra_fields = self.get_extra_search_fields(extra_fields)?;
         let queries = selector.into_queries();
 
         let mut parts = Vec::with_capacity(queries.len());
         for query in queries {
             let occur = query.occur.as_tantivy_value();
-            let built = self.get_query_from_payload(query).await?;
+            let filter_key = if query.filter && self.filter_cache.is_enabled() {
+                Some(self.filter_key(
+                    &query,
+                    default_operator,
+                    minimum_should_match,
+                    raw_query,
+                    &extra_fields,
+                ))
+            } else {
+                None
+            };
+            let is_filter = query.filter;
+            let mut built = self
+                .get_query_from_payload(
+                    query,
+                    default_operator,
+                    minimum_should_match,
+                    raw_query,
+                    &extra_fields,
+                )
+                .await?;
+
+            // Without a cache the filter is evaluated directly rather than
+            // building bitsets that would never be stored.
+            if let Some(key) = filter_key {
+                built = Box::new(CachedFilterQuery::new(
+                    key,
+                    built,
+                    self.filter_cache.clone(),
+                ));
+            } else if is_filter {
+                built = Box::new(BoostQuery::new(built, 0.0));
+            }
 
             parts.push((occur, built));
         }
@@ -464,6 +682,166 @@ impl QueryBuilder {
         Ok(Box::new(BooleanQuery::new(parts)))
     }
 
+    /// Builds a query from the given query selector without executing it.
+    ///
+    /// This returns the built query along with the fields it searches.
+    pub(crate) async fn explain_query(
+        &self,
+        selector: QuerySelector,
+        default_operator: Option<Operator>,
+        minimum_should_match: Option<MinimumShouldMatch>,
+        raw_query: bool,
+        extra_fields: &[String],
+    ) -> Result<QueryExplanation> {
+        let fields =
+            self.target_fields(&selector, &self.get_extra_search_fields(extra_fields)?);
+        let query = self
+            .build_query(
+                selector,
+                default_operator,
+                minimum_should_match,
+                raw_query,
+                extra_fields,
+            )
+            .await?;
+
+        Ok(QueryExplanation {
+            query: format!("{:?}", query),
+            fields,
+        })
+    }
+
+    /// Gets the names of the fields the given queries search.
+    ///
+    /// More-like-this queries search the fields of their reference document
+    /// so do not contribute any fields.
+    fn target_fields(
+        &self,
+        selector: &QuerySelector,
+        extra_fields: &[(Field, Score)],
+    ) -> Vec<String> {
+        let queries = match selector {
+            QuerySelector::Single(query) => std::slice::from_ref(query),
+            QuerySelector::Multi(queries) => queries.as_slice(),
+        };
+
+        let mut fields = BTreeSet::new();
+        for query in queries {
+            fields.extend(self.query_fields(&query.kind, extra_fields));
+        }
+
+        fields.into_iter().collect()
+    }
+
+    /// Gets the names of the fields the given query searches.
+    fn query_fields(
+        &self,
+        kind: &QueryKind,
+        extra_fields: &[(Field, Score)],
+    ) -> BTreeSet<String> {
+        let field_names = |fields: &[(Field, Score)]| {
+            fields
+                .iter()
+                .map(|(field, _)| self.schema.get_field_name(*field).to_string())
+                .collect::<Vec<String>>()
+        };
+
+        let resolve =
+            |name: &String| self.ctx.field_aliases.get(name).unwrap_or(name).to_string();
+
+        let mut fields = BTreeSet::new();
+        match kind {
+            QueryKind::Fuzzy { .. } => {
+                fields.extend(field_names(&self.ctx.fuzzy_search_fields));
+                fields.extend(field_names(extra_fields));
+            },
+            QueryKind::Normal { .. } => {
+                fields.extend(field_names(&self.ctx.default_search_fields));
+                fields.extend(field_names(extra_fields));
+            },
+            QueryKind::MoreLikeThis { .. } => {},
+            QueryKind::Term {
+                fields: selector, ..
+            } => match selector {
+                FieldSelector::DefaultFields => {
+                    fields.extend(field_names(&self.ctx.default_search_fields));
+                    fields.extend(field_names(extra_fields));
+                },
+                FieldSelector::Single(name) => {
+                    fields.insert(resolve(name));
+                },
+                FieldSelector::Multi(names) => fields.extend(names.iter().map(resolve)),
+                FieldSelector::MultiWithBoost(names) => {
+                    fields.extend(names.keys().map(resolve))
+                },
+            },
+        }
+
+        fields
+    }
+
+    /// Gets the key the documents matching the given filter are cached on.
+    ///
+    /// This is the filter's value along with the fields it searches and
+    /// any of the request's settings which change the documents it matches,
+    /// boosts are left out as filters do not contribute to the score.
+    fn filter_key(
+        &self,
+        query: &QueryData,
+        default_operator: Option<Operator>,
+        minimum_should_match: Option<MinimumShouldMatch>,
+        raw_query: bool,
+        extra_fields: &[(Field, Score)],
+    ) -> Arc<str> {
+        let minimum_should_match = minimum_should_match.map(|minimum| match minimum {
+            MinimumShouldMatch::Count(count) => serde_json::json!(count),
+            MinimumShouldMatch::Percent(percent) => {
+                serde_json::json!(format!("{}%", percent))
+            },
+        });
+
+        let (value, settings) = match &query.kind {
+            QueryKind::Fuzzy { ctx, cfg } => (
+                ctx,
+                serde_json::json!({
+                    "cfg": cfg,
+                    "minimum_should_match": minimum_should_match,
+                }),
+            ),
+            QueryKind::Normal { ctx } => (
+                ctx,
+                serde_json::json!({
+                    "default_operator": default_operator,
+                    "minimum_should_match": minimum_should_match,
+                    "raw_query": raw_query,
+                }),
+            ),
+            QueryKind::MoreLikeThis { ctx, cfg } => {
+                (ctx, serde_json::json!({ "cfg": cfg }))
+            },
+            QueryKind::Term { ctx, .. } => (ctx, serde_json::Value::Null),
+        };
+
+        let value = match value {
+            DocumentValue::I64(v) => serde_json::json!({ "i64": v }),
+            DocumentValue::F64(v) => serde_json::json!({ "f64": v.to_string() }),
+            DocumentValue::U64(v) => serde_json::json!({ "u64": v }),
+            DocumentValue::Datetime(v) => {
+                serde_json::json!({ "datetime": v.to_string() })
+            },
+            DocumentValue::Text(v) => serde_json::json!({ "text": v }),
+        };
+
+        let key = serde_json::json!({
+            "mode": query.kind.mode(),
+            "fields": self.query_fields(&query.kind, extra_fields),
+            "value": value,
+            "settings": settings,
+        });
+
+        Arc::from(key.to_string())
+    }
+
     /// Gets a list of suggested corrections based off of the index corpus.
     pub(crate) fn get_corrected_query_hint(&self, query: &str) -> String {
         self.corrections.correct(query)
@@ -476,15 +854,36 @@ impl QueryBuilder {
     }
 
     /// Builds a query from the given query payload.
-    async fn get_query_from_payload(&self, qry: QueryData) -> Result<Box<dyn Query>> {
+    async fn get_query_from_payload(
+        &self,
+        qry: QueryData,
+        default_operator: Option<Operator>,
+        minimum_should_match: Option<MinimumShouldMatch>,
+        raw_query: bool,
+        extra_fields: &[(Field, Score)],
+    ) -> Result<Box<dyn Query>> {
         match qry.kind {
-            QueryKind::Fuzzy { ctx: query, cfg } => self.make_fuzzy_query(query, cfg),
-            QueryKind::Normal { ctx: query } => self.make_normal_query(query),
+            QueryKind::Fuzzy { ctx: query, cfg } => {
+                self.make_fuzzy_query(query, cfg, minimum_should_match, extra_fields)
+            },
+            QueryKind::Normal { ctx: query } => {
+                let query = self.make_normal_query(
+                    query,
+                    default_operator,
+                    raw_query,
+                    extra_fields,
+                )?;
+
+                Ok(match minimum_should_match {
+                    Some(minimum) => apply_minimum_should_match(query, minimum),
+                    None => query,
+                })
+            },
             QueryKind::MoreLikeThis { ctx: query, cfg } => {
                 self.make_more_like_this_query(query, cfg).await
             },
             QueryKind::Term { ctx: query, fields } => {
-                self.make_term_query(query, fields)
+                self.make_term_query(query, fields, extra_fields)
             },
         }
     }
@@ -495,15 +894,28 @@ impl QueryBuilder {
     /// If `use_fast_fuzzy` is enabled both on server and index this will
     /// produce a fast-fuzzy query. Otherwise this will produce a feature
     /// fuzzy search.
+    ///
+    /// While the fast-fuzzy frequency dictionary is empty this falls back to
+    /// a feature fuzzy search unless `fast_fuzzy_fallback` is disabled.
     // TODO add-back #[instrument(name = "fuzzy-query", level = "trace", skip_all)]
     fn make_fuzzy_query(
         &self,
         value: DocumentValue,
         cfg: FuzzyConfig,
+        minimum_should_match: Option<MinimumShouldMatch>,
+        extra_fields: &[(Field, Score)],
     ) -> Result<Box<dyn Query>> {
         use tantivy::query::Occur;
 
-        if self.ctx.fuzzy_search_fields.is_empty() {
+        let search_fields: Vec<(Field, Score)> = self
+            .ctx
+            .fuzzy_search_fields
+            .iter()
+            .chain(extra_fields.iter())
+            .copied()
+            .collect();
+
+        if search_fields.is_empty() {
             return Err(anyhow!(
                 "no string/text fields have been marked as search fields, \
                 because of this fuzzy search has been disabled"
@@ -515,42 +927,83 @@ impl QueryBuilder {
             return Ok(Box::new(EmptyQuery {}));
         }
 
-        if self.ctx.use_fast_fuzzy {
+        let mut use_fast_fuzzy = self.ctx.use_fast_fuzzy;
+        if use_fast_fuzzy && self.ctx.fast_fuzzy_fallback && self.corrections.is_empty()
+        {
+            debug!(
+                "fast-fuzzy frequency dictionary is empty, falling back to fuzzy terms"
+            );
+            use_fast_fuzzy = false;
+        }
+
+        if use_fast_fuzzy {
             query = self.corrections.correct(&query);
         }
 
-        let mut parts: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+        // Each word is paired with the position of the input word it came
+        // from, synonyms share the position of the word they replace.
         let mut words = vec![];
         let mut tokens = self.tokenizer.token_stream(&query);
         let mut ignore_stop_words = false;
+        let mut position = 0;
 
         while let Some(token) = tokens.next() {
-            words.push(token.text.to_string());
+            words.push((position, token.text.to_string()));
 
             if let Some(synonyms) = self.synonyms.get_synonyms(&token.text) {
-                words.extend_from_slice(&synonyms);
+                words.extend(synonyms.iter().map(|synonym| (position, synonym.clone())));
             }
+
+            position += 1;
         }
 
         if self.ctx.strip_stop_words && words.len() > 1 {
-            for word in words.iter() {
-                if !self.stop_words.is_stop_word(word) {
+            for (_, word) in words.iter() {
+                if !self.stop_words.is_stop_word_or_default(word) {
                     ignore_stop_words = true;
                     break;
                 }
             }
         }
 
+        if ignore_stop_words {
+            words.retain(|(_, word)| !self.stop_words.is_stop_word_or_default(word));
+        }
+
+        let num_terms = words.len() * search_fields.len();
+        if num_terms > self.ctx.max_fuzzy_terms {
+            return Err(anyhow!(
+                "fuzzy query expands into {} term queries which exceeds the limit of {}, \
+                try using fewer words",
+                num_terms,
+                self.ctx.max_fuzzy_terms,
+            ));
+        }
+
+        let extra_stemmers = get_stemmers(extra_fields, &self.schema, &self.tokenizers);
+
         debug!("building fuzzy query {:?}", &words);
-        for search_term in words.iter() {
-            if ignore_stop_words && self.stop_words.is_stop_word(search_term) {
-                continue;
-            }
+        let mut word_parts = Vec::with_capacity(words.len());
+        for (position, search_term) in words.iter() {
+            let mut parts: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+            for (field, boost) in search_fields.iter() {
+                let stemmer = self
+                    .stemmers
+                    .get(field)
+                    .or_else(|| extra_stemmers.get(field));
+                // Words removed by the field's analyzer, e.g. its language's
+                // stop words, are never indexed so are not searched.
+                let search_term = match stemmer {
+                    Some(stemmer) => match stem_word(stemmer, search_term) {
+                        Some(stemmed) => stemmed,
+                        None => continue,
+                    },
+                    None => search_term.clone(),
+                };
 
-            for (field, boost) in self.ctx.fuzzy_search_fields.iter() {
-                let term = Term::from_field_text(*field, search_term);
+                let term = Term::from_field_text(*field, &search_term);
 
-                let query: Box<dyn Query> = if self.ctx.use_fast_fuzzy {
+                let query: Box<dyn Query> = if use_fast_fuzzy {
                     Box::new(TermQuery::new(
                         term,
                         IndexRecordOption::WithFreqsAndPositions,
@@ -580,17 +1033,115 @@ impl QueryBuilder {
 
                 parts.push((Occur::Should, query));
             }
+
+            word_parts.push((*position, parts));
+        }
+
+        // Every input word must match in at least one field, a word matches
+        // if any of its synonyms do.
+        if self.ctx.fuzzy_require_all_terms {
+            if minimum_should_match.is_some() {
+                return Err(Error::msg(
+                    "`minimum_should_match` cannot be used with fuzzy queries on an \
+                    index which requires all fuzzy terms to match",
+                ));
+            }
+
+            let words = group_words_by_position(word_parts)
+                .into_iter()
+                .map(|query| (Occur::Must, query))
+                .collect();
+
+            return Ok(Box::new(BooleanQuery::new(words)));
         }
 
+        // Each input word is grouped into its own clause so the minimum
+        // counts words rather than the terms of each field or synonym.
+        if let Some(minimum) = minimum_should_match {
+            let words = group_words_by_position(word_parts)
+                .into_iter()
+                .map(|query| (Occur::Should, query))
+                .collect();
+
+            return Ok(apply_minimum_should_match(
+                Box::new(BooleanQuery::new(words)),
+                minimum,
+            ));
+        }
+
+        let parts = word_parts
+            .into_iter()
+            .flat_map(|(_, parts)| parts)
+            .collect();
         Ok(Box::new(BooleanQuery::new(parts)))
     }
 
+    /// Parses the query string with the same syntax as normal queries.
+    pub(crate) fn parse_normal_query(&self, query: &str) -> Result<Box<dyn Query>> {
+        self.make_normal_query(DocumentValue::Text(query.to_string()), None, false, &[])
+    }
+
     /// Makes a new query by feeding the value into the tantivy QueryParser.
+    ///
+    /// If `raw_query` is set every field is parsed with the raw tokenizer,
+    /// so each term must exactly match an indexed term.
     // TODO add-back #[instrument(name = "normal-query", level = "trace", skip_all)]
-    fn make_normal_query(&self, value: DocumentValue) -> Result<Box<dyn Query>> {
-        let value = value.as_string();
+    fn make_normal_query(
+        &self,
+        value: DocumentValue,
+        default_operator: Option<Operator>,
+        raw_query: bool,
+        extra_fields: &[(Field, Score)],
+    ) -> Result<Box<dyn Query>> {
+        let value = rewrite_field_aliases(&value.as_string(), &self.ctx.field_aliases);
+
+        self.check_queryable_fields(&get_scoped_fields(&value))?;
+
+        let conjunction = match default_operator {
+            Some(Operator::And) => true,
+            Some(Operator::Or) => false,
+            None => self.ctx.set_conjunction_by_default,
+        };
+
+        let extended_parser;
+        let parser = if raw_query || !extra_fields.is_empty() {
+            let fields: Vec<(Field, Score)> = self
+                .ctx
+                .default_search_fields
+                .iter()
+                .chain(extra_fields.iter())
+                .copied()
+                .collect();
+
+            let tokenizers = if raw_query {
+                &self.raw_tokenizers
+            } else {
+                &self.tokenizers
+            };
+
+            // Unprefixed terms search the extra fields alongside the
+            // default search field if one is set.
+            let default_fields = self.ctx.default_search_field.map(|field| {
+                std::iter::once(field)
+                    .chain(extra_fields.iter().map(|(field, _)| *field))
+                    .collect()
+            });
+
+            extended_parser = get_parser(
+                &fields,
+                default_fields,
+                &self.schema,
+                tokenizers,
+                conjunction,
+            );
+            &extended_parser
+        } else if conjunction {
+            self.conjunction_parser.as_ref()
+        } else {
+            self.disjunction_parser.as_ref()
+        };
 
-        let query = match self.query_parser.parse_query(&value) {
+        let query = match parser.parse_query(&value) {
             Ok(qry) => qry,
             Err(e) => return Err(Error::msg(format!("invalid query: {:?}", e))),
         };
@@ -665,12 +1216,17 @@ impl QueryBuilder {
         &self,
         value: DocumentValue,
         field: FieldSelector,
+        extra_fields: &[(Field, Score)],
     ) -> Result<Box<dyn Query>> {
         use tantivy::query::Occur;
 
         let fields = {
             match field {
-                FieldSelector::DefaultFields => self.ctx.default_search_fields.clone(),
+                FieldSelector::DefaultFields => {
+                    let mut fields = self.ctx.default_search_fields.clone();
+                    fields.extend_from_slice(extra_fields);
+                    fields
+                },
                 FieldSelector::Single(field) => {
                     vec![(self.get_searchable_field(&field)?, 1.0)]
                 },
@@ -728,6 +1284,14 @@ impl QueryBuilder {
     }
 
     fn get_searchable_field(&self, field: &str) -> Result<Field> {
+        let field = self
+            .ctx
+            .field_aliases
+            .get(field)
+            .map(String::as_str)
+            .unwrap_or(field);
+        self.check_queryable_fields(&[field])?;
+
         let field = self.schema.get_field(field).ok_or_else(|| {
             Error::msg(format!("no field exists with name: {:?}", field))
         })?;
@@ -741,29 +1305,299 @@ impl QueryBuilder {
 
         Ok(field)
     }
+
+    /// Checks the given explicitly targeted fields against the index's
+    /// queryable fields if any are set.
+    ///
+    /// The names are expected to already have any aliases resolved.
+    fn check_queryable_fields(&self, names: &[impl AsRef<str>]) -> Result<()> {
+        let queryable_fields = match self.ctx.queryable_fields {
+            Some(ref fields) => fields,
+            None => return Ok(()),
+        };
+
+        let rejected_fields: Vec<&str> = names
+            .iter()
+            .map(AsRef::as_ref)
+            .filter(|name| !queryable_fields.contains(*name))
+            .collect();
+
+        if !rejected_fields.is_empty() {
+            return Err(anyhow!(
+                "invalid query: the query targets fields which cannot be queried: {}",
+                rejected_fields.join(", "),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the extra fields a single query should search alongside
+    /// the index's search fields.
+    ///
+    /// Each field must be an indexed text or string field, fields which
+    /// are already search fields are skipped.
+    fn get_extra_search_fields(&self, names: &[String]) -> Result<Vec<(Field, Score)>> {
+        let mut fields = Vec::with_capacity(names.len());
+        for name in names {
+            let name = self
+                .ctx
+                .field_aliases
+                .get(name)
+                .map(String::as_str)
+                .unwrap_or(name);
+            self.check_queryable_fields(&[name])?;
+
+            let field = self
+                .schema
+                .get_field(name)
+                .ok_or_else(|| anyhow!("no field exists with name: {:?}", name))?;
+
+            let entry = self.schema.get_field_entry(field);
+            match entry.field_type() {
+                FieldType::Str(opts) if opts.get_indexing_options().is_some() => {},
+                FieldType::Str(_) => {
+                    return Err(anyhow!(
+                        "extra search field {:?} is not indexed, stored only fields \
+                        cannot be searched",
+                        name,
+                    ))
+                },
+                _ => {
+                    return Err(anyhow!(
+                        "extra search field {:?} must be a text or string field",
+                        name,
+                    ))
+                },
+            }
+
+            let is_search_field = self
+                .ctx
+                .default_search_fields
+                .iter()
+                .chain(self.ctx.fuzzy_search_fields.iter())
+                .chain(fields.iter())
+                .any(|(existing, _)| *existing == field);
+            if !is_search_field {
+                fields.push((field, 1.0));
+            }
+        }
+
+        Ok(fields)
+    }
 }
 
-fn get_parser(ctx: &QueryContext, index: &Index) -> QueryParser {
-    let mut default_fields = vec![];
-    for (field, _) in ctx.default_search_fields.iter() {
-        default_fields.push(*field);
+/// Gets the stemming analyzers of any of the given fields which are stemmed.
+fn get_stemmers(
+    fields: &[(Field, Score)],
+    schema: &Schema,
+    tokenizers: &TokenizerManager,
+) -> HashMap<Field, TextAnalyzer> {
+    let mut stemmers = HashMap::new();
+    for (field, _) in fields.iter() {
+        let tokenizer = match schema.get_field_entry(*field).field_type() {
+            FieldType::Str(opts) => opts.get_indexing_options().map(|v| v.tokenizer()),
+            _ => None,
+        };
+
+        if let Some(name) = tokenizer.filter(|name| is_stemming_tokenizer(name)) {
+            if let Some(analyzer) = tokenizers.get(name) {
+                stemmers.insert(*field, analyzer);
+            }
+        }
+    }
+
+    stemmers
+}
+
+/// Combines the clauses of each input word and its synonyms into a single
+/// query per word, skipping any positions left empty by removed words.
+fn group_words_by_position(
+    word_parts: Vec<(usize, Vec<(tantivy::query::Occur, Box<dyn Query>)>)>,
+) -> Vec<Box<dyn Query>> {
+    let mut groups: Vec<Vec<(tantivy::query::Occur, Box<dyn Query>)>> = vec![];
+    for (position, parts) in word_parts {
+        match groups.get_mut(position) {
+            Some(group) => group.extend(parts),
+            None => {
+                groups.resize_with(position, Vec::new);
+                groups.push(parts);
+            },
+        }
     }
 
-    let mut parser = QueryParser::for_index(index, default_fields);
-    for (field, boost) in ctx.default_search_fields.iter() {
+    groups
+        .into_iter()
+        .filter(|parts| !parts.is_empty())
+        .map(|parts| -> Box<dyn Query> { Box::new(BooleanQuery::new(parts)) })
+        .collect()
+}
+
+/// Stems a single word with the given analyzer.
+///
+/// Returns `None` if the analyzer removes the word entirely.
+fn stem_word(stemmer: &TextAnalyzer, word: &str) -> Option<String> {
+    let mut tokens = stemmer.token_stream(word);
+    tokens.next().map(|token| token.text.clone())
+}
+
+/// Gets a tokenizer manager mapping the tokenizer of every indexed text
+/// field to the raw tokenizer.
+fn get_raw_tokenizers(schema: &Schema) -> TokenizerManager {
+    let tokenizers = TokenizerManager::default();
+    for (_, entry) in schema.fields() {
+        if let FieldType::Str(opts) = entry.field_type() {
+            if let Some(indexing) = opts.get_indexing_options() {
+                tokenizers.register(indexing.tokenizer(), RawTokenizer);
+            }
+        }
+    }
+
+    tokenizers
+}
+
+/// Creates a query parser searching the given fields.
+///
+/// If default fields are given unprefixed terms only search those fields,
+/// otherwise they search every given field.
+fn get_parser(
+    fields: &[(Field, Score)],
+    default_fields: Option<Vec<Field>>,
+    schema: &Schema,
+    tokenizers: &TokenizerManager,
+    conjunction: bool,
+) -> QueryParser {
+    let default_fields = default_fields
+        .unwrap_or_else(|| fields.iter().map(|(field, _)| *field).collect());
+
+    let mut parser =
+        QueryParser::new(schema.clone(), default_fields, tokenizers.clone());
+    for (field, boost) in fields.iter() {
         if *boost == 0f32 {
             continue;
         };
         parser.set_field_boost(*field, *boost);
     }
 
-    if ctx.set_conjunction_by_default {
+    if conjunction {
         parser.set_conjunction_by_default();
     }
 
     parser
 }
 
+/// Requires documents to match a minimum amount of the optional clauses
+/// of a boolean query.
+///
+/// Any other query, or a minimum of at most one clause, is returned as is.
+fn apply_minimum_should_match(
+    query: Box<dyn Query>,
+    minimum: MinimumShouldMatch,
+) -> Box<dyn Query> {
+    use tantivy::query::Occur;
+
+    let boolean = match query.downcast_ref::<BooleanQuery>() {
+        Some(boolean) => boolean,
+        None => return query,
+    };
+
+    let (should, mut parts): (Vec<_>, Vec<_>) = boolean
+        .clauses()
+        .iter()
+        .map(|(occur, clause)| (*occur, clause.box_clone()))
+        .partition(|(occur, _)| *occur == Occur::Should);
+
+    let minimum = minimum.resolve(should.len());
+    if minimum <= 1 {
+        return query;
+    }
+
+    let should = should.into_iter().map(|(_, clause)| clause).collect();
+    parts.push((
+        Occur::Must,
+        Box::new(MinShouldMatchQuery::new(should, minimum)),
+    ));
+
+    Box::new(BooleanQuery::new(parts))
+}
+
+/// Checks if the given character continues a field name in a query string.
+///
+/// A leading `-` excludes the clause rather than being part of the name.
+#[inline]
+fn is_field_name_char(c: char, word: &str) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.' || (c == '-' && !word.is_empty())
+}
+
+/// Replaces any aliased field names in a query string with the name of the
+/// field they refer to.
+///
+/// Field names are the words directly followed by a `:` outside of quotes.
+fn rewrite_field_aliases(query: &str, aliases: &HashMap<String, String>) -> String {
+    if aliases.is_empty() {
+        return query.to_string();
+    }
+
+    let mut rewritten = String::with_capacity(query.len());
+    let mut word = String::new();
+    let mut in_quotes = false;
+    let mut escaped = false;
+
+    for c in query.chars() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            in_quotes = !in_quotes;
+        } else if !in_quotes && is_field_name_char(c, &word) {
+            word.push(c);
+            continue;
+        } else if !in_quotes && c == ':' && !word.is_empty() {
+            let field = aliases.get(&word).unwrap_or(&word);
+            rewritten.push_str(field);
+            rewritten.push(c);
+            word.clear();
+            continue;
+        }
+
+        rewritten.push_str(&word);
+        rewritten.push(c);
+        word.clear();
+    }
+
+    rewritten.push_str(&word);
+    rewritten
+}
+
+/// Gets the names of the fields explicitly targeted by the query.
+fn get_scoped_fields(query: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut word = String::new();
+    let mut in_quotes = false;
+    let mut escaped = false;
+
+    for c in query.chars() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            in_quotes = !in_quotes;
+        } else if !in_quotes && is_field_name_char(c, &word) {
+            word.push(c);
+            continue;
+        } else if !in_quotes && c == ':' && !word.is_empty() {
+            fields.push(std::mem::take(&mut word));
+            continue;
+        }
+
+        word.clear();
+    }
+
+    fields
+}
+
 fn convert_to_term(
     value: DocumentValue,
     field: Field,
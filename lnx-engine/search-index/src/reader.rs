@@ -1,32 +1,52 @@
 use std::borrow::Cow;
 use std::cmp::Reverse;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use aexecutor::SearcherExecutorPool;
-use anyhow::{anyhow, Error, Result};
-use hashbrown::HashMap;
+use aexecutor::{SearcherExecutorPool, ThreadOptions};
+use anyhow::{anyhow, Context, Error, Result};
+use arc_swap::ArcSwap;
+use hashbrown::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 use tantivy::collector::{Count, TopDocs};
 use tantivy::fastfield::FastFieldReader;
-use tantivy::query::{Query, TermQuery};
+use tantivy::query::{AllQuery, Query, TermQuery};
 use tantivy::schema::{Field, FieldType, IndexRecordOption, Schema, Value};
 use tantivy::{
     DateTime,
     DocAddress,
     DocId,
+    Document,
     Executor,
     IndexReader,
     LeasedItem,
     ReloadPolicy,
+    Score,
     Searcher,
+    SegmentOrdinal,
     SegmentReader,
     Term,
 };
+use tokio::sync::mpsc;
+use tracing::Instrument;
 
+use crate::cardinality::CardinalityCollector;
+use crate::filter_cache::FilterCache;
+use crate::geo::{GeoDistanceCollector, GeoFilter};
 use crate::helpers::{AsScore, Validate};
-use crate::query::{DocumentId, QueryBuilder, QuerySelector};
+use crate::highlight::{Highlighter, DEFAULT_POST_TAG, DEFAULT_PRE_TAG};
+use crate::query::{
+    DocumentId,
+    Operator,
+    QueryBuilder,
+    QueryExplanation,
+    QuerySelector,
+};
+use crate::rescore::{rescore_top_docs, MAX_RESCORE_HITS};
 use crate::schema::SchemaContext;
+use crate::should_match::MinimumShouldMatch;
 use crate::structures::{DocumentHit, IndexContext};
+use crate::suggest::{spell_check, suggest_terms, SpellCheck, Suggestion};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct ReaderContext {
@@ -43,6 +63,65 @@ pub(crate) struct ReaderContext {
 
     /// The maximum searches that can be done at any one time.
     max_concurrency: usize,
+
+    /// The maximum time in milliseconds a search waits for one of the
+    /// `max_concurrency` permits before it is rejected.
+    ///
+    /// Searches wait indefinitely if this is not set.
+    #[serde(default)]
+    acquire_timeout: Option<u64>,
+
+    /// The amount of results to limit by if a query does not specify a limit.
+    #[serde(default = "ReaderContext::default_limit")]
+    default_limit: usize,
+
+    /// The amount of documents to skip if a query does not specify an offset.
+    #[serde(default)]
+    default_offset: usize,
+
+    /// The maximum `offset + limit` a query can request.
+    ///
+    /// Collecting hits deep into the results is expensive, queries paging
+    /// past this are rejected. No bound is applied if this is not set.
+    #[serde(default)]
+    max_result_window: Option<usize>,
+
+    /// The nice value of the reader's threads, between -20 and 19.
+    ///
+    /// Higher values lower the priority of the index's searches relative
+    /// to other indexes. This is only supported on linux.
+    #[serde(default)]
+    thread_niceness: Option<i32>,
+
+    /// The CPU cores the reader's threads are pinned to.
+    ///
+    /// This is only supported on linux.
+    #[serde(default)]
+    thread_affinity: Option<Vec<usize>>,
+
+    /// The maximum size in bytes of the cached documents matching filter
+    /// queries.
+    ///
+    /// Each cached filter takes up 1 bit per document of a segment, the
+    /// oldest filters are evicted once the cache is full. Defaults to `0`
+    /// which disables the cache.
+    #[serde(default)]
+    filter_cache_size: usize,
+
+    /// If the index should be warmed up after each commit.
+    ///
+    /// The new segments are loaded by running the `warmup_query` in the
+    /// background once the commit completes, avoiding slow first searches
+    /// after each commit.
+    #[serde(default)]
+    warm_on_reload: bool,
+
+    /// The query used to warm up the index, following the same syntax as
+    /// normal queries.
+    ///
+    /// Defaults to matching all documents.
+    #[serde(default)]
+    warmup_query: Option<String>,
 }
 
 impl Validate for ReaderContext {
@@ -51,6 +130,42 @@ impl Validate for ReaderContext {
             return Err(Error::msg("max concurrency must be at least 1."));
         }
 
+        if self.default_limit == 0 {
+            return Err(Error::msg("default limit must be at least 1."));
+        }
+
+        if self.max_result_window == Some(0) {
+            return Err(Error::msg("max result window must be at least 1."));
+        }
+
+        if self.acquire_timeout == Some(0) {
+            return Err(Error::msg(
+                "acquire timeout must be at least 1 millisecond.",
+            ));
+        }
+
+        if let Some(niceness) = self.thread_niceness {
+            if !(-20..=19).contains(&niceness) {
+                return Err(Error::msg("thread niceness must be between -20 and 19."));
+            }
+        }
+
+        if matches!(self.thread_affinity, Some(ref cores) if cores.is_empty()) {
+            return Err(Error::msg("thread affinity must contain at least 1 core."));
+        }
+
+        if let Some(ref query) = self.warmup_query {
+            if query.trim().is_empty() {
+                return Err(Error::msg("warmup query cannot be empty."));
+            }
+
+            if !self.warm_on_reload {
+                return Err(Error::msg(
+                    "warmup query is only used when warm_on_reload is enabled.",
+                ));
+            }
+        }
+
         Ok(())
     }
 }
@@ -59,6 +174,10 @@ impl ReaderContext {
     fn default_reader_threads() -> usize {
         1
     }
+
+    fn default_limit() -> usize {
+        20
+    }
 }
 
 /// A given query payload that describes how the reader should
@@ -69,12 +188,14 @@ pub struct QueryPayload {
     pub(crate) query: QuerySelector,
 
     /// The amount of results to limit by.
-    #[serde(default = "QueryPayload::default_limit")]
-    pub(crate) limit: usize,
+    ///
+    /// Defaults to the index's `default_limit` if not given.
+    pub(crate) limit: Option<usize>,
 
     /// The amount of documents to skip before getting the results.
-    #[serde(default)]
-    pub(crate) offset: usize,
+    ///
+    /// Defaults to the index's `default_offset` if not given.
+    pub(crate) offset: Option<usize>,
 
     /// A specified field to order results by, this defaults to the
     /// score of the indexed documents (relevancy).
@@ -83,11 +204,151 @@ pub struct QueryPayload {
     /// How to sort the data (asc/desc).
     #[serde(default)]
     pub(crate) sort: Sort,
+
+    /// A fast field to collapse results by.
+    ///
+    /// If set, only the most relevant hit for each distinct value of the field
+    /// is returned.
+    pub(crate) collapse_field: Option<String>,
+
+    /// Limits results to documents within a given distance of a point.
+    ///
+    /// If set, results are ordered by their distance to the point, closest
+    /// first, and each hit contains its distance in metres.
+    pub(crate) geo_filter: Option<GeoFilter>,
+
+    /// The operator used to combine the terms of normal queries.
+    ///
+    /// Defaults to the index's `set_conjunction_by_default` behaviour.
+    pub(crate) default_operator: Option<Operator>,
+
+    /// The stored text fields to return highlighted snippets of.
+    pub(crate) highlight_fields: Option<Vec<String>>,
+
+    /// The tag inserted before each highlighted term, defaults to `<em>`.
+    pub(crate) highlight_pre_tag: Option<String>,
+
+    /// The tag inserted after each highlighted term, defaults to `</em>`.
+    pub(crate) highlight_post_tag: Option<String>,
+
+    /// The maximum amount of snippets returned for each highlighted field.
+    ///
+    /// Defaults to 1.
+    pub(crate) max_snippets_per_field: Option<usize>,
+
+    /// If a search returning no hits should respond with a `404` rather
+    /// than a `200` with an empty set of hits.
+    ///
+    /// This is only used by the server, searches themselves are unaffected.
+    #[serde(default)]
+    pub(crate) empty_as_404: bool,
+
+    /// A script expression used to rescore the top hits.
+    ///
+    /// The script has access to the hit's `score` and the document's single
+    /// value numeric fast fields, e.g. `score * popularity`, the hits are
+    /// then re-sorted by their new score before the offset and limit are
+    /// applied.
+    pub(crate) rescore_script: Option<String>,
+
+    /// The amount of top hits ranked by relevancy which are rescored by the
+    /// rescore script.
+    ///
+    /// This must cover the offset and limit, which it defaults to. Setting
+    /// a fixed window keeps the order consistent when paging through hits.
+    pub(crate) rescore_window: Option<usize>,
+
+    /// Additional indexed text fields to search alongside the index's
+    /// search fields for this query only.
+    pub(crate) extra_search_fields: Option<Vec<String>>,
+
+    /// If each hit's score should be divided by the highest score of the
+    /// returned hits, scaling them to between 0 and 1.
+    ///
+    /// The normalized scores are only relative to the other hits of the same
+    /// response, they cannot be compared across different searches.
+    #[serde(default)]
+    pub(crate) normalize_scores: bool,
+
+    /// If the results should include the time taken by each phase of the
+    /// search.
+    #[serde(default)]
+    pub(crate) profile: bool,
+
+    /// The minimum amount of words of fuzzy queries, or optional clauses of
+    /// normal queries, a document must match.
+    ///
+    /// This is either a count e.g. `2` or a percentage of the clauses
+    /// rounded down e.g. `"75%"`. Defaults to matching any of them.
+    pub(crate) minimum_should_match: Option<MinimumShouldMatch>,
+
+    /// If the terms of normal queries should be matched exactly as they are
+    /// written, bypassing each field's tokenizer.
+    ///
+    /// This skips any stemming or stop word removal, so a term only matches
+    /// documents with the exact same indexed term. Fuzzy, term and more like
+    /// this queries are unaffected.
+    #[serde(default)]
+    pub(crate) raw_query: bool,
+
+    /// If the search should be split across the index's reader threads.
+    ///
+    /// Setting this to `false` runs the search on a single thread, avoiding
+    /// the overhead of splitting up cheap searches. Setting it to `true`
+    /// cannot use more than the index's `reader_threads`. Defaults to the
+    /// index's executor if not given.
+    pub(crate) parallel: Option<bool>,
+
+    /// If the query can page past the index's `max_result_window`.
+    ///
+    /// This is only set by internal searches which page through every
+    /// matching document, it cannot be given in a payload.
+    #[serde(skip)]
+    pub(crate) unbounded: bool,
 }
 
 impl QueryPayload {
-    fn default_limit() -> usize {
-        20
+    #[inline]
+    pub fn empty_as_404(&self) -> bool {
+        self.empty_as_404
+    }
+
+    /// The name of the query's mode, multi queries are always `multi`.
+    #[inline]
+    pub fn mode(&self) -> &'static str {
+        self.query.mode()
+    }
+}
+
+/// The limits bounding how much work a single export can perform.
+///
+/// Once a limit is reached the export stops early and the receiver is
+/// closed the same way as if every document had been read.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct ExportLimits {
+    /// The maximum amount of documents to export.
+    pub max_rows: Option<usize>,
+
+    /// The maximum amount of time to spend reading documents.
+    pub max_duration: Option<Duration>,
+}
+
+impl ExportLimits {
+    /// If the export should stop having read `rows` documents since `start`.
+    fn is_reached(&self, rows: usize, start: Instant) -> bool {
+        if let Some(max_rows) = self.max_rows {
+            if rows >= max_rows {
+                return true;
+            }
+        }
+
+        if let Some(max_duration) = self.max_duration {
+            if start.elapsed() >= max_duration {
+                return true;
+            }
+        }
+
+        false
     }
 }
 
@@ -116,8 +377,30 @@ pub struct QueryResults {
     /// The total amount of documents matching the search
     count: usize,
 
+    /// The amount of hits removed due to sharing a collapse field value
+    /// with a more relevant hit.
+    ///
+    /// Only the over-fetched window of hits is collapsed so this only counts
+    /// the hits removed from that window rather than across all matching
+    /// documents, see `total_after_collapse` for the total amount of groups.
+    pub(crate) collapsed: usize,
+
+    /// The amount of distinct collapse field values across all matching
+    /// documents, i.e. the total amount of collapsed groups.
+    ///
+    /// This is an approximate count, typically within 1% of the exact
+    /// count, and is only set when collapsing results.
+    pub(crate) total_after_collapse: Option<usize>,
+
     /// The amount of time taken to search in seconds.
     time_taken: f32,
+
+    /// The amount of time taken by each phase of the search in seconds.
+    ///
+    /// This is only set when the search is profiled, the phases are
+    /// `parse`, `collect`, `retrieve`, `rescore` and `highlight`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) timings: Option<HashMap<String, f64>>,
 }
 
 impl QueryResults {
@@ -132,6 +415,58 @@ impl QueryResults {
     }
 }
 
+/// The document counts and size of a single segment of the index.
+#[derive(Debug, Serialize)]
+pub struct SegmentDiagnostics {
+    /// The id of the segment.
+    pub(crate) id: String,
+
+    /// The amount of live documents in the segment.
+    pub(crate) num_docs: u32,
+
+    /// The amount of deleted documents which have not yet been merged out
+    /// of the segment.
+    pub(crate) num_deleted_docs: u32,
+
+    /// The total size of the segment's files in bytes.
+    pub(crate) size_bytes: usize,
+}
+
+/// The factor of extra documents to fetch when collapsing results.
+///
+/// This allows the requested limit to still be filled after any hits
+/// sharing the same value have been removed.
+const COLLAPSE_OVERFETCH_FACTOR: usize = 4;
+
+/// The maximum amount of extra documents fetched when collapsing results
+/// regardless of the overfetch factor.
+const MAX_COLLAPSE_OVERFETCH: usize = 1_000;
+
+/// The amount of hits retrieved at a time when streaming search results.
+const STREAM_CHUNK_SIZE: usize = 100;
+
+/// Describes how to collapse a set of collected hits.
+#[derive(Debug, Copy, Clone)]
+struct Collapse {
+    field: Field,
+    offset: usize,
+    limit: usize,
+}
+
+/// The processed hits of a search along with its collected metadata.
+struct SearchHits {
+    hits: Vec<DocumentHit>,
+    count: usize,
+    collapsed: usize,
+    total_after_collapse: Option<usize>,
+
+    /// The time taken to retrieve the hits' stored documents.
+    retrieval_time: Duration,
+
+    /// The time taken to rescore the top hits.
+    rescore_time: Duration,
+}
+
 /// Attaches an order by clause to the collector.
 ///
 /// This collected the values with be returned in the order according to the
@@ -149,6 +484,75 @@ fn order_and_search<R: AsScore + tantivy::fastfield::FastValue>(
         .map_err(Error::from)
 }
 
+/// Keeps only the first hit for each distinct value of the collapse field.
+///
+/// The hits are expected to already be in order so the first hit of each
+/// value is the most relevant, the collapse offset and limit are applied
+/// after the duplicates are removed.
+fn collapse_top_docs<S>(
+    searcher: &Searcher,
+    collapse: Collapse,
+    top_docs: Vec<(S, DocAddress)>,
+) -> Result<(Vec<(S, DocAddress)>, usize)> {
+    let mut readers = HashMap::new();
+    let mut seen = HashSet::with_capacity(top_docs.len());
+    let mut kept = Vec::with_capacity(top_docs.len());
+    let mut collapsed = 0;
+
+    for (ratio, ref_address) in top_docs {
+        if !readers.contains_key(&ref_address.segment_ord) {
+            let reader = searcher
+                .segment_reader(ref_address.segment_ord)
+                .fast_fields()
+                .u64_lenient(collapse.field)?;
+
+            readers.insert(ref_address.segment_ord, reader);
+        }
+
+        let value = readers[&ref_address.segment_ord].get(ref_address.doc_id);
+        if !seen.insert(value) {
+            collapsed += 1;
+            continue;
+        }
+
+        kept.push((ratio, ref_address));
+    }
+
+    let kept = kept
+        .into_iter()
+        .skip(collapse.offset)
+        .take(collapse.limit)
+        .collect();
+
+    Ok((kept, collapsed))
+}
+
+/// Collapses the top docs if required and then processes the results.
+fn collect_hits<S: AsScore>(
+    ctx: &SchemaContext,
+    searcher: &Searcher,
+    schema: &Schema,
+    (top_docs, count): (Vec<(S, DocAddress)>, usize),
+    collapse: Option<Collapse>,
+) -> Result<SearchHits> {
+    let (top_docs, collapsed) = match collapse {
+        Some(collapse) => collapse_top_docs(searcher, collapse, top_docs)?,
+        None => (top_docs, 0),
+    };
+
+    let start = Instant::now();
+    let hits = process_search(ctx, searcher, schema, top_docs)?;
+
+    Ok(SearchHits {
+        hits,
+        count,
+        collapsed,
+        total_after_collapse: None,
+        retrieval_time: start.elapsed(),
+        rescore_time: Duration::ZERO,
+    })
+}
+
 /// Performs the search operation and processes the returned results.
 fn process_search<S: AsScore>(
     ctx: &SchemaContext,
@@ -159,26 +563,150 @@ fn process_search<S: AsScore>(
     let mut hits = Vec::with_capacity(top_docs.len());
     for (ratio, ref_address) in top_docs {
         let retrieved_doc = searcher.doc(ref_address)?;
-        let mut doc = schema.to_named_doc(&retrieved_doc);
-        let id = doc.0
-            .remove("_id")
-            .ok_or_else(|| Error::msg("document has been missed labeled (missing primary key '_id'), the dataset is invalid"))?;
-
-        if let Value::U64(doc_id) = id[0] {
-            hits.push(DocumentHit::from_tantivy_document(
-                ctx,
-                doc_id,
-                doc,
-                ratio.as_score(),
-            ));
-        } else {
-            return Err(Error::msg("document has been missed labeled (missing identifier tag), the dataset is invalid"));
-        }
+        hits.push(to_document_hit(
+            ctx,
+            schema,
+            &retrieved_doc,
+            ratio.as_score(),
+        )?);
     }
 
     Ok(hits)
 }
 
+/// Converts a retrieved tantivy document into a hit using it's stored id.
+fn to_document_hit(
+    ctx: &SchemaContext,
+    schema: &Schema,
+    retrieved_doc: &Document,
+    score: Option<Score>,
+) -> Result<DocumentHit> {
+    let mut doc = schema.to_named_doc(retrieved_doc);
+    let id = doc.0
+        .remove("_id")
+        .ok_or_else(|| Error::msg("document has been missed labeled (missing primary key '_id'), the dataset is invalid"))?;
+
+    if let Value::U64(doc_id) = id[0] {
+        Ok(DocumentHit::from_tantivy_document(ctx, doc_id, doc, score))
+    } else {
+        Err(Error::msg("document has been missed labeled (missing identifier tag), the dataset is invalid"))
+    }
+}
+
+/// Divides the score of each hit by the highest score of the hits.
+///
+/// Nothing is changed if none of the hits have a positive score.
+fn normalize_hit_scores(hits: &mut [DocumentHit]) {
+    let max_score = hits
+        .iter()
+        .filter_map(|hit| hit.score)
+        .fold(0.0, Score::max);
+
+    if max_score <= 0.0 {
+        return;
+    }
+
+    for hit in hits.iter_mut() {
+        if let Some(ref mut score) = hit.score {
+            *score /= max_score;
+        }
+    }
+}
+
+/// Reads every alive document in the searcher sending them in chunks of
+/// `chunk_size` to the given sender.
+///
+/// If a segment id is given only the documents within that segment are read.
+/// This stops early if the receiving half of the sender is dropped or once
+/// any of the given limits are reached.
+fn export_documents(
+    ctx: &SchemaContext,
+    searcher: &Searcher,
+    chunk_size: usize,
+    segment: Option<&str>,
+    limits: ExportLimits,
+    tx: &mpsc::Sender<Result<Vec<DocumentHit>>>,
+) -> Result<()> {
+    let schema = searcher.schema();
+    let segment_readers = searcher.segment_readers();
+
+    if let Some(segment) = segment {
+        let exists = segment_readers
+            .iter()
+            .any(|reader| reader.segment_id().uuid_string() == segment);
+
+        if !exists {
+            return Err(anyhow!("no segment exists with id: {:?}", segment));
+        }
+    }
+
+    let start = Instant::now();
+    let mut rows = 0;
+    let mut chunk = Vec::with_capacity(chunk_size);
+    'segments: for (segment_ord, segment_reader) in segment_readers.iter().enumerate() {
+        if let Some(segment) = segment {
+            if segment_reader.segment_id().uuid_string() != segment {
+                continue;
+            }
+        }
+
+        for doc_id in 0..segment_reader.max_doc() {
+            if segment_reader.is_deleted(doc_id) {
+                continue;
+            }
+
+            if limits.is_reached(rows, start) {
+                debug!(
+                    rows = rows,
+                    "export stopped early due to reaching its limits"
+                );
+                break 'segments;
+            }
+            rows += 1;
+
+            let address = DocAddress::new(segment_ord as SegmentOrdinal, doc_id);
+            let retrieved_doc = searcher.doc(address)?;
+            chunk.push(to_document_hit(ctx, schema, &retrieved_doc, None)?);
+
+            if chunk.len() >= chunk_size {
+                let full = std::mem::replace(&mut chunk, Vec::with_capacity(chunk_size));
+                if tx.blocking_send(Ok(full)).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    if !chunk.is_empty() {
+        let _ = tx.blocking_send(Ok(chunk));
+    }
+
+    Ok(())
+}
+
+/// Retrieves the stored documents of the collected hits, sending them in
+/// chunks to the given sender.
+///
+/// Only the addresses of the hits are collected up front so at most a
+/// single chunk of documents is held in memory at a time. This stops early
+/// if the receiving half of the sender is dropped.
+fn stream_hits(
+    ctx: &SchemaContext,
+    searcher: &Searcher,
+    top_docs: &[(Score, DocAddress)],
+    tx: &mpsc::Sender<Result<Vec<DocumentHit>>>,
+) -> Result<()> {
+    let schema = searcher.schema();
+    for addresses in top_docs.chunks(STREAM_CHUNK_SIZE) {
+        let chunk = process_search(ctx, searcher, schema, addresses.to_vec())?;
+        if tx.blocking_send(Ok(chunk)).is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
 /// Orders the search results by the given field with a given sort (ASC, DESC)
 ///
 /// This function is super messy just because of all the type inference
@@ -193,7 +721,8 @@ fn order_and_sort(
     searcher: &Searcher,
     collector: TopDocs,
     executor: &Executor,
-) -> Result<(Vec<DocumentHit>, usize)> {
+    collapse: Option<Collapse>,
+) -> Result<SearchHits> {
     let is_multi_value = ctx
         .multi_value_fields()
         .contains(schema.get_field_name(field));
@@ -210,22 +739,22 @@ fn order_and_sort(
             FieldType::I64(_) => {
                 let out: (Vec<(i64, DocAddress)>, usize) =
                     order_and_search(searcher, field, query, collector, executor)?;
-                Ok((process_search(ctx, searcher, schema, out.0)?, out.1))
+                collect_hits(ctx, searcher, schema, out, collapse)
             },
             FieldType::U64(_) => {
                 let out: (Vec<(u64, DocAddress)>, usize) =
                     order_and_search(searcher, field, query, collector, executor)?;
-                Ok((process_search(ctx, searcher, schema, out.0)?, out.1))
+                collect_hits(ctx, searcher, schema, out, collapse)
             },
             FieldType::F64(_) => {
                 let out: (Vec<(f64, DocAddress)>, usize) =
                     order_and_search(searcher, field, query, collector, executor)?;
-                Ok((process_search(ctx, searcher, schema, out.0)?, out.1))
+                collect_hits(ctx, searcher, schema, out, collapse)
             },
             FieldType::Date(_) => {
                 let out: (Vec<(DateTime, DocAddress)>, usize) =
                     order_and_search(searcher, field, query, collector, executor)?;
-                Ok((process_search(ctx, searcher, schema, out.0)?, out.1))
+                collect_hits(ctx, searcher, schema, out, collapse)
             },
             _ => Err(Error::msg("field is not a fast field")),
         };
@@ -249,7 +778,7 @@ fn order_and_sort(
             let out: (Vec<(Reverse<i64>, DocAddress)>, usize) = searcher
                 .search_with_executor(query, &(collector, Count), executor)
                 .map_err(Error::from)?;
-            (process_search(ctx, searcher, schema, out.0)?, out.1)
+            collect_hits(ctx, searcher, schema, out, collapse)?
         },
         FieldType::U64(_) => {
             let collector =
@@ -268,7 +797,7 @@ fn order_and_sort(
             let out: (Vec<(Reverse<u64>, DocAddress)>, usize) = searcher
                 .search_with_executor(query, &(collector, Count), executor)
                 .map_err(Error::from)?;
-            (process_search(ctx, searcher, schema, out.0)?, out.1)
+            collect_hits(ctx, searcher, schema, out, collapse)?
         },
         FieldType::F64(_) => {
             let collector =
@@ -287,7 +816,7 @@ fn order_and_sort(
             let out: (Vec<(Reverse<f64>, DocAddress)>, usize) = searcher
                 .search_with_executor(query, &(collector, Count), executor)
                 .map_err(Error::from)?;
-            (process_search(ctx, searcher, schema, out.0)?, out.1)
+            collect_hits(ctx, searcher, schema, out, collapse)?
         },
         FieldType::Date(_) => {
             let collector =
@@ -306,7 +835,7 @@ fn order_and_sort(
             let out: (Vec<(Reverse<DateTime>, DocAddress)>, usize) = searcher
                 .search_with_executor(query, &(collector, Count), executor)
                 .map_err(Error::from)?;
-            (process_search(ctx, searcher, schema, out.0)?, out.1)
+            collect_hits(ctx, searcher, schema, out, collapse)?
         },
         _ => return Err(Error::msg("field is not a fast field")),
     };
@@ -314,6 +843,179 @@ fn order_and_sort(
     Ok(out)
 }
 
+/// Searches for documents within the radius of the geo filter.
+///
+/// The hits are ordered by their distance to the centre point, closest first.
+fn geo_search(
+    ctx: &SchemaContext,
+    schema: &Schema,
+    searcher: &Searcher,
+    query: &dyn Query,
+    executor: &Executor,
+    collector: GeoDistanceCollector,
+) -> Result<SearchHits> {
+    let (top_docs, count) =
+        searcher.search_with_executor(query, &collector, executor)?;
+
+    let distances: Vec<f64> = top_docs.iter().map(|(distance, _)| *distance).collect();
+    let start = Instant::now();
+    let mut hits = process_search(ctx, searcher, schema, top_docs)?;
+    for (hit, distance) in hits.iter_mut().zip(distances) {
+        hit.distance = Some(distance);
+    }
+
+    Ok(SearchHits {
+        hits,
+        count,
+        collapsed: 0,
+        total_after_collapse: None,
+        retrieval_time: start.elapsed(),
+        rescore_time: Duration::ZERO,
+    })
+}
+
+/// Describes how to rescore the top hits of a search.
+struct Rescore {
+    script: String,
+    window: usize,
+}
+
+/// The options describing which hits of a search are collected.
+struct SearchOptions {
+    limit: usize,
+    offset: usize,
+    sort: Sort,
+    order_by: Option<String>,
+    collapse_field: Option<String>,
+    geo_filter: Option<GeoFilter>,
+    rescore: Option<Rescore>,
+}
+
+/// Executes the query collecting the hits described by the given options.
+fn search_with_filters(
+    ctx: &SchemaContext,
+    searcher: &Searcher,
+    executor: &Executor,
+    query: &dyn Query,
+    opts: SearchOptions,
+) -> Result<SearchHits> {
+    let SearchOptions {
+        limit,
+        offset,
+        sort,
+        order_by,
+        collapse_field,
+        geo_filter,
+        rescore,
+    } = opts;
+    let schema = searcher.schema();
+
+    if let Some(ref filter) = geo_filter {
+        let collector =
+            GeoDistanceCollector::create(ctx, schema, filter, offset, limit)?;
+
+        return geo_search(ctx, schema, searcher, query, executor, collector);
+    }
+
+    let collapse = match collapse_field {
+        Some(name) => Some(Collapse {
+            field: get_collapse_field(ctx, schema, &name)?,
+            offset,
+            limit,
+        }),
+        None => None,
+    };
+
+    let collector = if let Some(ref rescore) = rescore {
+        TopDocs::with_limit(rescore.window)
+    } else if collapse.is_some() {
+        let window = offset.saturating_add(limit);
+        let fetch = window
+            .saturating_mul(COLLAPSE_OVERFETCH_FACTOR)
+            .min(window.saturating_add(MAX_COLLAPSE_OVERFETCH));
+
+        TopDocs::with_limit(fetch)
+    } else {
+        TopDocs::with_limit(limit).and_offset(offset)
+    };
+
+    let order_by = order_by.map(|v| schema.get_field(&v));
+
+    let mut search_hits = if let Some(Some(field)) = order_by {
+        order_and_sort(
+            sort, field, query, ctx, schema, searcher, collector, executor, collapse,
+        )?
+    } else {
+        let (top_docs, count) =
+            searcher.search_with_executor(query, &(collector, Count), executor)?;
+
+        // The whole window is rescored before the requested page is taken,
+        // collapsing applies its own offset and limit.
+        let rescore_start = Instant::now();
+        let top_docs = match rescore {
+            Some(rescore) => {
+                let rescored =
+                    rescore_top_docs(ctx, searcher, &rescore.script, top_docs)?;
+
+                if collapse.is_some() {
+                    rescored
+                } else {
+                    rescored.into_iter().skip(offset).take(limit).collect()
+                }
+            },
+            None => top_docs,
+        };
+
+        let rescore_time = rescore_start.elapsed();
+
+        let mut search_hits =
+            collect_hits(ctx, searcher, schema, (top_docs, count), collapse)?;
+        search_hits.rescore_time = rescore_time;
+        search_hits
+    };
+
+    // Only the over-fetched hits are collapsed, so the total amount of
+    // groups is estimated with a second pass over all matching documents.
+    if let Some(collapse) = collapse {
+        let collector = CardinalityCollector::new(collapse.field);
+        let sketch = searcher.search_with_executor(query, &collector, executor)?;
+        search_hits.total_after_collapse = Some(sketch.estimate());
+    }
+
+    Ok(search_hits)
+}
+
+/// Gets the field to collapse results by.
+///
+/// The field must be a single value fast field.
+fn get_collapse_field(
+    ctx: &SchemaContext,
+    schema: &Schema,
+    name: &str,
+) -> Result<Field> {
+    let field = schema
+        .get_field(name)
+        .ok_or_else(|| anyhow!("no field exists with name: {:?}", name))?;
+
+    if ctx.multi_value_fields().contains(name) {
+        return Err(anyhow!(
+            "multi-value fields cannot be used to collapse results"
+        ));
+    }
+
+    match schema.get_field_entry(field).field_type() {
+        FieldType::I64(opts)
+        | FieldType::U64(opts)
+        | FieldType::F64(opts)
+        | FieldType::Date(opts)
+            if opts.is_fast() =>
+        {
+            Ok(field)
+        },
+        _ => Err(Error::msg("collapse field is not a fast field")),
+    }
+}
+
 /// The reader of the given index.
 ///
 /// This manages all searches on the index which encompasses the concurrency
@@ -330,7 +1032,25 @@ pub(crate) struct Reader {
     pool: crate::ReaderExecutor,
 
     /// The query factory system.
-    query_handler: Arc<QueryBuilder>,
+    ///
+    /// This is swapped out when the index's search fields are changed.
+    query_handler: Arc<ArcSwap<QueryBuilder>>,
+
+    /// The limit to use for queries which do not specify one.
+    default_limit: usize,
+
+    /// The offset to use for queries which do not specify one.
+    default_offset: usize,
+
+    /// The maximum `offset + limit` a query can request.
+    max_result_window: Option<usize>,
+
+    /// If the reader is warmed up after each commit.
+    warm_on_reload: bool,
+
+    /// The query the reader is warmed up with, matching all documents if
+    /// not set.
+    warmup_query: Option<Arc<str>>,
 }
 
 impl Reader {
@@ -353,8 +1073,15 @@ impl Reader {
                 reader,
                 ctx.reader_ctx.reader_threads,
                 ctx.reader_ctx.max_concurrency,
+                ThreadOptions {
+                    niceness: ctx.reader_ctx.thread_niceness,
+                    cpu_affinity: ctx.reader_ctx.thread_affinity.clone(),
+                },
             )
-            .await?;
+            .await?
+            .with_acquire_timeout(
+                ctx.reader_ctx.acquire_timeout.map(Duration::from_millis),
+            );
             Arc::new(pool)
         };
         info!(
@@ -371,65 +1098,178 @@ impl Reader {
             ctx.synonyms.clone(),
             &ctx.index,
             pool.clone(),
+            Arc::new(FilterCache::new(ctx.reader_ctx.filter_cache_size)),
         );
         info!(
             "query builder constructed with config: fast-fuzzy={} strip-stop-words={}.",
             ctx.query_ctx.use_fast_fuzzy, ctx.query_ctx.strip_stop_words,
         );
 
+        // The warmup query is parsed up front so invalid queries are
+        // rejected when the index is created rather than after each commit.
+        if let Some(ref query) = ctx.reader_ctx.warmup_query {
+            query_handler
+                .parse_normal_query(query)
+                .with_context(|| format!("invalid warmup query {:?}", query))?;
+        }
+
         Ok(Self {
             index_name: Cow::Owned(ctx.name()),
             schema_ctx: Cow::Owned(ctx.schema_ctx.clone()),
             pool,
-            query_handler: Arc::new(query_handler),
+            query_handler: Arc::new(ArcSwap::from_pointee(query_handler)),
+            default_limit: ctx.reader_ctx.default_limit,
+            default_offset: ctx.reader_ctx.default_offset,
+            max_result_window: ctx.reader_ctx.max_result_window,
+            warm_on_reload: ctx.reader_ctx.warm_on_reload,
+            warmup_query: ctx.reader_ctx.warmup_query.as_deref().map(Arc::from),
         })
     }
 
+    /// Rejects queries paging past the reader's `max_result_window`.
+    fn check_result_window(&self, offset: usize, limit: usize) -> Result<()> {
+        match self.max_result_window {
+            Some(window) if offset.saturating_add(limit) > window => Err(anyhow!(
+                "offset + limit must not exceed the index's max result window of {}, \
+                 refine the query rather than paging this deep into the results.",
+                window,
+            )),
+            _ => Ok(()),
+        }
+    }
+
     /// Gets a list of suggested corrections based off of the index corpus.
     pub fn get_corrected_query_hint(&self, query: &str) -> String {
-        self.query_handler.get_corrected_query_hint(query)
+        self.query_handler.load().get_corrected_query_hint(query)
+    }
+
+    /// Builds the query of the given payload without executing it.
+    pub(crate) async fn explain_query(
+        &self,
+        qry: QueryPayload,
+    ) -> Result<QueryExplanation> {
+        self.query_handler
+            .load_full()
+            .explain_query(
+                qry.query,
+                qry.default_operator,
+                qry.minimum_should_match,
+                qry.raw_query,
+                qry.extra_search_fields.as_deref().unwrap_or_default(),
+            )
+            .await
+    }
+
+    /// Gets up to `limit` terms of the field starting with the given prefix.
+    pub(crate) async fn suggest(
+        &self,
+        field: String,
+        prefix: String,
+        limit: usize,
+    ) -> Result<Vec<Suggestion>> {
+        if limit == 0 {
+            return Err(Error::msg("limit must be at least 1."));
+        }
+
+        let ctx = self.schema_ctx.clone();
+        self.pool
+            .spawn(move |searcher, _| {
+                suggest_terms(ctx.as_ref(), &searcher, &field, &prefix, limit)
+            })
+            .await?
+    }
+
+    /// Corrects each word of the query with the closest term in the field.
+    pub(crate) async fn spell_check(
+        &self,
+        field: String,
+        query: String,
+    ) -> Result<SpellCheck> {
+        let ctx = self.schema_ctx.clone();
+        self.pool
+            .spawn(move |searcher, _| {
+                spell_check(ctx.as_ref(), &searcher, &field, &query)
+            })
+            .await?
+    }
+
+    /// Gets the document counts and size of each of the searched segments.
+    pub(crate) async fn segment_diagnostics(&self) -> Result<Vec<SegmentDiagnostics>> {
+        self.pool
+            .spawn(move |searcher, _| {
+                let mut segments = Vec::with_capacity(searcher.segment_readers().len());
+                for segment_reader in searcher.segment_readers() {
+                    segments.push(SegmentDiagnostics {
+                        id: segment_reader.segment_id().uuid_string(),
+                        num_docs: segment_reader.num_docs(),
+                        num_deleted_docs: segment_reader.num_deleted_docs(),
+                        size_bytes: segment_reader.space_usage()?.total(),
+                    });
+                }
+
+                Ok::<_, Error>(segments)
+            })
+            .await?
     }
 
     /// Gets a singular document from the given id.
     ///
-    /// If no document is found an error is raised without context.
+    /// If no document is found `None` is returned.
+    pub(crate) async fn get_document(
+        &self,
+        id: DocumentId,
+    ) -> Result<Option<DocumentHit>> {
+        let mut documents = self.get_documents(vec![id]).await?;
+        Ok(documents.pop().flatten())
+    }
+
+    /// Gets a set of documents from the given ids.
+    ///
+    /// The documents are returned in the same order as the given ids with
+    /// `None` in place of any document which does not exist.
     #[instrument(name = "document-fetcher", skip(self), fields(index = %self.index_name))]
-    pub(crate) async fn get_document(&self, id: DocumentId) -> Result<DocumentHit> {
-        let id_field = self.query_handler.id_field();
-        let document = self
-            .pool
+    pub(crate) async fn get_documents(
+        &self,
+        ids: Vec<DocumentId>,
+    ) -> Result<Vec<Option<DocumentHit>>> {
+        let id_field = self.query_handler.load().id_field();
+        let ctx = self.schema_ctx.clone();
+
+        self.pool
             .spawn(move |searcher, executor| {
-                let qry = TermQuery::new(
-                    Term::from_field_u64(id_field, id),
-                    IndexRecordOption::Basic,
-                );
+                let schema = searcher.schema();
 
-                let mut results = searcher.search_with_executor(
-                    &qry,
-                    &TopDocs::with_limit(1),
-                    executor,
-                )?;
-                if results.is_empty() {
-                    return Err(Error::msg(format!(
-                        "no document exists with id: '{}'",
-                        id
+                let mut documents = Vec::with_capacity(ids.len());
+                for id in ids {
+                    let qry = TermQuery::new(
+                        Term::from_field_u64(id_field, id),
+                        IndexRecordOption::Basic,
+                    );
+
+                    let mut results = searcher.search_with_executor(
+                        &qry,
+                        &TopDocs::with_limit(1),
+                        executor,
+                    )?;
+                    if results.is_empty() {
+                        documents.push(None);
+                        continue;
+                    }
+
+                    let (_, addr) = results.remove(0);
+                    let doc = searcher.doc(addr)?;
+
+                    documents.push(Some(DocumentHit::from_tantivy_document(
+                        ctx.as_ref(),
+                        id,
+                        schema.to_named_doc(&doc),
+                        Some(1.0),
                     )));
                 }
 
-                let (_, addr) = results.remove(0);
-                let doc = searcher.doc(addr)?;
-                let schema = searcher.schema();
-
-                Ok(schema.to_named_doc(&doc))
+                Ok::<_, Error>(documents)
             })
-            .await??;
-
-        Ok(DocumentHit::from_tantivy_document(
-            self.schema_ctx.as_ref(),
-            id,
-            document,
-            Some(1.0),
-        ))
+            .await?
     }
 
     /// Searches the index reader with the given query payload.
@@ -437,71 +1277,403 @@ impl Reader {
     /// The payload determines the behaviour of the query results.
     /// The actual behaviour of how a query is built is upto the query handler
     /// which will parse and interpret the given data.
-    #[instrument(name = "document-searcher", skip_all, fields(index = %self.index_name))]
+    #[instrument(
+        name = "document-searcher",
+        skip_all,
+        fields(index = %self.index_name, mode = qry.query.mode())
+    )]
     pub(crate) async fn search(&self, qry: QueryPayload) -> Result<QueryResults> {
-        let start = std::time::Instant::now();
+        let start = Instant::now();
+
+        let limit = qry.limit.unwrap_or(self.default_limit);
+        if limit == 0 {
+            return Err(Error::msg("limit must be at least 1."));
+        }
 
-        let limit = qry.limit;
         let sort = qry.sort;
-        let order_by = qry.order_by;
-        let offset = qry.offset;
-        let query = self.query_handler.build_query(qry.query).await?;
+        let offset = qry.offset.unwrap_or(self.default_offset);
+        if !qry.unbounded {
+            self.check_result_window(offset, limit)?;
+        }
+
+        // Aliases are resolved here as the fields are looked up on the executor.
+        let order_by = qry
+            .order_by
+            .map(|name| self.schema_ctx.resolve_field(&name).to_string());
+        let collapse_field = qry
+            .collapse_field
+            .map(|name| self.schema_ctx.resolve_field(&name).to_string());
+        let geo_filter = qry.geo_filter.map(|mut filter| {
+            filter.field = self.schema_ctx.resolve_field(&filter.field).to_string();
+            filter
+        });
+
+        if geo_filter.is_some() && (order_by.is_some() || collapse_field.is_some()) {
+            return Err(Error::msg(
+                "geo filters cannot be combined with `order_by` or `collapse_field`",
+            ));
+        }
+
+        let rescore = match qry.rescore_script {
+            None => None,
+            Some(script) => {
+                if geo_filter.is_some() || order_by.is_some() {
+                    return Err(Error::msg(
+                        "rescore scripts cannot be combined with `order_by` or geo filters",
+                    ));
+                }
+
+                let page = offset.saturating_add(limit);
+                let window = qry.rescore_window.unwrap_or(page);
+                if window < page {
+                    return Err(Error::msg(
+                        "the rescore window must cover the search's offset and limit",
+                    ));
+                }
+
+                if window > MAX_RESCORE_HITS {
+                    return Err(anyhow!(
+                        "rescore scripts can only be used with a window of at most {} hits",
+                        MAX_RESCORE_HITS
+                    ));
+                }
+
+                Some(Rescore { script, window })
+            },
+        };
+
+        let normalize_scores = qry.normalize_scores;
+        let profile = qry.profile;
+        let parallel = qry.parallel;
+
+        let highlighter = qry.highlight_fields.map(|fields| Highlighter {
+            fields,
+            pre_tag: qry
+                .highlight_pre_tag
+                .unwrap_or_else(|| DEFAULT_PRE_TAG.to_string()),
+            post_tag: qry
+                .highlight_post_tag
+                .unwrap_or_else(|| DEFAULT_POST_TAG.to_string()),
+            max_snippets_per_field: qry.max_snippets_per_field.unwrap_or(1),
+        });
+
+        let parse_start = Instant::now();
+        let query = self
+            .query_handler
+            .load_full()
+            .build_query(
+                qry.query,
+                qry.default_operator,
+                qry.minimum_should_match,
+                qry.raw_query,
+                qry.extra_search_fields.as_deref().unwrap_or_default(),
+            )
+            .instrument(trace_span!("query-builder"))
+            .await?;
+        let parse_time = parse_start.elapsed();
         let ctx = self.schema_ctx.clone();
 
-        let (hits, count) = self
+        // The search runs on the executor pool so the span must be carried over.
+        let span = debug_span!("search-executor");
+        let (search_hits, timings) = self
             .pool
             .spawn(move |searcher, executor| {
+                let _enter = span.enter();
                 let schema = searcher.schema();
-                let collector = TopDocs::with_limit(limit).and_offset(offset);
+                let mut timings = HashMap::new();
 
-                let order_by = order_by.map(|v| schema.get_field(&v));
+                let single_thread;
+                let executor = if parallel == Some(false) {
+                    single_thread = Executor::single_thread();
+                    &single_thread
+                } else {
+                    executor
+                };
 
-                let (hits, count) = if let Some(Some(field)) = order_by {
-                    order_and_sort(
+                let phase_start = Instant::now();
+                let mut search_hits = search_with_filters(
+                    ctx.as_ref(),
+                    &searcher,
+                    executor,
+                    query.as_ref(),
+                    SearchOptions {
+                        limit,
+                        offset,
                         sort,
-                        field,
-                        &query,
+                        order_by,
+                        collapse_field,
+                        geo_filter,
+                        rescore,
+                    },
+                )?;
+
+                let collect_time = phase_start
+                    .elapsed()
+                    .saturating_sub(search_hits.retrieval_time)
+                    .saturating_sub(search_hits.rescore_time);
+                timings.insert("collect", collect_time);
+                timings.insert("retrieve", search_hits.retrieval_time);
+
+                let phase_start = Instant::now();
+                if normalize_scores {
+                    normalize_hit_scores(&mut search_hits.hits);
+                }
+                timings
+                    .insert("rescore", search_hits.rescore_time + phase_start.elapsed());
+
+                let phase_start = Instant::now();
+                if let Some(ref highlighter) = highlighter {
+                    highlighter.apply(
                         ctx.as_ref(),
                         schema,
                         &searcher,
-                        collector,
-                        executor,
-                    )?
-                } else {
-                    let (out, count) = searcher.search_with_executor(
-                        &query,
-                        &(collector, Count),
-                        executor,
+                        query.as_ref(),
+                        &mut search_hits.hits,
                     )?;
-                    (process_search(ctx.as_ref(), &searcher, schema, out)?, count)
-                };
+                }
+                timings.insert("highlight", phase_start.elapsed());
 
-                Ok::<_, Error>((hits, count))
+                Ok::<_, Error>((search_hits, timings))
             })
             .await??;
 
         let elapsed = start.elapsed();
 
+        let timings = profile.then(|| {
+            std::iter::once(("parse", parse_time))
+                .chain(timings)
+                .map(|(phase, time)| (phase.to_string(), time.as_secs_f64()))
+                .collect()
+        });
+
         Ok(QueryResults {
             time_taken: elapsed.as_secs_f32(), // filled in by handler later
-            hits,
-            count,
+            hits: search_hits.hits,
+            count: search_hits.count,
+            collapsed: search_hits.collapsed,
+            total_after_collapse: search_hits.total_after_collapse,
+            timings,
         })
     }
 
+    /// Searches the index streaming the hits in chunks through the returned
+    /// receiver rather than collecting every hit before returning.
+    ///
+    /// This is intended for searches with large limits, only the payload's
+    /// query, limit and offset are supported and hits are always ordered by
+    /// relevancy. The search holds one of the reader's concurrency permits
+    /// until every hit has been sent or the receiver is dropped.
+    #[instrument(
+        name = "document-streamer",
+        skip_all,
+        fields(index = %self.index_name, mode = qry.query.mode())
+    )]
+    pub(crate) async fn search_stream(
+        &self,
+        qry: QueryPayload,
+    ) -> Result<mpsc::Receiver<Result<Vec<DocumentHit>>>> {
+        let limit = qry.limit.unwrap_or(self.default_limit);
+        if limit == 0 {
+            return Err(Error::msg("limit must be at least 1."));
+        }
+
+        let offset = qry.offset.unwrap_or(self.default_offset);
+        self.check_result_window(offset, limit)?;
+
+        let is_unsupported = qry.order_by.is_some()
+            || qry.collapse_field.is_some()
+            || qry.geo_filter.is_some()
+            || qry.highlight_fields.is_some()
+            || qry.rescore_script.is_some()
+            || qry.normalize_scores
+            || qry.profile;
+
+        if is_unsupported {
+            return Err(Error::msg(
+                "streamed searches cannot be combined with `order_by`, `collapse_field`, \
+                 `geo_filter`, `highlight_fields`, `rescore_script`, `normalize_scores` \
+                 or `profile`",
+            ));
+        }
+
+        let query = self
+            .query_handler
+            .load_full()
+            .build_query(
+                qry.query,
+                qry.default_operator,
+                qry.minimum_should_match,
+                qry.raw_query,
+                qry.extra_search_fields.as_deref().unwrap_or_default(),
+            )
+            .instrument(trace_span!("query-builder"))
+            .await?;
+
+        let (tx, rx) = mpsc::channel(2);
+        let ctx = self.schema_ctx.clone();
+        let pool = self.pool.clone();
+        let parallel = qry.parallel;
+
+        tokio::spawn(async move {
+            // The hits are sent before the task returns so the searcher and
+            // the pool's permit are only released once streaming finishes.
+            let res = pool
+                .spawn({
+                    let tx = tx.clone();
+                    move |searcher, executor| {
+                        let single_thread;
+                        let executor = if parallel == Some(false) {
+                            single_thread = Executor::single_thread();
+                            &single_thread
+                        } else {
+                            executor
+                        };
+
+                        let collector = TopDocs::with_limit(limit).and_offset(offset);
+                        let top_docs = searcher.search_with_executor(
+                            query.as_ref(),
+                            &collector,
+                            executor,
+                        )?;
+
+                        stream_hits(ctx.as_ref(), &searcher, &top_docs, &tx)
+                    }
+                })
+                .await;
+
+            if let Err(e) = res.and_then(|res| res) {
+                let _ = tx.send(Err(e)).await;
+            }
+        });
+
+        Ok(rx)
+    }
+
+    /// Exports every document in the index in chunks of `chunk_size`,
+    /// optionally limited to a single segment.
+    ///
+    /// The documents are read by the reader's pool, so exports count
+    /// towards the reader's concurrency limit like any other search, and
+    /// sent through the returned receiver. Dropping the receiver or
+    /// reaching any of the limits stops the export.
+    pub(crate) fn export_documents(
+        &self,
+        chunk_size: usize,
+        segment: Option<String>,
+        limits: ExportLimits,
+    ) -> mpsc::Receiver<Result<Vec<DocumentHit>>> {
+        let (tx, rx) = mpsc::channel(2);
+        let ctx = self.schema_ctx.clone();
+        let pool = self.pool.clone();
+
+        tokio::spawn(async move {
+            let export_tx = tx.clone();
+            let res = pool
+                .spawn(move |searcher, _| {
+                    export_documents(
+                        ctx.as_ref(),
+                        &searcher,
+                        chunk_size,
+                        segment.as_deref(),
+                        limits,
+                        &export_tx,
+                    )
+                })
+                .await;
+
+            if let Err(e) = res.and_then(|res| res) {
+                let _ = tx.send(Err(e)).await;
+            }
+        });
+
+        rx
+    }
+
     pub(crate) fn get_synonyms(&self) -> HashMap<String, Box<[String]>> {
-        self.query_handler.synonyms()
+        self.query_handler.load().synonyms()
     }
 
     pub(crate) fn get_stop_words(&self) -> Vec<String> {
-        self.query_handler.stop_words()
+        self.query_handler.load().stop_words()
+    }
+
+    /// Removes the cached documents of every filter.
+    ///
+    /// This must be called after the index's stop words or synonyms change
+    /// as cached filters would otherwise keep matching the old words.
+    pub(crate) fn clear_filter_cache(&self) {
+        self.query_handler.load().clear_filter_cache()
+    }
+
+    /// Gets the fields searched by normal and fuzzy queries along with
+    /// their boost factor.
+    pub(crate) fn search_fields(&self) -> (Vec<(Field, Score)>, Vec<(Field, Score)>) {
+        let query_handler = self.query_handler.load();
+        let (default_fields, fuzzy_fields) = query_handler.search_fields();
+
+        (default_fields.to_vec(), fuzzy_fields.to_vec())
+    }
+
+    /// Changes the boost factor of the fields searched by queries.
+    ///
+    /// Searches already in progress continue with the previous boosts.
+    pub(crate) fn set_search_fields(
+        &self,
+        default_search_fields: Vec<(Field, Score)>,
+        fuzzy_search_fields: Vec<(Field, Score)>,
+    ) {
+        let query_handler = self
+            .query_handler
+            .load()
+            .with_search_fields(default_search_fields, fuzzy_search_fields);
+
+        self.query_handler.store(Arc::new(query_handler));
     }
 
     /// This forces the reader to reload after a commit.
+    ///
+    /// This is also used to pick up any changes made to the index data
+    /// externally which the reader would otherwise not see.
     pub(crate) fn force_reload(&self) -> Result<()> {
         self.pool.reload()
     }
 
+    #[inline]
+    pub(crate) fn warm_on_reload(&self) -> bool {
+        self.warm_on_reload
+    }
+
+    /// Runs the warmup query against the segments the reader last loaded.
+    ///
+    /// The top documents are also retrieved so the relevant parts of the
+    /// document store are loaded.
+    pub(crate) fn warm_up(&self) -> Result<()> {
+        let query: Box<dyn Query> = match self.warmup_query {
+            Some(ref query) => self.query_handler.load().parse_normal_query(query)?,
+            None => Box::new(AllQuery),
+        };
+
+        let searcher = self.pool.searcher();
+
+        let collector = (Count, TopDocs::with_limit(self.default_limit));
+        let (count, top_docs) = searcher.search(query.as_ref(), &collector)?;
+        for (_, address) in top_docs {
+            searcher.doc(address)?;
+        }
+
+        debug!(
+            "index warmed up, {} documents matched the warmup query",
+            count
+        );
+
+        Ok(())
+    }
+
+    /// Shuts down the reader's executor pool rejecting any new searches.
+    pub(crate) fn shutdown(&self) {
+        self.pool.shutdown()
+    }
+
     /// This should not be used for general things.
     ///
     /// This is an internal export to allow the writer
@@ -0,0 +1,138 @@
+use std::cmp::Ordering;
+
+use anyhow::{anyhow, Error, Result};
+use hashbrown::HashMap;
+use rhai::{Dynamic, Engine, Scope, AST};
+use tantivy::fastfield::{DynamicFastFieldReader, FastFieldReader};
+use tantivy::schema::{Field, FieldType, Schema};
+use tantivy::{DocAddress, DocId, Score, Searcher, SegmentOrdinal, SegmentReader};
+
+use crate::schema::SchemaContext;
+
+/// The maximum amount of hits a rescore script can be ran over.
+///
+/// This caps the rescore window and so the search's offset and limit.
+pub(crate) const MAX_RESCORE_HITS: usize = 1_000;
+
+/// The maximum amount of operations a single evaluation of a script can
+/// perform before being aborted.
+const MAX_SCRIPT_OPERATIONS: u64 = 10_000;
+
+/// A single value numeric fast field a rescore script can read.
+enum NumericFastField {
+    I64(DynamicFastFieldReader<i64>),
+    U64(DynamicFastFieldReader<u64>),
+    F64(DynamicFastFieldReader<f64>),
+}
+
+impl NumericFastField {
+    fn get(&self, doc: DocId) -> Dynamic {
+        match self {
+            Self::I64(reader) => Dynamic::from(reader.get(doc)),
+            Self::U64(reader) => Dynamic::from(reader.get(doc) as i64),
+            Self::F64(reader) => Dynamic::from(reader.get(doc)),
+        }
+    }
+}
+
+/// Gets the single value numeric fast fields of the index which are made
+/// available to rescore scripts.
+fn get_script_fields(ctx: &SchemaContext, schema: &Schema) -> Vec<(String, Field)> {
+    ctx.fields()
+        .keys()
+        .filter(|name| !ctx.multi_value_fields().contains(*name))
+        .filter_map(|name| {
+            let field = schema.get_field(name)?;
+            match schema.get_field_entry(field).field_type() {
+                FieldType::I64(opts) | FieldType::U64(opts) | FieldType::F64(opts)
+                    if opts.is_fast() =>
+                {
+                    Some((name.clone(), field))
+                },
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Opens the fast field readers of the script fields for the given segment.
+fn open_segment_fields(
+    schema: &Schema,
+    fields: &[(String, Field)],
+    segment_reader: &SegmentReader,
+) -> Result<Vec<NumericFastField>> {
+    let fast_fields = segment_reader.fast_fields();
+
+    let mut readers = Vec::with_capacity(fields.len());
+    for (_, field) in fields {
+        let reader = match schema.get_field_entry(*field).field_type() {
+            FieldType::I64(_) => NumericFastField::I64(fast_fields.i64(*field)?),
+            FieldType::U64(_) => NumericFastField::U64(fast_fields.u64(*field)?),
+            FieldType::F64(_) => NumericFastField::F64(fast_fields.f64(*field)?),
+            _ => unreachable!("script fields are always numeric"),
+        };
+
+        readers.push(reader);
+    }
+
+    Ok(readers)
+}
+
+fn evaluate(engine: &Engine, ast: &AST, scope: &mut Scope) -> Result<Score> {
+    let result = engine
+        .eval_ast_with_scope::<Dynamic>(scope, ast)
+        .map_err(|e| anyhow!("failed to evaluate rescore script: {}", e))?;
+
+    match (result.as_float(), result.as_int()) {
+        (Ok(v), _) => Ok(v as Score),
+        (_, Ok(v)) => Ok(v as Score),
+        _ => Err(Error::msg("rescore scripts must evaluate to a number")),
+    }
+}
+
+/// Rescores the given window of top docs with a script expression
+/// re-sorting them by their new score, highest first.
+///
+/// The script has access to the hit's `score` and the values of any single
+/// value numeric fast fields as variables, e.g. `score * popularity`. The
+/// values are read from the fast fields so no documents are retrieved.
+pub(crate) fn rescore_top_docs(
+    ctx: &SchemaContext,
+    searcher: &Searcher,
+    script: &str,
+    top_docs: Vec<(Score, DocAddress)>,
+) -> Result<Vec<(Score, DocAddress)>> {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_SCRIPT_OPERATIONS);
+
+    let ast = engine
+        .compile_expression(script)
+        .map_err(|e| anyhow!("invalid rescore script: {}", e))?;
+
+    let schema = searcher.schema();
+    let fields = get_script_fields(ctx, schema);
+
+    let mut segments: HashMap<SegmentOrdinal, Vec<NumericFastField>> = HashMap::new();
+    let mut rescored = Vec::with_capacity(top_docs.len());
+    for (score, address) in top_docs {
+        if !segments.contains_key(&address.segment_ord) {
+            let segment_reader = searcher.segment_reader(address.segment_ord);
+            let readers = open_segment_fields(schema, &fields, segment_reader)?;
+            segments.insert(address.segment_ord, readers);
+        }
+
+        let mut scope = Scope::new();
+        scope.push("score", score as f64);
+
+        let readers = &segments[&address.segment_ord];
+        for ((name, _), reader) in fields.iter().zip(readers) {
+            scope.push_dynamic(name.as_str(), reader.get(address.doc_id));
+        }
+
+        rescored.push((evaluate(&engine, &ast, &mut scope)?, address));
+    }
+
+    rescored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+
+    Ok(rescored)
+}
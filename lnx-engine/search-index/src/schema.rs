@@ -1,8 +1,12 @@
+use std::fmt;
 use std::iter::FromIterator;
+use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{anyhow, Error, Result};
 use hashbrown::{HashMap, HashSet};
-use serde::{Deserialize, Serialize};
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
 use tantivy::schema::{
     Cardinality,
     FacetOptions,
@@ -18,22 +22,99 @@ use tantivy::schema::{
     INDEXED,
     STORED,
 };
-use tantivy::Score;
+use tantivy::tokenizer::{
+    BoxTokenStream,
+    Language,
+    LowerCaser,
+    RawTokenizer,
+    RemoveLongFilter,
+    SimpleTokenizer,
+    Stemmer,
+    StopWordFilter,
+    TextAnalyzer,
+    Token,
+    TokenFilter,
+    TokenStream,
+};
+use tantivy::{Index, Score};
 
 use crate::helpers::{Calculated, Validate};
+use crate::pipeline::Processor;
+use crate::stop_words::{default_stop_words, StopWordLanguage};
 
 pub static PRIMARY_KEY: &str = "_id";
 
+/// The largest term in bytes tantivy can index.
+pub(crate) const MAX_TERM_LEN: usize = u16::MAX as usize - 4;
+
+/// The prefix of the tokenizer names used by stemmed text fields.
+static STEMMING_TOKENIZER_PREFIX: &str = "lnx_stem_";
+
+/// The prefix of the tokenizer names used by text fields with a language.
+static LANGUAGE_TOKENIZER_PREFIX: &str = "lnx_lang_";
+
+/// The maximum amount of decimal places float values can be rounded to,
+/// f64 values cannot represent any more than this.
+const MAX_OUTPUT_PRECISION: u32 = 15;
+
 fn default_to_true() -> bool {
     true
 }
 
+/// Deserializes the index's fields, rejecting any field declared more
+/// than once.
+///
+/// Maps would otherwise silently keep the last declaration of the field.
+fn deserialize_unique_fields<'de, D>(
+    deserializer: D,
+) -> std::result::Result<HashMap<String, FieldDeclaration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct FieldsVisitor;
+
+    impl<'de> Visitor<'de> for FieldsVisitor {
+        type Value = HashMap<String, FieldDeclaration>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a map of field names to field declarations")
+        }
+
+        fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            let mut fields = HashMap::new();
+            let mut positions: HashMap<String, usize> = HashMap::new();
+
+            let mut position = 1;
+            while let Some((name, declaration)) = map.next_entry::<String, _>()? {
+                if let Some(first) = positions.get(&name) {
+                    return Err(de::Error::custom(format!(
+                        "field {:?} is declared more than once, at positions {} and {}",
+                        name, first, position,
+                    )));
+                }
+
+                positions.insert(name.clone(), position);
+                fields.insert(name, declaration);
+                position += 1;
+            }
+
+            Ok(fields)
+        }
+    }
+
+    deserializer.deserialize_map(FieldsVisitor)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SchemaContext {
     /// The index's fields.
     ///
     /// Document entries will be implicitly converted into the index's schema types
     /// if they need to be.
+    #[serde(deserialize_with = "deserialize_unique_fields")]
     fields: HashMap<String, FieldDeclaration>,
 
     /// The fields what are actually searched via tantivy.
@@ -49,6 +130,14 @@ pub struct SchemaContext {
     #[serde(default)]
     search_fields: Vec<String>,
 
+    /// The single field searched by the terms of a normal query which are
+    /// not prefixed with a field name.
+    ///
+    /// Terms prefixed with a field name still search that field. By default
+    /// unprefixed terms search every field of `search_fields`.
+    #[serde(default)]
+    default_search_field: Option<String>,
+
     /// A set of fields to boost by a given factor.
     ///
     /// The score of each field is adjusted so that it is the score of the original
@@ -56,11 +145,137 @@ pub struct SchemaContext {
     #[serde(default)]
     boost_fields: HashMap<String, Score>,
 
+    /// The field used to derive each document's id.
+    ///
+    /// Documents with the same value in this field share the same id, so
+    /// adding a document replaces any existing document with that id.
+    /// Integer values are used directly while text values are hashed.
+    ///
+    /// By default each document is given a random id.
+    #[serde(default)]
+    id_field: Option<String>,
+
+    /// If documents containing fields which are not defined in the schema
+    /// should be rejected.
+    ///
+    /// By default unknown fields are silently dropped.
+    #[serde(default)]
+    strict: bool,
+
+    /// A set of alternative names mapped to the field they refer to.
+    ///
+    /// Aliases can be used in place of the field's name when querying,
+    /// ordering or collapsing results.
+    #[serde(default)]
+    field_aliases: HashMap<String, String>,
+
+    /// A set of text fields mapped to the source fields they are populated
+    /// from when documents are added.
+    ///
+    /// The values of the source fields are joined together into a single
+    /// value of the compound field, so searching it is equivalent to
+    /// searching every source field. This is typically combined with
+    /// `default_search_field` to search a single catch-all field.
+    #[serde(default)]
+    compound_fields: HashMap<String, Vec<String>>,
+
+    /// The fields queries are allowed to explicitly target e.g. `title:sea`
+    /// in a normal query, a term query's `fields` or `extra_search_fields`.
+    ///
+    /// Queries targeting any other field are rejected, by default any
+    /// field can be targeted.
+    #[serde(default)]
+    queryable_fields: Option<Vec<String>>,
+
+    /// The processors applied in order to each added document before it is
+    /// validated and indexed.
+    #[serde(default)]
+    ingest_pipeline: Vec<Processor>,
+
+    /// The analyzer used by every text field which does not set its own
+    /// tokenizer or stemming language.
+    ///
+    /// Like the field tokenizers this cannot be changed once the index has
+    /// been created.
+    ///
+    /// By default this is the `simple` analyzer.
+    #[serde(default)]
+    default_analyzer: Option<IndexAnalyzer>,
+
+    /// The field used to detect duplicate documents on ingest.
+    ///
+    /// When a document is added with a value for this field which already
+    /// exists in the index it is either skipped or replaces the existing
+    /// documents depending on the `dedup_mode`. This must be a single value
+    /// indexed u64, i64 or string field.
+    ///
+    /// Each added document costs an extra term deletion, or an extra term
+    /// lookup against the committed documents when skipping, so this
+    /// noticeably lowers the ingest throughput of large imports.
+    #[serde(default)]
+    dedup_field: Option<String>,
+
+    /// How documents with a duplicate `dedup_field` value are handled.
+    ///
+    /// Defaults to `replace`.
+    #[serde(default)]
+    dedup_mode: DedupMode,
+
+    /// How string field values larger than the maximum term length are
+    /// handled when adding documents.
+    ///
+    /// String fields index their whole value as a single term, which
+    /// tantivy limits to 65,530 bytes. Text fields are split into smaller
+    /// terms by their tokenizer so are unaffected.
+    ///
+    /// Defaults to `truncate`, which stores the value in full but only
+    /// indexes its first 65,530 bytes. The amount of values truncated or
+    /// skipped is returned when adding documents.
+    #[serde(default)]
+    oversized_terms: OversizedTerms,
+
+    /// How long documents are kept for in seconds before being deleted.
+    ///
+    /// A document expires once the date in its `ttl_field` is older than
+    /// this, expired documents are periodically deleted in the background
+    /// and the deletion committed. Note that this also commits any other
+    /// pending changes to the index.
+    #[serde(default)]
+    document_ttl: Option<u64>,
+
+    /// The date field each document's age is taken from for the
+    /// `document_ttl`.
+    ///
+    /// This must be a single value indexed date field.
+    #[serde(default)]
+    ttl_field: Option<String>,
+
+    /// The order fields are returned in within each search hit.
+    ///
+    /// The listed fields come first in the given order followed by any
+    /// other fields sorted by name, fields missing from a hit are skipped.
+    #[serde(default)]
+    result_field_order: Option<Vec<String>>,
+
+    /// Whether fields without a value are returned as `null` in each
+    /// search hit.
+    ///
+    /// When disabled single value fields missing from a document are left
+    /// out of the hit entirely.
+    #[serde(default = "default_to_true")]
+    include_nulls: bool,
+
     #[serde(skip)]
     required_fields: HashSet<String>,
 
     #[serde(skip)]
     multi_value_fields: HashSet<String>,
+
+    #[serde(skip)]
+    output_precisions: Arc<HashMap<String, u32>>,
+
+    #[serde(skip)]
+    shared_field_order: Option<Arc<[String]>>,
 }
 
 impl Validate for SchemaContext {
@@ -69,6 +284,11 @@ impl Validate for SchemaContext {
             return Err(Error::msg("at least one indexed field must be defined."));
         }
 
+        for (name, info) in self.fields.iter() {
+            info.validate()
+                .map_err(|e| anyhow!("field {:?} has invalid options: {}", name, e))?;
+        }
+
         {
             let mut rejected_fields = vec![];
             for field_name in self.boost_fields.keys() {
@@ -86,6 +306,144 @@ impl Validate for SchemaContext {
             }
         }
 
+        if let Some(ref id_field) = self.id_field {
+            self.verify_id_field(id_field)?;
+        }
+
+        if let Some(ref dedup_field) = self.dedup_field {
+            self.verify_dedup_field(dedup_field)?;
+        }
+
+        match (self.document_ttl, self.ttl_field.as_deref()) {
+            (None, None) => {},
+            (Some(0), _) => {
+                return Err(Error::msg("key 'document_ttl' must be at least 1 second."))
+            },
+            (Some(_), Some(ttl_field)) => self.verify_ttl_field(ttl_field)?,
+            (Some(_), None) | (None, Some(_)) => {
+                return Err(Error::msg(
+                    "keys 'document_ttl' and 'ttl_field' must be set together.",
+                ))
+            },
+        }
+
+        for (alias, field_name) in self.field_aliases.iter() {
+            if self.has_field(alias) || alias == PRIMARY_KEY {
+                return Err(anyhow!(
+                    "key 'field_aliases' contains an alias which is already a field name: {}",
+                    alias,
+                ));
+            }
+
+            if !self.has_field(field_name) {
+                return Err(anyhow!(
+                    "key 'field_aliases' maps alias {:?} to a field that is not defined in the schema: {}",
+                    alias,
+                    field_name,
+                ));
+            }
+        }
+
+        for (name, sources) in self.compound_fields.iter() {
+            match self.fields.get(name) {
+                Some(FieldDeclaration::Text { .. }) => {},
+                Some(_) => {
+                    return Err(anyhow!(
+                        "key 'compound_fields' contains the field {:?} which is not a text field",
+                        name,
+                    ))
+                },
+                None => {
+                    return Err(anyhow!(
+                        "key 'compound_fields' contains the field {:?} which is not defined in the schema",
+                        name,
+                    ))
+                },
+            }
+
+            if sources.is_empty() {
+                return Err(anyhow!(
+                    "key 'compound_fields' must give at least one source field for {:?}",
+                    name,
+                ));
+            }
+
+            for source in sources {
+                if !self.has_field(source) {
+                    return Err(anyhow!(
+                        "key 'compound_fields' maps {:?} from a field that is not defined in the schema: {}",
+                        name,
+                        source,
+                    ));
+                }
+
+                if self.compound_fields.contains_key(source) {
+                    return Err(anyhow!(
+                        "key 'compound_fields' maps {:?} from another compound field: {}",
+                        name,
+                        source,
+                    ));
+                }
+            }
+        }
+
+        for processor in self.ingest_pipeline.iter() {
+            let rejected_fields: Vec<&str> = processor
+                .fields()
+                .into_iter()
+                .filter(|name| !self.has_field(name))
+                .collect();
+
+            if !rejected_fields.is_empty() {
+                return Err(anyhow!(
+                    "key 'ingest_pipeline' contain {} fields that are not defined in the schema: {}",
+                    rejected_fields.len(),
+                    rejected_fields.join(", "),
+                ));
+            }
+        }
+
+        if let Some(ref fields) = self.queryable_fields {
+            let rejected_fields: Vec<&str> = fields
+                .iter()
+                .filter(|name| !self.has_field(name))
+                .map(|name| name.as_str())
+                .collect();
+
+            if !rejected_fields.is_empty() {
+                return Err(anyhow!(
+                    "key 'queryable_fields' contain {} fields that are not defined in the schema: {}",
+                    rejected_fields.len(),
+                    rejected_fields.join(", "),
+                ));
+            }
+        }
+
+        if let Some(ref order) = self.result_field_order {
+            let rejected_fields: Vec<&str> = order
+                .iter()
+                .filter(|name| !self.has_field(name))
+                .map(|name| name.as_str())
+                .collect();
+
+            if !rejected_fields.is_empty() {
+                return Err(anyhow!(
+                    "key 'result_field_order' contain {} fields that are not defined in the schema: {}",
+                    rejected_fields.len(),
+                    rejected_fields.join(", "),
+                ));
+            }
+        }
+
+        if let Some(ref field_name) = self.default_search_field {
+            if !self.has_field(field_name) {
+                return Err(anyhow!(
+                    "key 'default_search_field' contains the field {:?} which is not defined in the schema",
+                    field_name,
+                ));
+            }
+        }
+
         // If it is empty we default to the indexed field.
         // So we know they are valid.
         if !self.search_fields.is_empty() {
@@ -147,6 +505,21 @@ impl Calculated for SchemaContext {
                 .cloned(),
         );
 
+        self.shared_field_order = self.result_field_order.as_deref().map(Arc::from);
+
+        self.output_precisions = Arc::new(
+            self.fields
+                .iter()
+                .filter_map(|(name, info)| match info {
+                    FieldDeclaration::F64 {
+                        output_precision: Some(precision),
+                        ..
+                    } => Some((name.clone(), *precision)),
+                    _ => None,
+                })
+                .collect(),
+        );
+
         Ok(())
     }
 }
@@ -161,11 +534,119 @@ impl SchemaContext {
         &self.boost_fields
     }
 
+    /// Replaces the field boosts with the given set.
+    ///
+    /// Only text and string fields can be boosted.
+    pub fn set_boost_fields(
+        &mut self,
+        boost_fields: HashMap<String, Score>,
+    ) -> Result<()> {
+        for name in boost_fields.keys() {
+            match self.fields.get(name) {
+                Some(FieldDeclaration::Text { .. } | FieldDeclaration::String { .. }) => {},
+                Some(_) => {
+                    return Err(anyhow!(
+                        "key 'boost_fields' must only contain text or string fields: {}",
+                        name,
+                    ))
+                },
+                None => {
+                    return Err(anyhow!(
+                        "key 'boost_fields' contains a field that is not defined in the schema: {}",
+                        name,
+                    ))
+                },
+            }
+        }
+
+        self.boost_fields = boost_fields;
+
+        Ok(())
+    }
+
     #[inline]
     pub fn fields(&self) -> &HashMap<String, FieldDeclaration> {
         &self.fields
     }
 
+    #[inline]
+    pub fn id_field(&self) -> Option<&str> {
+        self.id_field.as_deref()
+    }
+
+    #[inline]
+    pub fn dedup_field(&self) -> Option<&str> {
+        self.dedup_field.as_deref()
+    }
+
+    #[inline]
+    pub fn dedup_mode(&self) -> DedupMode {
+        self.dedup_mode
+    }
+
+    #[inline]
+    pub fn oversized_terms(&self) -> OversizedTerms {
+        self.oversized_terms
+    }
+
+    /// The date field documents expire by and how long they are kept for.
+    #[inline]
+    pub fn document_ttl(&self) -> Option<(&str, Duration)> {
+        match (self.ttl_field.as_deref(), self.document_ttl) {
+            (Some(field), Some(ttl)) => Some((field, Duration::from_secs(ttl))),
+            _ => None,
+        }
+    }
+
+    /// The order fields are returned in within each search hit.
+    ///
+    /// This is shared with every hit rather than copied per hit.
+    #[inline]
+    pub fn result_field_order(&self) -> Option<&Arc<[String]>> {
+        self.shared_field_order.as_ref()
+    }
+
+    #[inline]
+    pub fn include_nulls(&self) -> bool {
+        self.include_nulls
+    }
+
+    #[inline]
+    pub fn ingest_pipeline(&self) -> &[Processor] {
+        &self.ingest_pipeline
+    }
+
+    #[inline]
+    pub fn queryable_fields(&self) -> Option<&[String]> {
+        self.queryable_fields.as_deref()
+    }
+
+    #[inline]
+    pub fn field_aliases(&self) -> &HashMap<String, String> {
+        &self.field_aliases
+    }
+
+    #[inline]
+    pub fn compound_fields(&self) -> &HashMap<String, Vec<String>> {
+        &self.compound_fields
+    }
+
+    /// Gets the name of the field the given name refers to.
+    ///
+    /// If the name is not an alias it is returned as is.
+    #[inline]
+    pub fn resolve_field<'a>(&'a self, name: &'a str) -> &'a str {
+        self.field_aliases
+            .get(name)
+            .map(String::as_str)
+            .unwrap_or(name)
+    }
+
+    #[inline]
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
     #[inline]
     pub fn required_fields(&self) -> &HashSet<String> {
         &self.required_fields
@@ -176,6 +657,15 @@ impl SchemaContext {
         &self.multi_value_fields
     }
 
+    /// The amount of decimal places the values of each float field with
+    /// an output precision are rounded to.
+    ///
+    /// This is shared with every hit rather than copied per hit.
+    #[inline]
+    pub fn output_precisions(&self) -> &Arc<HashMap<String, u32>> {
+        &self.output_precisions
+    }
+
     /// Checks and asserts that the fields defined by Tantivy are also the same set of fields
     /// defined in the schema.
     ///
@@ -206,6 +696,125 @@ impl SchemaContext {
         }
     }
 
+    /// Validates that the id field is a single value integer or text field.
+    fn verify_id_field(&self, name: &str) -> Result<()> {
+        let info = match self.fields.get(name) {
+            Some(info) => info,
+            None => {
+                return Err(anyhow!(
+                    "key 'id_field' references a field that is not defined in the schema: {}",
+                    name,
+                ))
+            },
+        };
+
+        match info {
+            FieldDeclaration::U64 { .. }
+            | FieldDeclaration::I64 { .. }
+            | FieldDeclaration::Text { .. }
+            | FieldDeclaration::String { .. } => {},
+            _ => {
+                return Err(anyhow!(
+                    "key 'id_field' must reference a u64, i64, text or string field: {}",
+                    name,
+                ))
+            },
+        }
+
+        if info.is_multi() {
+            return Err(anyhow!(
+                "key 'id_field' must reference a single value field: {}",
+                name,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Validates that the dedup field is a single value indexed integer or
+    /// string field.
+    ///
+    /// Text fields are tokenized so their values cannot be matched as a
+    /// whole and are rejected.
+    fn verify_dedup_field(&self, name: &str) -> Result<()> {
+        let info = match self.fields.get(name) {
+            Some(info) => info,
+            None => {
+                return Err(anyhow!(
+                    "key 'dedup_field' references a field that is not defined in the schema: {}",
+                    name,
+                ))
+            },
+        };
+
+        match info {
+            FieldDeclaration::U64 { .. }
+            | FieldDeclaration::I64 { .. }
+            | FieldDeclaration::String { .. } => {},
+            _ => {
+                return Err(anyhow!(
+                    "key 'dedup_field' must reference a u64, i64 or string field: {}",
+                    name,
+                ))
+            },
+        }
+
+        if !info.is_indexed() {
+            return Err(anyhow!(
+                "key 'dedup_field' must reference an indexed field: {}",
+                name,
+            ));
+        }
+
+        if info.is_multi() {
+            return Err(anyhow!(
+                "key 'dedup_field' must reference a single value field: {}",
+                name,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Validates the field documents expire by.
+    ///
+    /// Expired documents are found with a range query over the field so it
+    /// must be indexed.
+    fn verify_ttl_field(&self, name: &str) -> Result<()> {
+        let info = match self.fields.get(name) {
+            Some(info) => info,
+            None => {
+                return Err(anyhow!(
+                    "key 'ttl_field' references a field that is not defined in the schema: {}",
+                    name,
+                ))
+            },
+        };
+
+        if !matches!(info, FieldDeclaration::Date { .. }) {
+            return Err(anyhow!(
+                "key 'ttl_field' must reference a date field: {}",
+                name
+            ));
+        }
+
+        if !info.is_indexed() {
+            return Err(anyhow!(
+                "key 'ttl_field' must reference an indexed field: {}",
+                name,
+            ));
+        }
+
+        if info.is_multi() {
+            return Err(anyhow!(
+                "key 'ttl_field' must reference a single value field: {}",
+                name,
+            ));
+        }
+
+        Ok(())
+    }
+
     /// Validates all search fields so that they're all indexed.
     ///
     /// If the search fields contain any fields that are not indexed,
@@ -225,15 +834,37 @@ impl SchemaContext {
             }
         }
 
-        if reject.is_empty() {
-            Ok(())
-        } else {
-            Err(anyhow!(
+        if !reject.is_empty() {
+            return Err(anyhow!(
                 "the given search fields contain non-indexed fields, \
                  fields cannot be searched without being indexed. Invalid fields: {}",
                 reject.join(", ")
-            ))
+            ));
+        }
+
+        if let Some(ref name) = self.default_search_field {
+            let is_indexed = schema
+                .get_field(name)
+                .map(|field| schema.get_field_entry(field).is_indexed())
+                .unwrap_or(false);
+
+            if !is_indexed {
+                return Err(anyhow!(
+                    "the default search field {:?} is not indexed, \
+                     fields cannot be searched without being indexed.",
+                    name,
+                ));
+            }
         }
+
+        Ok(())
+    }
+
+    /// Gets the field searched by unprefixed query terms if one is set.
+    pub fn get_default_search_field(&self, schema: &Schema) -> Option<Field> {
+        self.default_search_field
+            .as_ref()
+            .and_then(|name| schema.get_field(name))
     }
 
     /// Gets all fields that exist in the schema and are marked as search
@@ -307,7 +938,7 @@ impl SchemaContext {
                 FieldDeclaration::I64 { opts } => {
                     schema.add_i64_field(field, *opts);
                 },
-                FieldDeclaration::F64 { opts } => {
+                FieldDeclaration::F64 { opts, .. } => {
                     schema.add_f64_field(field, *opts);
                 },
                 FieldDeclaration::Date { opts } => {
@@ -317,10 +948,16 @@ impl SchemaContext {
                     schema.add_facet_field(field, *opts);
                 },
                 FieldDeclaration::Text { opts } => {
-                    schema.add_text_field(field, opts.opts_as_text());
+                    schema.add_text_field(
+                        field,
+                        opts.as_text_options(self.default_analyzer),
+                    );
                 },
                 FieldDeclaration::String { opts } => {
-                    schema.add_text_field(field, opts.opts_as_string());
+                    schema.add_text_field(field, opts.as_string_options());
+                },
+                FieldDeclaration::Geo { opts } => {
+                    schema.add_f64_field(field, opts.opts_as_geo());
                 },
             }
         }
@@ -373,16 +1010,26 @@ impl BaseFieldOptions {
         opts
     }
 
-    fn opts_as_text(&self) -> TextOptions {
+    fn opts_as_text(&self, tokenizer: &str) -> TextOptions {
         let raw = self.as_raw_opts();
         raw.set_indexing_options(
             TextFieldIndexing::default()
-                .set_tokenizer("default")
+                .set_tokenizer(tokenizer)
                 .set_fieldnorms(true)
                 .set_index_option(IndexRecordOption::WithFreqsAndPositions),
         )
     }
 
+    fn opts_as_geo(&self) -> IntOptions {
+        let mut opts = IntOptions::default().set_fast(Cardinality::MultiValues);
+
+        if self.stored {
+            opts = opts.set_stored();
+        }
+
+        opts
+    }
+
     fn opts_as_string(&self) -> TextOptions {
         let raw = self.as_raw_opts();
         raw.set_indexing_options(
@@ -406,6 +1053,491 @@ impl From<BaseFieldOptions> for TextOptions {
     }
 }
 
+/// A language supported for stemming text fields.
+///
+/// Each language can also be given as its ISO 639-1 code e.g. `fr`.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StemmingLanguage {
+    #[serde(alias = "da")]
+    Danish,
+    #[serde(alias = "nl")]
+    Dutch,
+    #[serde(alias = "en")]
+    English,
+    #[serde(alias = "fi")]
+    Finnish,
+    #[serde(alias = "fr")]
+    French,
+    #[serde(alias = "de")]
+    German,
+    #[serde(alias = "hu")]
+    Hungarian,
+    #[serde(alias = "it")]
+    Italian,
+    #[serde(alias = "no")]
+    Norwegian,
+    #[serde(alias = "pt")]
+    Portuguese,
+    #[serde(alias = "ro")]
+    Romanian,
+    #[serde(alias = "ru")]
+    Russian,
+    #[serde(alias = "es")]
+    Spanish,
+    #[serde(alias = "sv")]
+    Swedish,
+    #[serde(alias = "tr")]
+    Turkish,
+}
+
+impl StemmingLanguage {
+    /// Every supported stemming language.
+    const ALL: [Self; 15] = [
+        Self::Danish,
+        Self::Dutch,
+        Self::English,
+        Self::Finnish,
+        Self::French,
+        Self::German,
+        Self::Hungarian,
+        Self::Italian,
+        Self::Norwegian,
+        Self::Portuguese,
+        Self::Romanian,
+        Self::Russian,
+        Self::Spanish,
+        Self::Swedish,
+        Self::Turkish,
+    ];
+
+    fn as_tantivy_language(&self) -> Language {
+        match self {
+            Self::Danish => Language::Danish,
+            Self::Dutch => Language::Dutch,
+            Self::English => Language::English,
+            Self::Finnish => Language::Finnish,
+            Self::French => Language::French,
+            Self::German => Language::German,
+            Self::Hungarian => Language::Hungarian,
+            Self::Italian => Language::Italian,
+            Self::Norwegian => Language::Norwegian,
+            Self::Portuguese => Language::Portuguese,
+            Self::Romanian => Language::Romanian,
+            Self::Russian => Language::Russian,
+            Self::Spanish => Language::Spanish,
+            Self::Swedish => Language::Swedish,
+            Self::Turkish => Language::Turkish,
+        }
+    }
+
+    /// The name the language's tokenizer is registered under.
+    fn tokenizer_name(&self) -> String {
+        format!("{}{:?}", STEMMING_TOKENIZER_PREFIX, self).to_lowercase()
+    }
+
+    /// The default tokenizer pipeline with the language's stemmer applied.
+    fn analyzer(&self) -> TextAnalyzer {
+        TextAnalyzer::from(SimpleTokenizer)
+            .filter(RemoveLongFilter::limit(40))
+            .filter(LowerCaser)
+            .filter(Stemmer::new(self.as_tantivy_language()))
+    }
+
+    /// The language's bundled stop words, if there are any.
+    fn as_stop_word_language(&self) -> Option<StopWordLanguage> {
+        match self {
+            Self::English => Some(StopWordLanguage::En),
+            Self::French => Some(StopWordLanguage::Fr),
+            Self::German => Some(StopWordLanguage::De),
+            Self::Italian => Some(StopWordLanguage::It),
+            Self::Russian => Some(StopWordLanguage::Ru),
+            Self::Spanish => Some(StopWordLanguage::Es),
+            _ => None,
+        }
+    }
+
+    /// The name the language's analyzer is registered under.
+    fn language_tokenizer_name(&self) -> String {
+        format!("{}{:?}", LANGUAGE_TOKENIZER_PREFIX, self).to_lowercase()
+    }
+
+    /// The default tokenizer pipeline with the language's stop words
+    /// removed before the language's stemmer is applied.
+    fn language_analyzer(&self) -> Result<TextAnalyzer> {
+        let analyzer = TextAnalyzer::from(SimpleTokenizer)
+            .filter(RemoveLongFilter::limit(40))
+            .filter(LowerCaser);
+
+        let analyzer = match self.as_stop_word_language() {
+            Some(language) => {
+                analyzer.filter(StopWordFilter::remove(default_stop_words(language)?))
+            },
+            None => analyzer,
+        };
+
+        Ok(analyzer.filter(Stemmer::new(self.as_tantivy_language())))
+    }
+}
+
+/// Registers the tokenizers of every stemming language with the index.
+///
+/// This must be done every time the index is opened as the tokenizers are
+/// not persisted alongside the schema.
+pub(crate) fn register_stemming_tokenizers(index: &Index) {
+    for language in StemmingLanguage::ALL {
+        index
+            .tokenizers()
+            .register(&language.tokenizer_name(), language.analyzer());
+    }
+}
+
+/// Registers the analyzers of every language with the index.
+///
+/// Like the stemming tokenizers these must be registered every time the
+/// index is opened.
+pub(crate) fn register_language_tokenizers(index: &Index) -> Result<()> {
+    for language in StemmingLanguage::ALL {
+        index.tokenizers().register(
+            &language.language_tokenizer_name(),
+            language.language_analyzer()?,
+        );
+    }
+
+    Ok(())
+}
+
+/// Replaces the index's default tokenizer with one removing the bundled
+/// stop words of the given languages.
+///
+/// Text fields using the default tokenizer no longer index the stop words,
+/// and queries against them drop the stop words in the same way.
+pub(crate) fn register_stop_word_tokenizer(
+    index: &Index,
+    languages: &[StopWordLanguage],
+) -> Result<()> {
+    if languages.is_empty() {
+        return Ok(());
+    }
+
+    let mut words = vec![];
+    for language in languages {
+        words.extend(default_stop_words(*language)?);
+    }
+
+    let analyzer = TextAnalyzer::from(SimpleTokenizer)
+        .filter(RemoveLongFilter::limit(40))
+        .filter(LowerCaser)
+        .filter(StopWordFilter::remove(words));
+
+    index
+        .tokenizers()
+        .register(TextTokenizer::Default.tokenizer_name(), analyzer);
+
+    Ok(())
+}
+
+/// Truncates tokens to the maximum term length without splitting a
+/// character.
+#[derive(Clone)]
+struct TruncateTermFilter;
+
+impl TokenFilter for TruncateTermFilter {
+    fn transform<'a>(&self, token_stream: BoxTokenStream<'a>) -> BoxTokenStream<'a> {
+        BoxTokenStream::from(TruncateTermStream { tail: token_stream })
+    }
+}
+
+struct TruncateTermStream<'a> {
+    tail: BoxTokenStream<'a>,
+}
+
+impl<'a> TokenStream for TruncateTermStream<'a> {
+    fn advance(&mut self) -> bool {
+        if !self.tail.advance() {
+            return false;
+        }
+
+        let text = &mut self.tail.token_mut().text;
+        if text.len() > MAX_TERM_LEN {
+            let mut len = MAX_TERM_LEN;
+            while !text.is_char_boundary(len) {
+                len -= 1;
+            }
+
+            text.truncate(len);
+        }
+
+        true
+    }
+
+    fn token(&self) -> &Token {
+        self.tail.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.tail.token_mut()
+    }
+}
+
+/// Replaces the index's raw tokenizer with one truncating values too large
+/// to be indexed as a single term.
+///
+/// This lets oversized values be stored in full while only their indexed
+/// term is truncated.
+pub(crate) fn register_raw_tokenizer(index: &Index) {
+    let analyzer = TextAnalyzer::from(RawTokenizer).filter(TruncateTermFilter);
+
+    index
+        .tokenizers()
+        .register(TextTokenizer::Raw.tokenizer_name(), analyzer);
+}
+
+/// Checks if the given tokenizer name belongs to a stemming tokenizer.
+///
+/// The analyzers of fields with a language stem their words so are
+/// included.
+pub(crate) fn is_stemming_tokenizer(name: &str) -> bool {
+    name.starts_with(STEMMING_TOKENIZER_PREFIX)
+        || name.starts_with(LANGUAGE_TOKENIZER_PREFIX)
+}
+
+/// A tokenizer that can be used by text fields.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextTokenizer {
+    /// Splits on whitespace and punctuation and lowercases each word.
+    Default,
+
+    /// Keeps the whole value as a single token.
+    Raw,
+}
+
+impl TextTokenizer {
+    fn tokenizer_name(&self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Raw => "raw",
+        }
+    }
+}
+
+/// How a document is handled when its dedup field value already exists
+/// in the index.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DedupMode {
+    /// The existing documents are deleted and the new document is added.
+    Replace,
+
+    /// The new document is dropped, keeping the existing documents.
+    Skip,
+}
+
+impl Default for DedupMode {
+    fn default() -> Self {
+        Self::Replace
+    }
+}
+
+/// How values which are too large to be indexed as a single term are
+/// handled on ingest.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OversizedTerms {
+    /// The value is stored in full but its indexed term is truncated to the
+    /// maximum term length.
+    Truncate,
+
+    /// The value is left out of the document.
+    Skip,
+
+    /// The document is rejected.
+    Reject,
+}
+
+impl Default for OversizedTerms {
+    fn default() -> Self {
+        Self::Truncate
+    }
+}
+
+/// An analyzer applied to the text fields of an index by default.
+///
+/// Each analyzer maps onto a tokenizer which is registered with the index's
+/// `TokenizerManager` when it is opened.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexAnalyzer {
+    /// The simple analyzer with english stemming applied.
+    En,
+
+    /// Splits on whitespace and punctuation and lowercases each word.
+    Simple,
+
+    /// Keeps the whole value as a single token.
+    Keyword,
+}
+
+impl IndexAnalyzer {
+    fn tokenizer_name(&self) -> String {
+        match self {
+            Self::En => StemmingLanguage::English.tokenizer_name(),
+            Self::Simple => TextTokenizer::Default.tokenizer_name().to_string(),
+            Self::Keyword => TextTokenizer::Raw.tokenizer_name().to_string(),
+        }
+    }
+}
+
+/// The options of a tokenized text field.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct TextFieldOptions {
+    /// Should the field be indexed to be searched?
+    ///
+    /// Defaults to true.
+    #[serde(default = "default_to_true")]
+    indexed: bool,
+
+    /// Is the field a fast field?
+    ///
+    /// Text fields cannot be fast fields, this exists so the option is
+    /// rejected rather than silently ignored.
+    #[serde(default)]
+    fast: bool,
+
+    /// The tokenizer used to split the field into terms.
+    ///
+    /// Defaults to the `default` tokenizer.
+    #[serde(default)]
+    tokenizer: Option<TextTokenizer>,
+
+    /// The language to stem the field's words with.
+    ///
+    /// Queries against the field are stemmed the same way, so a search for
+    /// `running` will match `run`. This cannot be changed once the index
+    /// has been created.
+    #[serde(default)]
+    stemming_language: Option<StemmingLanguage>,
+
+    /// The language of the field's text.
+    ///
+    /// This stems the field's words like `stemming_language` and also
+    /// removes the language's bundled stop words, queries against the field
+    /// are analyzed the same way. This cannot be combined with a tokenizer
+    /// or stemming language.
+    #[serde(default)]
+    language: Option<StemmingLanguage>,
+
+    #[serde(flatten)]
+    base: BaseFieldOptions,
+}
+
+impl Validate for TextFieldOptions {
+    fn validate(&self) -> Result<()> {
+        if self.fast {
+            return Err(Error::msg(
+                "text fields cannot be fast fields, only numeric and date fields can be",
+            ));
+        }
+
+        if !self.indexed
+            && (self.tokenizer.is_some()
+                || self.stemming_language.is_some()
+                || self.language.is_some())
+        {
+            return Err(Error::msg(
+                "a tokenizer, stemming language or language cannot be set on a field that is not indexed",
+            ));
+        }
+
+        if self.language.is_some()
+            && (self.tokenizer.is_some() || self.stemming_language.is_some())
+        {
+            return Err(Error::msg(
+                "a language cannot be combined with a tokenizer or stemming language",
+            ));
+        }
+
+        if self.tokenizer == Some(TextTokenizer::Raw) && self.stemming_language.is_some()
+        {
+            return Err(Error::msg(
+                "a stemming language cannot be used with the raw tokenizer",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl TextFieldOptions {
+    /// Gets the field's tantivy options.
+    ///
+    /// The field's own language, tokenizer or stemming language takes
+    /// priority over the index's default analyzer.
+    fn as_text_options(&self, default_analyzer: Option<IndexAnalyzer>) -> TextOptions {
+        if !self.indexed {
+            return self.base.as_raw_opts();
+        }
+
+        if let Some(language) = self.language {
+            return self.base.opts_as_text(&language.language_tokenizer_name());
+        }
+
+        match (self.stemming_language, self.tokenizer, default_analyzer) {
+            (Some(language), _, _) => self.base.opts_as_text(&language.tokenizer_name()),
+            (None, Some(tokenizer), _) => {
+                self.base.opts_as_text(tokenizer.tokenizer_name())
+            },
+            (None, None, Some(analyzer)) => {
+                self.base.opts_as_text(&analyzer.tokenizer_name())
+            },
+            (None, None, None) => self.base.opts_as_text("default"),
+        }
+    }
+}
+
+/// The options of a string field which is not tokenized.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct StringFieldOptions {
+    /// Should the field be indexed to be searched?
+    ///
+    /// Defaults to true.
+    #[serde(default = "default_to_true")]
+    indexed: bool,
+
+    /// Is the field a fast field?
+    ///
+    /// String fields cannot be fast fields, this exists so the option is
+    /// rejected rather than silently ignored.
+    #[serde(default)]
+    fast: bool,
+
+    #[serde(flatten)]
+    base: BaseFieldOptions,
+}
+
+impl Validate for StringFieldOptions {
+    fn validate(&self) -> Result<()> {
+        if self.fast {
+            return Err(Error::msg(
+                "string fields cannot be fast fields, only numeric and date fields can be",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl StringFieldOptions {
+    fn as_string_options(&self) -> TextOptions {
+        if self.indexed {
+            self.base.opts_as_string()
+        } else {
+            self.base.as_raw_opts()
+        }
+    }
+}
+
 /// A set of field options that takes into account if a field is
 /// multi-value or not in order to determine the fast-field cardinality.
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
@@ -430,6 +1562,18 @@ pub struct CalculatedIntOptions {
     base: BaseFieldOptions,
 }
 
+impl Validate for CalculatedIntOptions {
+    fn validate(&self) -> Result<()> {
+        if self.fieldnorms == Some(true) && !self.indexed {
+            return Err(Error::msg(
+                "fieldnorms can only be enabled on fields that are indexed",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
 impl From<CalculatedIntOptions> for IntOptions {
     fn from(v: CalculatedIntOptions) -> Self {
         let mut opts = IntOptions::default();
@@ -472,6 +1616,14 @@ pub enum FieldDeclaration {
     F64 {
         #[serde(flatten)]
         opts: CalculatedIntOptions,
+
+        /// The amount of decimal places values are rounded to when
+        /// returned in results.
+        ///
+        /// This only changes how values are returned, they are stored and
+        /// indexed at their full precision.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        output_precision: Option<u32>,
     },
 
     /// A u64 field with given options.
@@ -499,7 +1651,7 @@ pub enum FieldDeclaration {
     /// This will be tokenized.
     Text {
         #[serde(flatten)]
-        opts: BaseFieldOptions,
+        opts: TextFieldOptions,
     },
 
     /// A string field with given options.
@@ -507,7 +1659,7 @@ pub enum FieldDeclaration {
     /// This wont be tokenized.
     String {
         #[serde(flatten)]
-        opts: BaseFieldOptions,
+        opts: StringFieldOptions,
     },
 
     /// A facet field.
@@ -517,45 +1669,103 @@ pub enum FieldDeclaration {
         #[serde(flatten)]
         opts: BaseFieldOptions,
     },
+
+    /// A geo point field.
+    ///
+    /// This is given and returned as a `[lat, lon]` pair and is stored as
+    /// a fast field so results can be filtered by their distance to a point.
+    /// Only a single point can be given per document.
+    Geo {
+        #[serde(flatten)]
+        opts: BaseFieldOptions,
+    },
 }
 
 impl FieldDeclaration {
     #[inline]
     pub fn is_required(&self) -> bool {
         match self {
-            FieldDeclaration::F64 { opts } => opts.base.required,
+            FieldDeclaration::F64 { opts, .. } => opts.base.required,
             FieldDeclaration::U64 { opts } => opts.base.required,
             FieldDeclaration::I64 { opts } => opts.base.required,
             FieldDeclaration::Date { opts } => opts.base.required,
-            FieldDeclaration::Text { opts } => opts.required,
-            FieldDeclaration::String { opts } => opts.required,
+            FieldDeclaration::Text { opts } => opts.base.required,
+            FieldDeclaration::String { opts } => opts.base.required,
             FieldDeclaration::Facet { opts } => opts.required,
+            FieldDeclaration::Geo { opts } => opts.required,
         }
     }
 
     #[inline]
     pub fn is_multi(&self) -> bool {
         match self {
-            FieldDeclaration::F64 { opts } => opts.base.multi,
+            FieldDeclaration::F64 { opts, .. } => opts.base.multi,
             FieldDeclaration::U64 { opts } => opts.base.multi,
             FieldDeclaration::I64 { opts } => opts.base.multi,
             FieldDeclaration::Date { opts } => opts.base.multi,
-            FieldDeclaration::Text { opts } => opts.multi,
-            FieldDeclaration::String { opts } => opts.multi,
+            FieldDeclaration::Text { opts } => opts.base.multi,
+            FieldDeclaration::String { opts } => opts.base.multi,
             FieldDeclaration::Facet { opts } => opts.multi,
+            // The point is stored as a `[lat, lon]` pair of values.
+            FieldDeclaration::Geo { .. } => true,
+        }
+    }
+
+    /// The name of the field's type as given in the declaration.
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            FieldDeclaration::F64 { .. } => "f64",
+            FieldDeclaration::U64 { .. } => "u64",
+            FieldDeclaration::I64 { .. } => "i64",
+            FieldDeclaration::Date { .. } => "date",
+            FieldDeclaration::Text { .. } => "text",
+            FieldDeclaration::String { .. } => "string",
+            FieldDeclaration::Facet { .. } => "facet",
+            FieldDeclaration::Geo { .. } => "geo",
         }
     }
 
     #[inline]
     pub fn is_indexed(&self) -> bool {
         match self {
-            FieldDeclaration::F64 { opts } => opts.indexed,
+            FieldDeclaration::F64 { opts, .. } => opts.indexed,
             FieldDeclaration::U64 { opts } => opts.indexed,
             FieldDeclaration::I64 { opts } => opts.indexed,
             FieldDeclaration::Date { opts } => opts.indexed,
-            FieldDeclaration::Text { .. } => true,
-            FieldDeclaration::String { .. } => true,
+            FieldDeclaration::Text { opts } => opts.indexed,
+            FieldDeclaration::String { opts } => opts.indexed,
             FieldDeclaration::Facet { .. } => true,
+            FieldDeclaration::Geo { .. } => false,
+        }
+    }
+}
+
+impl Validate for FieldDeclaration {
+    fn validate(&self) -> Result<()> {
+        match self {
+            FieldDeclaration::F64 {
+                opts,
+                output_precision,
+            } => {
+                if let Some(precision) = output_precision {
+                    if *precision > MAX_OUTPUT_PRECISION {
+                        return Err(anyhow!(
+                            "output precision cannot be more than {} decimal places",
+                            MAX_OUTPUT_PRECISION
+                        ));
+                    }
+                }
+
+                opts.validate()
+            },
+            FieldDeclaration::U64 { opts } => opts.validate(),
+            FieldDeclaration::I64 { opts } => opts.validate(),
+            FieldDeclaration::Date { opts } => opts.validate(),
+            FieldDeclaration::Text { opts } => opts.validate(),
+            FieldDeclaration::String { opts } => opts.validate(),
+            FieldDeclaration::Facet { .. } => Ok(()),
+            FieldDeclaration::Geo { .. } => Ok(()),
         }
     }
 }
@@ -0,0 +1,281 @@
+use core::fmt;
+use std::collections::BTreeMap;
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer};
+use tantivy::query::{EmptyScorer, Explanation, Query, Scorer, Weight};
+use tantivy::{
+    DocId,
+    DocSet,
+    Score,
+    Searcher,
+    SegmentReader,
+    TantivyError,
+    Term,
+    TERMINATED,
+};
+
+/// The minimum amount of optional clauses a document must match.
+///
+/// This is either an absolute count e.g. `2` or a percentage of the
+/// clauses rounded down e.g. `"75%"`.
+#[derive(Debug, Copy, Clone)]
+pub enum MinimumShouldMatch {
+    Count(usize),
+    Percent(u8),
+}
+
+impl MinimumShouldMatch {
+    /// Gets the amount of the given clauses which must match.
+    pub(crate) fn resolve(&self, num_clauses: usize) -> usize {
+        match self {
+            Self::Count(count) => (*count).min(num_clauses),
+            Self::Percent(percent) => num_clauses * *percent as usize / 100,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MinimumShouldMatch {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct MinimumShouldMatchVisitor;
+
+        impl<'de> Visitor<'de> for MinimumShouldMatchVisitor {
+            type Value = MinimumShouldMatch;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("a positive integer or a percentage e.g. \"75%\"")
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(MinimumShouldMatch::Count(v as usize))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                let percent = v
+                    .strip_suffix('%')
+                    .and_then(|percent| percent.trim().parse::<u8>().ok())
+                    .filter(|percent| *percent <= 100)
+                    .ok_or_else(|| {
+                        E::custom(format!(
+                            "invalid minimum should match percentage {:?}, \
+                            expected a value between 0% and 100%",
+                            v
+                        ))
+                    })?;
+
+                Ok(MinimumShouldMatch::Percent(percent))
+            }
+        }
+
+        deserializer.deserialize_any(MinimumShouldMatchVisitor)
+    }
+}
+
+/// A query matching documents which match at least `minimum` of the
+/// clauses, scored by the sum of the matching clauses' scores.
+pub(crate) struct MinShouldMatchQuery {
+    clauses: Vec<Box<dyn Query>>,
+    minimum: usize,
+}
+
+impl MinShouldMatchQuery {
+    pub(crate) fn new(clauses: Vec<Box<dyn Query>>, minimum: usize) -> Self {
+        Self { clauses, minimum }
+    }
+}
+
+impl Clone for MinShouldMatchQuery {
+    fn clone(&self) -> Self {
+        Self {
+            clauses: self
+                .clauses
+                .iter()
+                .map(|clause| clause.box_clone())
+                .collect(),
+            minimum: self.minimum,
+        }
+    }
+}
+
+impl fmt::Debug for MinShouldMatchQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MinShouldMatchQuery")
+            .field("clauses", &self.clauses)
+            .field("minimum", &self.minimum)
+            .finish()
+    }
+}
+
+impl Query for MinShouldMatchQuery {
+    fn weight(
+        &self,
+        searcher: &Searcher,
+        scoring_enabled: bool,
+    ) -> tantivy::Result<Box<dyn Weight>> {
+        let weights = self
+            .clauses
+            .iter()
+            .map(|clause| clause.weight(searcher, scoring_enabled))
+            .collect::<tantivy::Result<Vec<_>>>()?;
+
+        Ok(Box::new(MinShouldMatchWeight {
+            weights,
+            minimum: self.minimum,
+        }))
+    }
+
+    fn query_terms(&self, terms: &mut BTreeMap<Term, bool>) {
+        for clause in self.clauses.iter() {
+            clause.query_terms(terms);
+        }
+    }
+}
+
+struct MinShouldMatchWeight {
+    weights: Vec<Box<dyn Weight>>,
+    minimum: usize,
+}
+
+impl Weight for MinShouldMatchWeight {
+    fn scorer(
+        &self,
+        reader: &SegmentReader,
+        boost: Score,
+    ) -> tantivy::Result<Box<dyn Scorer>> {
+        let scorers = self
+            .weights
+            .iter()
+            .map(|weight| weight.scorer(reader, boost))
+            .collect::<tantivy::Result<Vec<_>>>()?;
+
+        if scorers.len() < self.minimum {
+            return Ok(Box::new(EmptyScorer));
+        }
+
+        Ok(Box::new(MinShouldMatchScorer::new(scorers, self.minimum)))
+    }
+
+    fn explain(
+        &self,
+        reader: &SegmentReader,
+        doc: DocId,
+    ) -> tantivy::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Document #({}) does not match",
+                doc
+            )));
+        }
+
+        Ok(Explanation::new("MinShouldMatch", scorer.score()))
+    }
+}
+
+/// Iterates over the documents matched by at least `minimum` of the
+/// scorers.
+///
+/// Each scorer is kept positioned after the current document.
+struct MinShouldMatchScorer {
+    scorers: Vec<Box<dyn Scorer>>,
+    minimum: usize,
+    doc: DocId,
+    score: Score,
+}
+
+impl MinShouldMatchScorer {
+    fn new(scorers: Vec<Box<dyn Scorer>>, minimum: usize) -> Self {
+        let mut scorer = Self {
+            scorers,
+            minimum: minimum.max(1),
+            doc: 0,
+            score: 0.0,
+        };
+        scorer.find_next();
+        scorer
+    }
+
+    /// Moves to the next document matched by enough scorers.
+    fn find_next(&mut self) -> DocId {
+        loop {
+            let doc = self
+                .scorers
+                .iter()
+                .map(|scorer| scorer.doc())
+                .min()
+                .unwrap_or(TERMINATED);
+
+            if doc == TERMINATED {
+                self.doc = TERMINATED;
+                return TERMINATED;
+            }
+
+            let mut matched = 0;
+            let mut score = 0.0;
+            for scorer in self.scorers.iter_mut() {
+                if scorer.doc() == doc {
+                    matched += 1;
+                    score += scorer.score();
+                    scorer.advance();
+                }
+            }
+
+            if matched >= self.minimum {
+                self.doc = doc;
+                self.score = score;
+                return doc;
+            }
+        }
+    }
+}
+
+impl DocSet for MinShouldMatchScorer {
+    fn advance(&mut self) -> DocId {
+        if self.doc == TERMINATED {
+            return TERMINATED;
+        }
+
+        self.find_next()
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        if self.doc == TERMINATED || self.doc >= target {
+            return self.doc;
+        }
+
+        for scorer in self.scorers.iter_mut() {
+            if scorer.doc() < target {
+                scorer.seek(target);
+            }
+        }
+
+        self.find_next()
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.scorers
+            .iter()
+            .map(|scorer| scorer.size_hint())
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+impl Scorer for MinShouldMatchScorer {
+    fn score(&mut self) -> Score {
+        self.score
+    }
+}
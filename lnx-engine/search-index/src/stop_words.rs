@@ -6,40 +6,95 @@ use anyhow::{Error, Result};
 use arc_swap::ArcSwap;
 use bincode::Options;
 use flate2::write::GzDecoder;
+use hashbrown::{HashMap, HashSet};
 use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
 
 use crate::storage::StorageBackend;
 
-static DEFAULT_WORDS: OnceCell<Vec<String>> = OnceCell::new();
+static DEFAULT_WORDS: OnceCell<HashMap<StopWordLanguage, Vec<String>>> = OnceCell::new();
+
+/// A language with a bundled list of default stop words.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StopWordLanguage {
+    De,
+    En,
+    Es,
+    Fr,
+    He,
+    It,
+    Ru,
+    Zh,
+}
+
+impl StopWordLanguage {
+    /// Every language with a bundled stop word list.
+    pub const ALL: [Self; 8] = [
+        Self::De,
+        Self::En,
+        Self::Es,
+        Self::Fr,
+        Self::He,
+        Self::It,
+        Self::Ru,
+        Self::Zh,
+    ];
+
+    /// The compressed stop words bundled for the language.
+    fn compressed_words(&self) -> &'static [u8] {
+        match self {
+            Self::De => include_bytes!("../_dist/stop_word_lists/de"),
+            Self::En => include_bytes!("../_dist/stop_word_lists/en"),
+            Self::Es => include_bytes!("../_dist/stop_word_lists/es"),
+            Self::Fr => include_bytes!("../_dist/stop_word_lists/fr"),
+            Self::He => include_bytes!("../_dist/stop_word_lists/he"),
+            Self::It => include_bytes!("../_dist/stop_word_lists/it"),
+            Self::Ru => include_bytes!("../_dist/stop_word_lists/ru"),
+            Self::Zh => include_bytes!("../_dist/stop_word_lists/zh"),
+        }
+    }
+}
+
+/// Extracts the stop words from a bundled compressed stop words binary.
+///
+/// The words are split on a line by line basis.
+fn decompress_words(buffer: &[u8]) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    if buffer.is_empty() {
+        return Ok(words);
+    }
+
+    let mut data = GzDecoder::new(vec![]);
+    data.write_all(buffer)?;
+    let data = data.finish()?;
+
+    let raw = String::from_utf8(data)
+        .map_err(|_| Error::msg("failed to parse stop words from linked data."))?;
+
+    for word in raw.to_lowercase().split('\n') {
+        if let Some(word) = word.strip_suffix('\r') {
+            words.push(word.to_string());
+        } else {
+            words.push(word.to_string());
+        }
+    }
+
+    Ok(words)
+}
 
 /// Ensures the default words are initialised.
 ///
 /// If the words are already set then this is a no op.
-///
-/// The words are extracted from the bundled compressed stop_words binary
-/// and split on a line by line basis.
 fn init_default_words() -> Result<()> {
     if DEFAULT_WORDS.get().is_some() {
         return Ok(());
     }
 
-    let mut default_words = Vec::new();
-    let buffer: &[u8] = include_bytes!("../_dist/stop_words");
-    if !buffer.is_empty() {
-        let mut data = GzDecoder::new(vec![]);
-        data.write_all(buffer)?;
-        let data = data.finish()?;
-
-        let words = String::from_utf8(data)
-            .map_err(|_| Error::msg("failed to parse stop words from linked data."))?;
-
-        for word in words.to_lowercase().split('\n') {
-            if let Some(word) = word.strip_suffix('\r') {
-                default_words.push(word.to_string());
-            } else {
-                default_words.push(word.to_string());
-            }
-        }
+    let mut default_words = HashMap::with_capacity(StopWordLanguage::ALL.len());
+    for language in StopWordLanguage::ALL {
+        let words = decompress_words(language.compressed_words())?;
+        default_words.insert(language, words);
     }
 
     let _ = DEFAULT_WORDS.set(default_words);
@@ -47,29 +102,91 @@ fn init_default_words() -> Result<()> {
     Ok(())
 }
 
+/// Gets the bundled stop words of the given language.
+pub(crate) fn default_stop_words(language: StopWordLanguage) -> Result<Vec<String>> {
+    init_default_words()?;
+
+    let all_words = DEFAULT_WORDS.get().expect("get defaults");
+    Ok(all_words.get(&language).cloned().unwrap_or_default())
+}
+
 /// The structure in charge of controlling the stop words.
 #[derive(Clone)]
 pub struct StopWordManager {
     index_stop_words: Arc<ArcSwap<Vec<String>>>,
+
+    /// The default stop words used when the index has no custom stop words.
+    default_words: Arc<Vec<String>>,
+
+    /// A lookup of the default stop words stripped from queries.
+    ///
+    /// This is empty unless the index selected it's stop word languages.
+    default_lookup: Arc<HashSet<String>>,
 }
 
 impl StopWordManager {
-    /// Creates a new `StopWordManager`.
+    /// Creates a new `StopWordManager` using the stop words of every
+    /// bundled language as the defaults.
+    ///
+    /// The defaults are only listed, queries only have the index's custom
+    /// stop words stripped.
     pub(crate) fn init() -> Result<Self> {
-        // Ensure the default words are set.
-        init_default_words()?;
+        let default_words = Self::bundled_words(&StopWordLanguage::ALL)?;
 
         Ok(Self {
             index_stop_words: Arc::new(ArcSwap::from_pointee(vec![])),
+            default_words: Arc::new(default_words),
+            default_lookup: Arc::new(HashSet::new()),
         })
     }
 
+    /// Creates a new `StopWordManager` using the stop words of the given
+    /// languages as the defaults.
+    pub(crate) fn init_with_languages(languages: &[StopWordLanguage]) -> Result<Self> {
+        let default_words = Self::bundled_words(languages)?;
+        let default_lookup = default_words.iter().cloned().collect();
+
+        Ok(Self {
+            index_stop_words: Arc::new(ArcSwap::from_pointee(vec![])),
+            default_words: Arc::new(default_words),
+            default_lookup: Arc::new(default_lookup),
+        })
+    }
+
+    fn bundled_words(languages: &[StopWordLanguage]) -> Result<Vec<String>> {
+        // Ensure the default words are set.
+        init_default_words()?;
+
+        let all_words = DEFAULT_WORDS.get().expect("get defaults");
+
+        let mut default_words = vec![];
+        for language in languages {
+            if let Some(words) = all_words.get(language) {
+                default_words.extend_from_slice(words);
+            }
+        }
+
+        Ok(default_words)
+    }
+
     /// Checks if the given word is in the list of stop words.
     #[inline]
     pub fn is_stop_word(&self, word: &str) -> bool {
         self.index_stop_words.load().iter().any(|v| v == word)
     }
 
+    /// Checks if the given word is in the list of stop words, falling back to
+    /// the stop words of the index's languages if the index has no custom
+    /// stop words.
+    pub fn is_stop_word_or_default(&self, word: &str) -> bool {
+        let words = self.index_stop_words.load();
+        if words.len() == 0 {
+            self.default_lookup.contains(word)
+        } else {
+            words.iter().any(|v| v == word)
+        }
+    }
+
     /// Gets all the stop words for the given index.
     ///
     /// If the index has no specific custom stop words
@@ -77,12 +194,18 @@ impl StopWordManager {
     pub fn get_stop_words(&self) -> Vec<String> {
         let words = self.index_stop_words.load();
         if words.len() == 0 {
-            DEFAULT_WORDS.get().expect("get defaults").to_vec()
+            self.default_words.as_ref().clone()
         } else {
             words.as_ref().to_vec()
         }
     }
 
+    /// Gets the index's custom stop words, without falling back to the
+    /// default stop words.
+    pub fn get_index_stop_words(&self) -> Vec<String> {
+        self.index_stop_words.load().as_ref().to_vec()
+    }
+
     /// Adds a list of stop words to the given index's sector.
     ///
     /// If the index already has specific words added, the words are appended.
@@ -116,6 +239,21 @@ impl StopWordManager {
         self.index_stop_words.store(Arc::new(new_words));
     }
 
+    /// Replaces the index's custom stop words with the given set.
+    ///
+    /// The new set is swapped in at once so queries never see a partially
+    /// replaced set.
+    pub fn replace_stop_words(&self, words: Vec<String>) {
+        let mut new_words: Vec<String> = Vec::with_capacity(words.len());
+        for word in words.into_iter().map(|v| v.to_lowercase()) {
+            if !new_words.contains(&word) {
+                new_words.push(word);
+            }
+        }
+
+        self.index_stop_words.store(Arc::new(new_words));
+    }
+
     /// Clears all custom stop words from all indexes.
     pub fn clear_stop_words(&self) {
         self.index_stop_words.store(Arc::new(vec![]))
@@ -172,6 +310,11 @@ impl PersistentStopWordManager {
         self.manager.remove_stop_words(words)
     }
 
+    /// Replaces the index's custom stop words with the given set.
+    pub fn replace_stop_words(&self, words: Vec<String>) {
+        self.manager.replace_stop_words(words)
+    }
+
     /// Clears all custom stop words from all indexes.
     pub fn clear_stop_words(&self) {
         self.manager.clear_stop_words()
@@ -281,4 +424,28 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_stop_word_languages() -> Result<()> {
+        let manager = StopWordManager::init_with_languages(&[StopWordLanguage::En])?;
+
+        assert!(manager.is_stop_word_or_default("the"));
+        assert!(!manager.is_stop_word_or_default("aber"));
+        assert!(manager.get_stop_words().contains(&String::from("the")));
+
+        manager.add_stop_words(vec!["aber".into()]);
+
+        assert!(manager.is_stop_word_or_default("aber"));
+        assert!(!manager.is_stop_word_or_default("the"));
+
+        let manager = StopWordManager::init_with_languages(&[])?;
+        assert!(manager.get_stop_words().is_empty());
+        assert!(!manager.is_stop_word_or_default("the"));
+
+        // The bundled stop words are only stripped once opted into.
+        let manager = StopWordManager::init()?;
+        assert!(!manager.is_stop_word_or_default("the"));
+
+        Ok(())
+    }
 }
@@ -16,6 +16,7 @@ use tantivy::directory::{
 use tantivy::Directory;
 
 use crate::helpers::cr32_hash;
+use crate::structures::Durability;
 
 static WATCHED_MANAGED_FILE: &str = ".managed.json";
 static WATCHED_META_FILE: &str = "meta.json";
@@ -54,6 +55,7 @@ impl OpenType {
 pub struct SledBackedDirectory {
     inner: MmapDirectory,
     conn: sled::Db,
+    durability: Durability,
 }
 
 impl SledBackedDirectory {
@@ -64,7 +66,13 @@ impl SledBackedDirectory {
     ///
     /// If OpenType::TempFile is set the system will create a temporary structure,
     /// normally for testing.
-    pub fn new_with_root(path: &OpenType) -> anyhow::Result<Self> {
+    ///
+    /// With a relaxed durability the metastore is flushed in the background
+    /// rather than on every write and directory syncs are skipped.
+    pub fn new_with_root(
+        path: &OpenType,
+        durability: Durability,
+    ) -> anyhow::Result<Self> {
         let (conn, inner) = match path {
             OpenType::Dir(path) => {
                 std::fs::create_dir_all(path)?;
@@ -89,7 +97,11 @@ impl SledBackedDirectory {
             ),
         };
 
-        Ok(Self { inner, conn })
+        Ok(Self {
+            inner,
+            conn,
+            durability,
+        })
     }
 }
 
@@ -181,12 +193,21 @@ impl Directory for SledBackedDirectory {
         debug!("using sled backed atomic write");
         let id = cr32_hash(path).to_string();
         self.conn.insert(id, data)?;
-        self.conn.flush()?;
+
+        // Sled flushes periodically in the background so relaxed writes
+        // are persisted shortly after rather than before returning.
+        if self.durability == Durability::Full {
+            self.conn.flush()?;
+        }
 
         Ok(())
     }
 
     fn sync_directory(&self) -> std::io::Result<()> {
+        if self.durability == Durability::Relaxed {
+            return Ok(());
+        }
+
         self.inner.sync_directory()
     }
 
@@ -1,16 +1,17 @@
 use std::collections::BTreeMap;
 use std::convert::TryInto;
 use std::fmt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
 
 use anyhow::{anyhow, Context, Error, Result};
 use chrono::{NaiveDateTime, Utc};
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
 use serde::de::{MapAccess, SeqAccess, Visitor};
-use serde::{Deserialize, Deserializer, Serialize};
+use serde::ser::{SerializeMap, SerializeStruct};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use tantivy::fastfield::FastValue;
 use tantivy::schema::{
     Facet,
@@ -24,15 +25,26 @@ use tantivy::schema::{
 use tantivy::{DateTime, Document as InternalDocument, Index, Score};
 
 use crate::corrections::{SymSpellCorrectionManager, SymSpellManager};
-use crate::helpers::{cr32_hash, Calculated, Validate};
+use crate::helpers::{cr32_hash, id_hash, Calculated, Validate};
+use crate::pipeline::{concat_fields, Processor};
 use crate::query::QueryContext;
-use crate::reader::ReaderContext;
-use crate::schema::{SchemaContext, PRIMARY_KEY};
-use crate::stop_words::StopWordManager;
+use crate::reader::{ReaderContext, SegmentDiagnostics};
+use crate::schema::{
+    register_language_tokenizers,
+    register_raw_tokenizer,
+    register_stemming_tokenizers,
+    register_stop_word_tokenizer,
+    FieldDeclaration,
+    OversizedTerms,
+    SchemaContext,
+    MAX_TERM_LEN,
+    PRIMARY_KEY,
+};
+use crate::stop_words::{StopWordLanguage, StopWordManager};
 use crate::storage::{OpenType, SledBackedDirectory, StorageBackend};
 use crate::synonyms::SynonymsManager;
 use crate::writer::WriterContext;
-use crate::DocumentId;
+use crate::{geo, DocumentId};
 
 pub static ROOT_PATH: &str = "./index";
 pub static INDEX_STORAGE_SUB_PATH: &str = "index-storage";
@@ -51,6 +63,47 @@ pub enum StorageType {
     FileSystem,
 }
 
+impl FromStr for StorageType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "memory" => Ok(Self::Memory),
+            "tempdir" => Ok(Self::TempDir),
+            "filesystem" => Ok(Self::FileSystem),
+            other => Err(anyhow!(
+                "unknown storage type {:?}, expected one of: memory, tempdir, filesystem",
+                other
+            )),
+        }
+    }
+}
+
+/// How durable each write to the index's storage is.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Durability {
+    /// Every write to the metastore is flushed and the directory is synced
+    /// to disk before it completes.
+    Full,
+
+    /// Metastore writes are buffered and flushed periodically in the
+    /// background and syncing the directory is left to the OS.
+    ///
+    /// This gives a higher throughput at the cost of durability, if the
+    /// process or host crashes the most recent commits and any metadata
+    /// changes, e.g. stop words and synonyms, made shortly before may be
+    /// lost and the index will reopen at an earlier commit.
+    /// This should only be used for indexes which can be rebuilt.
+    Relaxed,
+}
+
+impl Default for Durability {
+    fn default() -> Self {
+        Self::Full
+    }
+}
+
 fn add_boost_fields(
     schema: &Schema,
     boost_fields: &HashMap<String, Score>,
@@ -69,6 +122,33 @@ fn add_boost_fields(
     }
 }
 
+/// Gets the default and fuzzy search fields along with their boost factor.
+pub(crate) fn get_boosted_search_fields(
+    schema_ctx: &SchemaContext,
+    schema: &Schema,
+) -> (Vec<(Field, Score)>, Vec<(Field, Score)>) {
+    let default_fields = schema_ctx.get_search_fields(schema);
+    let fuzzy_fields = schema_ctx.get_fuzzy_search_fields(schema);
+
+    let mut default_fields_with_boost = Vec::with_capacity(default_fields.len());
+    add_boost_fields(
+        schema,
+        schema_ctx.boost_fields(),
+        &default_fields,
+        &mut default_fields_with_boost,
+    );
+
+    let mut fuzzy_fields_with_boost = Vec::with_capacity(fuzzy_fields.len());
+    add_boost_fields(
+        schema,
+        schema_ctx.boost_fields(),
+        &fuzzy_fields,
+        &mut fuzzy_fields_with_boost,
+    );
+
+    (default_fields_with_boost, fuzzy_fields_with_boost)
+}
+
 /// A given index declaration that describes the behaviour of a new index.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct IndexDeclaration {
@@ -76,7 +156,47 @@ pub struct IndexDeclaration {
     pub(crate) name: String,
 
     /// The storage type used to store index data.
-    pub(crate) storage_type: StorageType,
+    ///
+    /// Defaults to the engine's default storage type if one is set.
+    #[serde(default)]
+    pub(crate) storage_type: Option<StorageType>,
+
+    /// The directory `filesystem` indexes are stored within.
+    ///
+    /// Defaults to the engine's default storage path if one is set,
+    /// otherwise `./index/index-storage`.
+    #[serde(default)]
+    pub(crate) storage_path: Option<String>,
+
+    /// The name the index's storage directory is derived from.
+    ///
+    /// Defaults to the index's name, this is set when an index is renamed
+    /// so the index keeps using it's existing data rather than migrating it.
+    #[serde(default)]
+    pub(crate) storage_name: Option<String>,
+
+    /// The tenant the index belongs to.
+    ///
+    /// The data of `filesystem` indexes with a tenant is stored within a
+    /// directory named after the tenant, so all of a tenant's data can be
+    /// removed at once. This can only contain alphanumeric characters,
+    /// `-` and `_`.
+    #[serde(default)]
+    pub(crate) tenant_id: Option<String>,
+
+    /// If the server should log each search of the index.
+    ///
+    /// This overrides the server's `--silent-search` setting for this index,
+    /// if not set the server's setting is used.
+    #[serde(default)]
+    pub(crate) log_searches: Option<bool>,
+
+    /// How durable each write to the index's storage is.
+    ///
+    /// Defaults to `full`, see `Durability::Relaxed` for the data loss
+    /// the relaxed mode can cause.
+    #[serde(default)]
+    pub(crate) durability: Durability,
 
     #[serde(flatten)]
     schema_ctx: SchemaContext,
@@ -99,11 +219,49 @@ pub struct IndexDeclaration {
     #[serde(default)]
     pub(crate) use_fast_fuzzy: bool,
 
+    /// Whether fuzzy queries fall back to the standard fuzzy system while
+    /// the fast fuzzy frequency dictionary is empty.
+    ///
+    /// Otherwise fast fuzzy queries only match the exact words of the query
+    /// until the dictionary has been generated.
+    #[serde(default = "IndexDeclaration::default_fast_fuzzy_fallback")]
+    pub(crate) fast_fuzzy_fallback: bool,
+
     /// Whether or not to strip out stop words in fuzzy queries.
     ///
     /// This only applies to the fast-fuzzy query system.
     #[serde(default)]
     pub(crate) strip_stop_words: bool,
+
+    /// Whether every word of a fuzzy query must match.
+    ///
+    /// Each word must be found in at least one of the fuzzy search fields,
+    /// by default documents matching any of the words are returned.
+    #[serde(default)]
+    pub(crate) fuzzy_require_all_terms: bool,
+
+    /// The languages whose bundled stop words are used by the index.
+    ///
+    /// The stop words are removed by the default tokenizer of text fields,
+    /// and are stripped from fast-fuzzy queries when the index has no custom
+    /// stop words. If not set only custom stop words are stripped.
+    #[serde(default)]
+    pub(crate) stop_word_languages: Option<Vec<StopWordLanguage>>,
+
+    /// The maximum amount of term queries a fuzzy query can expand into.
+    ///
+    /// Each word of a fuzzy query is searched for in every fuzzy search field,
+    /// queries exceeding this limit are rejected.
+    #[serde(default = "IndexDeclaration::default_max_fuzzy_terms")]
+    pub(crate) max_fuzzy_terms: usize,
+
+    /// If fields of added documents which are not in the schema should be
+    /// inferred and added to the schema.
+    ///
+    /// This is set on indexes created from their first documents, adding
+    /// new fields re-creates the index copying over all of it's documents.
+    #[serde(default)]
+    pub(crate) infer_new_fields: bool,
 }
 
 impl Validate for IndexDeclaration {
@@ -112,16 +270,181 @@ impl Validate for IndexDeclaration {
         self.reader_ctx.validate()?;
         self.schema_ctx.validate()?;
 
+        if self.max_fuzzy_terms == 0 {
+            return Err(Error::msg(
+                "max fuzzy terms must be at least 1 for fuzzy queries to be possible.",
+            ));
+        }
+
+        if let Some(ref tenant_id) = self.tenant_id {
+            let is_valid = !tenant_id.is_empty()
+                && tenant_id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+            if !is_valid {
+                return Err(anyhow!(
+                    "tenant id {:?} must be non-empty and only contain alphanumeric characters, '-' or '_'.",
+                    tenant_id,
+                ));
+            }
+        }
+
         Ok(())
     }
 }
 
 impl IndexDeclaration {
+    fn default_max_fuzzy_terms() -> usize {
+        1024
+    }
+
+    fn default_fast_fuzzy_fallback() -> bool {
+        true
+    }
+
     #[inline]
     pub fn name(&self) -> &str {
         self.name.as_str()
     }
 
+    #[inline]
+    pub fn infer_new_fields(&self) -> bool {
+        self.infer_new_fields
+    }
+
+    #[inline]
+    pub fn tenant_id(&self) -> Option<&str> {
+        self.tenant_id.as_deref()
+    }
+
+    #[inline]
+    pub fn log_searches(&self) -> Option<bool> {
+        self.log_searches
+    }
+
+    /// Creates a copy of the declaration with a new name.
+    ///
+    /// The copy keeps the original's storage name so it opens the same
+    /// persisted data.
+    pub fn renamed(&self, name: &str) -> Self {
+        let mut declaration = self.clone();
+        declaration
+            .storage_name
+            .get_or_insert_with(|| self.name.clone());
+        declaration.name = name.to_string();
+
+        declaration
+    }
+
+    /// Creates a copy of the declaration with a new name.
+    ///
+    /// Unlike `renamed` the copy's storage is derived from it's new name,
+    /// so it does not open the original's persisted data.
+    pub fn with_name(&self, name: &str) -> Self {
+        let mut declaration = self.clone();
+        declaration.storage_name = None;
+        declaration.name = name.to_string();
+
+        declaration
+    }
+
+    /// Checks if the declaration's data is stored in the same directory as
+    /// the other declaration's.
+    ///
+    /// This happens when an index is created under the name another index
+    /// had before it was renamed. Only `filesystem` indexes can share storage.
+    pub fn shares_storage_with(&self, other: &IndexDeclaration) -> bool {
+        match (self.storage_dir(), other.storage_dir()) {
+            (Ok(Some(dir)), Ok(Some(other_dir))) => dir == other_dir,
+            _ => false,
+        }
+    }
+
+    /// The boost factor of each boosted field.
+    pub fn boost_fields(&self) -> &HashMap<String, Score> {
+        self.schema_ctx.boost_fields()
+    }
+
+    /// Creates a copy of the declaration with a new set of field boosts.
+    ///
+    /// Only text and string fields can be boosted.
+    pub fn with_boost_fields(
+        &self,
+        boost_fields: HashMap<String, Score>,
+    ) -> Result<Self> {
+        let mut declaration = self.clone();
+        declaration.schema_ctx.set_boost_fields(boost_fields)?;
+
+        Ok(declaration)
+    }
+
+    /// Fills in any storage settings the declaration leaves unspecified
+    /// with the given defaults.
+    ///
+    /// The storage path of `filesystem` indexes is always filled in so the
+    /// index is not moved if the default path later changes.
+    pub fn resolve_storage(
+        &mut self,
+        default_type: Option<StorageType>,
+        default_path: Option<&str>,
+    ) {
+        if self.storage_type.is_none() {
+            self.storage_type = default_type;
+        }
+
+        if self.storage_path.is_none()
+            && matches!(self.storage_type, Some(StorageType::FileSystem))
+        {
+            let path = match default_path {
+                Some(path) => path.to_string(),
+                None => format!("{}/{}", ROOT_PATH, INDEX_STORAGE_SUB_PATH),
+            };
+
+            self.storage_path = Some(path);
+        }
+    }
+
+    /// Gets the directory the index's data is stored in.
+    ///
+    /// This is only `Some` for `filesystem` indexes.
+    fn storage_dir(&self) -> Result<Option<PathBuf>> {
+        let storage_type = self.storage_type.ok_or_else(|| {
+            Error::msg(
+                "no storage type was given and the engine has no default storage type.",
+            )
+        })?;
+
+        if !matches!(storage_type, StorageType::FileSystem) {
+            return Ok(None);
+        }
+
+        let base = match self.storage_path {
+            Some(ref path) if path.trim().is_empty() => {
+                return Err(Error::msg(
+                    "the storage path of a filesystem index cannot be empty.",
+                ))
+            },
+            Some(ref path) => PathBuf::from(path),
+            None => Path::new(ROOT_PATH).join(INDEX_STORAGE_SUB_PATH),
+        };
+
+        if base.exists() && !base.is_dir() {
+            return Err(anyhow!(
+                "the storage path {:?} of a filesystem index must be a directory",
+                base
+            ));
+        }
+
+        let base = match self.tenant_id {
+            Some(ref tenant_id) => base.join(tenant_id),
+            None => base,
+        };
+
+        let storage_name = self.storage_name.as_deref().unwrap_or(&self.name);
+        Ok(Some(base.join(cr32_hash(storage_name).to_string())))
+    }
+
     /// Builds IndexContext from the declaration, applying any validation in
     /// the process.
     #[instrument(name = "index-setup", skip(self), fields(index = %self.name))]
@@ -131,21 +454,18 @@ impl IndexDeclaration {
         let mut schema_ctx = self.schema_ctx.clone();
         schema_ctx.calculate_once()?;
 
-        let open = match self.storage_type {
-            StorageType::Memory => {
+        let storage_dir = self.storage_dir()?;
+        let open = match (self.storage_type, storage_dir.clone()) {
+            (_, Some(dir)) => OpenType::Dir(dir),
+            (Some(StorageType::Memory), None) => {
                 // TODO: Remove in next major version.
                 warn!("Memory mode is depreciated, this now defaults to TempFile and will be removed in future versions");
                 OpenType::TempFile
             },
-            StorageType::TempDir => OpenType::TempFile,
-            StorageType::FileSystem => OpenType::Dir(
-                Path::new(ROOT_PATH)
-                    .join(INDEX_STORAGE_SUB_PATH)
-                    .join(cr32_hash(&self.name).to_string()),
-            ),
+            (_, None) => OpenType::TempFile,
         };
 
-        let dir = SledBackedDirectory::new_with_root(&open)?;
+        let dir = SledBackedDirectory::new_with_root(&open, self.durability)?;
         let does_exist = Index::exists(&dir).with_context(|| {
             format!("failed to check for existing index {:?}", &open)
         })?;
@@ -156,34 +476,33 @@ impl IndexDeclaration {
             Index::open_or_create(dir.clone(), schema_ctx.as_tantivy_schema())
         }?;
 
+        register_stemming_tokenizers(&index);
+        register_language_tokenizers(&index)?;
+        register_raw_tokenizer(&index);
+        if let Some(ref languages) = self.stop_word_languages {
+            register_stop_word_tokenizer(&index, languages)?;
+        }
+
         let schema = index.schema();
         schema_ctx.validate_with_schema(&schema)?;
 
         let query_context = {
-            let default_fields = schema_ctx.get_search_fields(&schema);
-            let fuzzy_fields = schema_ctx.get_fuzzy_search_fields(&schema);
-
-            let mut default_fields_with_boost = Vec::with_capacity(default_fields.len());
-            add_boost_fields(
-                &schema,
-                schema_ctx.boost_fields(),
-                &default_fields,
-                &mut default_fields_with_boost,
-            );
-
-            let mut fuzzy_fields_with_boost = Vec::with_capacity(fuzzy_fields.len());
-            add_boost_fields(
-                &schema,
-                schema_ctx.boost_fields(),
-                &fuzzy_fields,
-                &mut fuzzy_fields_with_boost,
-            );
+            let (default_fields_with_boost, fuzzy_fields_with_boost) =
+                get_boosted_search_fields(&schema_ctx, &schema);
 
             QueryContext {
                 id_field: schema.get_field(PRIMARY_KEY).expect("get pk"),
                 set_conjunction_by_default: self.set_conjunction_by_default,
                 use_fast_fuzzy: self.use_fast_fuzzy,
+                fast_fuzzy_fallback: self.fast_fuzzy_fallback,
                 strip_stop_words: self.strip_stop_words,
+                fuzzy_require_all_terms: self.fuzzy_require_all_terms,
+                max_fuzzy_terms: self.max_fuzzy_terms,
+                field_aliases: schema_ctx.field_aliases().clone(),
+                queryable_fields: schema_ctx
+                    .queryable_fields()
+                    .map(|fields| fields.iter().cloned().collect()),
+                default_search_field: schema_ctx.get_default_search_field(&schema),
                 default_search_fields: default_fields_with_boost,
                 fuzzy_search_fields: fuzzy_fields_with_boost,
             }
@@ -194,7 +513,10 @@ impl IndexDeclaration {
 
         Ok(IndexContext {
             name: self.name.clone(),
+            tenant_id: self.tenant_id.clone(),
+            log_searches: self.log_searches,
             storage,
+            storage_dir,
             correction_manager: corrections,
             index,
             schema_ctx: schema_ctx.clone(),
@@ -203,7 +525,10 @@ impl IndexDeclaration {
             query_ctx: query_context,
             fuzzy_search_fields: schema_ctx.get_fuzzy_search_fields(&schema),
             synonyms: SynonymsManager::init(),
-            stop_words: StopWordManager::init()?,
+            stop_words: match self.stop_word_languages {
+                Some(ref languages) => StopWordManager::init_with_languages(languages)?,
+                None => StopWordManager::init()?,
+            },
         })
     }
 }
@@ -213,9 +538,21 @@ pub struct IndexContext {
     /// The name of the index.
     pub(crate) name: String,
 
+    /// The tenant the index belongs to.
+    pub(crate) tenant_id: Option<String>,
+
+    /// If searches of the index should be logged, overriding the server's
+    /// default.
+    pub(crate) log_searches: Option<bool>,
+
     /// An SQLite DB instance used for storing engine state.
     pub(crate) storage: StorageBackend,
 
+    /// The directory the index's data is persisted in.
+    ///
+    /// This is only `Some` for `filesystem` indexes.
+    pub(crate) storage_dir: Option<PathBuf>,
+
     /// The index's custom stop words.
     pub(crate) stop_words: StopWordManager,
 
@@ -252,6 +589,11 @@ impl IndexContext {
         self.name.clone()
     }
 
+    #[inline]
+    pub fn tenant_id(&self) -> Option<&str> {
+        self.tenant_id.as_deref()
+    }
+
     /// Get the schema of the index.
     #[inline]
     pub(crate) fn schema(&self) -> Schema {
@@ -540,7 +882,7 @@ impl<'de> Deserialize<'de> for DocumentValue {
 }
 
 /// The possible formats for adding document values.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum DocumentValueOptions {
     /// A singular document value.
     Single(DocumentValue),
@@ -627,25 +969,370 @@ impl<'de> Deserialize<'de> for DocumentValueOptions {
     }
 }
 
+/// Returned when a document does not match the index's schema.
+///
+/// This lists every unknown and invalid field of the first invalid
+/// document, none of the given documents are added.
+#[derive(Debug, Serialize)]
+pub struct DocumentValidationError {
+    /// The position of the document within the given payload.
+    pub(crate) document: usize,
+
+    /// Fields which are not defined in the schema.
+    ///
+    /// These are only rejected if the index is strict.
+    pub(crate) unknown_fields: Vec<String>,
+
+    /// Fields mapped to why their values are invalid.
+    pub(crate) invalid_fields: BTreeMap<String, String>,
+}
+
+impl fmt::Display for DocumentValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "document {} does not match the index schema",
+            self.document
+        )?;
+
+        if !self.unknown_fields.is_empty() {
+            write!(f, ", unknown fields: {}", self.unknown_fields.join(", "))?;
+        }
+
+        for (field, reason) in self.invalid_fields.iter() {
+            write!(f, ", invalid field {:?}: {}", field, reason)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for DocumentValidationError {}
+
+/// The changes made when replacing a set of stop words or synonyms.
+#[derive(Debug, Serialize)]
+pub struct ReplacedEntries {
+    /// The amount of entries which were not in the previous set.
+    pub(crate) added: usize,
+
+    /// The amount of entries which are no longer in the set.
+    pub(crate) removed: usize,
+}
+
+impl ReplacedEntries {
+    /// Counts the entries added and removed between the two sets.
+    pub(crate) fn between<'a>(
+        before: impl Iterator<Item = &'a String>,
+        after: impl Iterator<Item = &'a String>,
+    ) -> Self {
+        let before: HashSet<&String> = before.collect();
+        let after: HashSet<&String> = after.collect();
+
+        Self {
+            added: after.difference(&before).count(),
+            removed: before.difference(&after).count(),
+        }
+    }
+}
+
+/// The internal state of an index useful for diagnosing fragmentation.
+#[derive(Debug, Serialize)]
+pub struct IndexDiagnostics {
+    /// Each of the segments currently being searched.
+    pub(crate) segments: Vec<SegmentDiagnostics>,
+
+    /// The amount of operations waiting in the writer's queue.
+    pub(crate) queued_operations: usize,
+
+    /// The amount of operations blocked waiting for space in the writer's
+    /// queue.
+    pub(crate) blocked_operations: usize,
+}
+
+/// How the index's search fields are ranked.
+#[derive(Debug, Serialize)]
+pub struct RankingReport {
+    /// The boost factor of each boosted search field.
+    pub(crate) boost_fields: BTreeMap<String, Score>,
+
+    /// The fields searched by normal queries.
+    pub(crate) search_fields: Vec<String>,
+
+    /// The fields searched by fuzzy queries.
+    pub(crate) fuzzy_search_fields: Vec<String>,
+
+    /// The capabilities of each of the search fields.
+    pub(crate) fields: BTreeMap<String, FieldCapabilities>,
+}
+
+impl RankingReport {
+    /// Creates a report of the given search fields.
+    pub(crate) fn new(
+        schema_ctx: &SchemaContext,
+        schema: &Schema,
+        default_fields: &[(Field, Score)],
+        fuzzy_fields: &[(Field, Score)],
+    ) -> Self {
+        let mut boost_fields = BTreeMap::new();
+        let mut fields = BTreeMap::new();
+        for (field, boost) in default_fields.iter().chain(fuzzy_fields.iter()) {
+            let name = schema.get_field_name(*field);
+            if *boost != 0.0 {
+                boost_fields.insert(name.to_string(), *boost);
+            }
+
+            let entry = schema.get_field_entry(*field);
+            let field_type = schema_ctx
+                .fields()
+                .get(name)
+                .map(|info| info.type_name())
+                .unwrap_or("unknown");
+
+            fields.insert(
+                name.to_string(),
+                FieldCapabilities {
+                    field_type,
+                    indexed: entry.is_indexed(),
+                    fast: entry.is_fast(),
+                    stored: entry.is_stored(),
+                },
+            );
+        }
+
+        let names = |fields: &[(Field, Score)]| {
+            fields
+                .iter()
+                .map(|(field, _)| schema.get_field_name(*field).to_string())
+                .collect()
+        };
+
+        Self {
+            boost_fields,
+            search_fields: names(default_fields),
+            fuzzy_search_fields: names(fuzzy_fields),
+            fields,
+        }
+    }
+}
+
+/// What a field can be used for when ranking.
+#[derive(Debug, Serialize)]
+pub struct FieldCapabilities {
+    /// The type of the field e.g. `text`.
+    #[serde(rename = "type")]
+    pub(crate) field_type: &'static str,
+
+    /// If the field can be searched.
+    pub(crate) indexed: bool,
+
+    /// If the field is a fast field.
+    pub(crate) fast: bool,
+
+    /// If the field's values are returned with each hit.
+    pub(crate) stored: bool,
+}
+
+/// The maximum amount of failed documents listed by a reindex report.
+pub(crate) const MAX_REPORTED_REINDEX_FAILURES: usize = 1_000;
+
+/// The outcome of copying the documents of one index into another.
+#[derive(Debug, Default, Serialize)]
+pub struct ReindexReport {
+    /// The amount of documents read from the source index.
+    pub(crate) read: usize,
+
+    /// The amount of documents added to the destination index.
+    pub(crate) copied: usize,
+
+    /// The amount of documents which did not match the destination schema.
+    pub(crate) failed: usize,
+
+    /// The first of the documents which did not match the destination
+    /// schema, this lists at most 1,000 documents.
+    pub(crate) failures: Vec<ReindexFailure>,
+}
+
+impl ReindexReport {
+    /// The amount of documents which did not match the destination schema.
+    #[inline]
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+}
+
+/// A document which could not be copied into the destination index.
+#[derive(Debug, Serialize)]
+pub struct ReindexFailure {
+    /// The id of the document in the source index.
+    #[serde(with = "document_id_serializer")]
+    pub(crate) document_id: u64,
+
+    /// Why the document was rejected.
+    pub(crate) reason: String,
+}
+
 /// A key-value map matching the target index's schema.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DocumentPayload(BTreeMap<String, DocumentValueOptions>);
 
 impl DocumentPayload {
+    /// Replaces the fields of the document with the fields of the other
+    /// document, any fields the other document does not have are kept.
+    pub(crate) fn merge(&mut self, other: DocumentPayload) {
+        self.0.extend(other.0);
+    }
+
+    /// If the document contains the field.
+    pub(crate) fn has_field(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    /// Applies each of the processors to the document in order.
+    pub(crate) fn apply_pipeline(&mut self, processors: &[Processor]) {
+        for processor in processors {
+            processor.apply(&mut self.0);
+        }
+    }
+
+    /// Populates each compound field from the values of its source fields.
+    pub(crate) fn apply_compound_fields(
+        &mut self,
+        compound_fields: &HashMap<String, Vec<String>>,
+    ) {
+        for (name, sources) in compound_fields {
+            concat_fields(&mut self.0, sources, name, " ");
+        }
+    }
+
+    /// Checks every field of the document against the schema.
+    ///
+    /// Unknown fields are only rejected if the index is strict, otherwise
+    /// they are dropped when the document is added. The amount of values
+    /// too large to be indexed as a single term is returned.
+    pub(crate) fn validate(
+        &self,
+        position: usize,
+        schema: &Schema,
+        ctx: &SchemaContext,
+    ) -> std::result::Result<usize, DocumentValidationError> {
+        let mut unknown_fields = vec![];
+        let mut invalid_fields = BTreeMap::new();
+        let mut oversized = 0;
+
+        for (field_name, data) in self.0.iter() {
+            let info = match ctx.fields().get(field_name) {
+                Some(info) => info,
+                None => {
+                    if ctx.is_strict() {
+                        unknown_fields.push(field_name.clone());
+                    }
+                    continue;
+                },
+            };
+
+            if info.is_required() & data.is_empty() {
+                invalid_fields.insert(
+                    field_name.clone(),
+                    "a required field must contain at least one value".to_string(),
+                );
+                continue;
+            }
+
+            let res = if let FieldDeclaration::Geo { .. } = info {
+                geo::parse_point(field_name, data.clone()).map(|_| ())
+            } else {
+                // should never panic as `ctx.fields` is inline with schema.
+                let field = schema.get_field(field_name).expect("get field");
+                let field_type = schema.get_field_entry(field).field_type();
+
+                // Only the last value of a single value field is used.
+                let values = match data {
+                    DocumentValueOptions::Single(value) => vec![value.clone()],
+                    DocumentValueOptions::Many(values) if info.is_multi() => {
+                        values.clone()
+                    },
+                    DocumentValueOptions::Many(values) => {
+                        values.last().cloned().into_iter().collect()
+                    },
+                };
+
+                values.into_iter().try_for_each(|value| {
+                    Self::to_field_value(
+                        field_name,
+                        field,
+                        field_type,
+                        value,
+                        ctx.oversized_terms(),
+                        &mut oversized,
+                    )
+                    .map(|_| ())
+                })
+            };
+
+            if let Err(e) = res {
+                invalid_fields.insert(field_name.clone(), e.to_string());
+            }
+        }
+
+        for field_name in ctx.required_fields() {
+            if !self.0.contains_key(field_name) {
+                invalid_fields
+                    .insert(field_name.clone(), "missing a required field".to_string());
+            }
+        }
+
+        if let Some(id_field) = ctx.id_field() {
+            if !self.0.contains_key(id_field) {
+                invalid_fields
+                    .insert(id_field.to_string(), "missing the id field".to_string());
+            }
+        }
+
+        if unknown_fields.is_empty() && invalid_fields.is_empty() {
+            Ok(oversized)
+        } else {
+            Err(DocumentValidationError {
+                document: position,
+                unknown_fields,
+                invalid_fields,
+            })
+        }
+    }
+
+    /// Converts the payload into a tantivy document matching the schema.
+    ///
+    /// The document is returned alongside the amount of oversized terms
+    /// which were truncated or skipped.
     pub(crate) fn parse_into_document(
+        self,
+        schema: &Schema,
+        ctx: &SchemaContext,
+    ) -> Result<(InternalDocument, usize)> {
+        let id = match ctx.id_field() {
+            Some(id_field) => self.derive_document_id(id_field, ctx)?,
+            None => rand::random::<DocumentId>(),
+        };
+
+        self.parse_into_document_with_id(id, schema, ctx)
+    }
+
+    /// Converts the payload into a tantivy document matching the schema
+    /// using the given id rather than deriving a new one.
+    pub(crate) fn parse_into_document_with_id(
         mut self,
+        id: DocumentId,
         schema: &Schema,
         ctx: &SchemaContext,
-    ) -> Result<InternalDocument> {
+    ) -> Result<(InternalDocument, usize)> {
         let mut doc = InternalDocument::new();
+        let mut oversized = 0;
+        let oversized_terms = ctx.oversized_terms();
 
         let field = schema.get_field(PRIMARY_KEY).ok_or_else(|| {
             Error::msg(
                 "index has no field '_id' and has been invalidated (This is a bug)",
             )
         })?;
-
-        let id = rand::random::<DocumentId>();
         doc.add_u64(field, id);
 
         for (field_name, info) in ctx.fields() {
@@ -675,53 +1362,133 @@ impl DocumentPayload {
             // should never panic as `ctx.fields` is inline with schema.
             let field = schema.get_field(field_name).expect("get field");
 
+            if let FieldDeclaration::Geo { .. } = info {
+                let (lat, lon) = geo::parse_point(field_name, data)?;
+                doc.add_f64(field, lat);
+                doc.add_f64(field, lon);
+                continue;
+            }
+
             let entry = schema.get_field_entry(field);
             let field_type = entry.field_type();
 
-            match data {
-                DocumentValueOptions::Single(value) => {
-                    Self::add_value(field_name, field, field_type, value, &mut doc)?
-                },
-                DocumentValueOptions::Many(mut values) => {
+            let values = match data {
+                DocumentValueOptions::Single(value) => vec![value],
+                DocumentValueOptions::Many(values) => {
                     if ctx.multi_value_fields().contains(field_name) {
-                        for value in values {
-                            Self::add_value(
-                                field_name, field, field_type, value, &mut doc,
-                            )?;
-                        }
-                    } else if let Some(value) = values.pop() {
-                        Self::add_value(field_name, field, field_type, value, &mut doc)?;
+                        values
+                    } else {
+                        values.into_iter().last().into_iter().collect()
                     }
                 },
             };
+
+            for value in values {
+                let value = Self::to_field_value(
+                    field_name,
+                    field,
+                    field_type,
+                    value,
+                    oversized_terms,
+                    &mut oversized,
+                )?;
+
+                if let Some(value) = value {
+                    doc.add(value);
+                }
+            }
         }
 
-        Ok(doc)
+        Ok((doc, oversized))
+    }
+
+    /// Derives the document id from the value of the index's id field.
+    fn derive_document_id(
+        &self,
+        id_field: &str,
+        ctx: &SchemaContext,
+    ) -> Result<DocumentId> {
+        let value = match self.0.get(id_field) {
+            Some(DocumentValueOptions::Single(value)) => value.clone(),
+            Some(DocumentValueOptions::Many(values)) if values.len() == 1 => {
+                values[0].clone()
+            },
+            Some(_) => {
+                return Err(anyhow!(
+                    "the id field ({:?}) must contain exactly one value",
+                    id_field
+                ))
+            },
+            None => return Err(anyhow!("missing the id field {:?}", id_field)),
+        };
+
+        match ctx.fields().get(id_field) {
+            Some(FieldDeclaration::U64 { .. }) => value.try_into(),
+            Some(FieldDeclaration::I64 { .. }) => {
+                let id: i64 = value.try_into()?;
+                Ok(id as DocumentId)
+            },
+            _ => Ok(id_hash(&value.as_string())),
+        }
     }
 
-    fn add_value(
+    /// Converts the value into the given field's type.
+    ///
+    /// String values too large to be indexed as a single term are handled
+    /// according to `oversized_terms`, incrementing `oversized` if they are
+    /// truncated or skipped. Skipped values return `None`, truncated values
+    /// are stored in full and only their indexed term is truncated.
+    fn to_field_value(
         key: &str,
         field: Field,
         field_type: &FieldType,
         value: DocumentValue,
-        doc: &mut InternalDocument,
-    ) -> Result<()> {
-        match field_type {
-            FieldType::U64(_) => doc.add_u64(field, value.try_into()?),
-            FieldType::I64(_) => doc.add_i64(field, value.try_into()?),
-            FieldType::F64(_) => doc.add_f64(field, value.try_into()?),
+        oversized_terms: OversizedTerms,
+        oversized: &mut usize,
+    ) -> Result<Option<FieldValue>> {
+        let value = match field_type {
+            FieldType::U64(_) => Value::U64(value.try_into()?),
+            FieldType::I64(_) => Value::I64(value.try_into()?),
+            FieldType::F64(_) => Value::F64(value.try_into()?),
             FieldType::Date(_) => {
                 let value: DateTime = value.try_into()?;
-                doc.add_date(field, &value)
+                Value::Date(value)
             },
-            FieldType::Str(_) => {
+            FieldType::Str(opts) => {
                 let value: String = value.try_into()?;
-                doc.add_text(field, &value)
+
+                let is_single_term = opts
+                    .get_indexing_options()
+                    .map(|indexing| indexing.tokenizer() == "raw")
+                    .unwrap_or_default();
+
+                if is_single_term && value.len() > MAX_TERM_LEN {
+                    match oversized_terms {
+                        // The value is stored in full, only its indexed term
+                        // is truncated by the raw tokenizer.
+                        OversizedTerms::Truncate => {},
+                        OversizedTerms::Skip => {
+                            *oversized += 1;
+                            return Ok(None);
+                        },
+                        OversizedTerms::Reject => {
+                            return Err(anyhow!(
+                                "field {:?} contains a value of {} bytes which exceeds the maximum term length of {} bytes",
+                                key,
+                                value.len(),
+                                MAX_TERM_LEN,
+                            ))
+                        },
+                    }
+
+                    *oversized += 1;
+                }
+
+                Value::Str(value)
             },
             FieldType::Facet(_) => {
                 let facet: Facet = value.try_into()?;
-                let val = FieldValue::new(field, Value::Facet(facet));
-                doc.add(val)
+                Value::Facet(facet)
             },
             _ => {
                 return Err(anyhow!(
@@ -729,9 +1496,9 @@ impl DocumentPayload {
                     key,
                 ))
             },
-        }
+        };
 
-        Ok(())
+        Ok(Some(FieldValue::new(field, value)))
     }
 }
 
@@ -816,7 +1583,7 @@ pub enum CompliantDocumentValue {
 }
 
 /// A individual document returned from the index.
-#[derive(Debug, Serialize)]
+#[derive(Debug)]
 pub struct DocumentHit {
     /// The document data itself.
     ///
@@ -828,12 +1595,178 @@ pub struct DocumentHit {
     /// This is a unique 64 bit integer that can be used
     /// to select other similar docs or the document itself.
     ///
+    /// Unlike tantivy's `DocAddress` this is stored alongside the document
+    /// itself so it remains stable across commits and segment merges,
+    /// making it safe for clients to cache.
+    ///
     /// This is serialized to a string for language support.
-    #[serde(with = "document_id_serializer")]
     pub(crate) document_id: u64,
 
     /// The computed score of the documents.
     pub(crate) score: Option<Score>,
+
+    /// The distance in metres from the centre point of the geo filter.
+    ///
+    /// This is only returned when searching with a geo filter.
+    pub(crate) distance: Option<f64>,
+
+    /// The highlighted snippets of each requested field.
+    ///
+    /// This is only returned when searching with highlight fields.
+    pub(crate) highlights: Option<HashMap<String, Vec<String>>>,
+
+    /// The index's `result_field_order` the document's fields are
+    /// serialized in, this is not serialized itself.
+    pub(crate) field_order: Option<Arc<[String]>>,
+
+    /// The decimal places the index's float fields are rounded to when
+    /// serialized, this is not serialized itself.
+    pub(crate) output_precisions: Option<Arc<HashMap<String, u32>>>,
+}
+
+impl Serialize for DocumentHit {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let len =
+            3 + self.distance.is_some() as usize + self.highlights.is_some() as usize;
+        let mut state = serializer.serialize_struct("DocumentHit", len)?;
+
+        state.serialize_field(
+            "doc",
+            &OrderedFields {
+                doc: &self.doc,
+                order: self.field_order.as_deref(),
+                precisions: self.output_precisions.as_deref(),
+            },
+        )?;
+        state.serialize_field("document_id", &self.document_id.to_string())?;
+        state.serialize_field("score", &self.score)?;
+
+        match self.distance {
+            Some(ref distance) => state.serialize_field("distance", distance)?,
+            None => state.skip_field("distance")?,
+        }
+
+        match self.highlights {
+            Some(ref highlights) => state.serialize_field("highlights", highlights)?,
+            None => state.skip_field("highlights")?,
+        }
+
+        state.end()
+    }
+}
+
+/// Serializes the fields of a document in the given order.
+///
+/// Without an order the fields are serialized in no particular order.
+/// Float fields with a precision are rounded to that amount of decimals.
+struct OrderedFields<'a> {
+    doc: &'a HashMap<String, Option<CompliantDocumentValue>>,
+    order: Option<&'a [String]>,
+    precisions: Option<&'a HashMap<String, u32>>,
+}
+
+impl<'a> OrderedFields<'a> {
+    fn field(&self, name: &str) -> Option<RoundedField<'a>> {
+        let value = self.doc.get(name)?;
+        let precision = self
+            .precisions
+            .and_then(|precisions| precisions.get(name))
+            .copied();
+
+        Some(RoundedField { value, precision })
+    }
+}
+
+impl<'a> Serialize for OrderedFields<'a> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let order = match (self.order, self.precisions) {
+            (Some(order), _) => order,
+            (None, None) => return self.doc.serialize(serializer),
+            (None, Some(_)) => {
+                let mut map = serializer.serialize_map(Some(self.doc.len()))?;
+                for name in self.doc.keys() {
+                    if let Some(value) = self.field(name) {
+                        map.serialize_entry(name, &value)?;
+                    }
+                }
+
+                return map.end();
+            },
+        };
+
+        let mut map = serializer.serialize_map(Some(self.doc.len()))?;
+        for name in order {
+            if let Some(value) = self.field(name) {
+                map.serialize_entry(name, &value)?;
+            }
+        }
+
+        let mut remaining: Vec<&String> = self
+            .doc
+            .keys()
+            .filter(|name| !order.contains(name))
+            .collect();
+        remaining.sort();
+
+        for name in remaining {
+            if let Some(value) = self.field(name) {
+                map.serialize_entry(name, &value)?;
+            }
+        }
+
+        map.end()
+    }
+}
+
+/// Serializes the value of a field, rounding any float values to the
+/// precision if one is given.
+struct RoundedField<'a> {
+    value: &'a Option<CompliantDocumentValue>,
+    precision: Option<u32>,
+}
+
+impl<'a> Serialize for RoundedField<'a> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let precision = match self.precision {
+            Some(precision) => precision,
+            None => return self.value.serialize(serializer),
+        };
+
+        // Values too large to be scaled without overflowing are left as
+        // they are, they have no decimal places to round anyway.
+        let round = |value: &Value| match value {
+            Value::F64(v) => {
+                let factor = 10f64.powi(precision as i32);
+                let scaled = v * factor;
+                if scaled.is_finite() {
+                    Value::F64(scaled.round() / factor)
+                } else {
+                    Value::F64(*v)
+                }
+            },
+            other => other.clone(),
+        };
+
+        match self.value {
+            Some(CompliantDocumentValue::Single(value)) => {
+                round(value).serialize(serializer)
+            },
+            Some(CompliantDocumentValue::Multi(values)) => {
+                let values: Vec<Value> = values.iter().map(round).collect();
+                values.serialize(serializer)
+            },
+            None => serializer.serialize_none(),
+        }
+    }
 }
 
 impl DocumentHit {
@@ -864,6 +1797,10 @@ impl DocumentHit {
                 },
             };
 
+            if val.is_none() && !ctx.include_nulls() {
+                continue;
+            }
+
             compliant.insert(name.clone(), val);
         }
 
@@ -871,8 +1808,68 @@ impl DocumentHit {
             doc: compliant,
             document_id: doc_id,
             score,
+            distance: None,
+            highlights: None,
+            field_order: ctx.result_field_order().cloned(),
+            output_precisions: Some(ctx.output_precisions())
+                .filter(|precisions| !precisions.is_empty())
+                .cloned(),
         }
     }
+
+    /// Removes any fields from the document which are not in the given set
+    /// of field names.
+    pub fn retain_fields(&mut self, fields: &[&str]) {
+        self.doc.retain(|name, _| fields.contains(&name.as_str()));
+    }
+
+    /// Converts the hit back into a document which can be added to an
+    /// index, renaming any fields with a mapping.
+    ///
+    /// Fields without a value are left out of the document.
+    pub(crate) fn into_payload(
+        self,
+        field_mappings: &HashMap<String, String>,
+    ) -> DocumentPayload {
+        let mut payload = BTreeMap::new();
+        for (name, value) in self.doc {
+            let value = match value {
+                Some(CompliantDocumentValue::Single(value)) => {
+                    match to_document_value(value) {
+                        Some(value) => DocumentValueOptions::Single(value),
+                        None => continue,
+                    }
+                },
+                Some(CompliantDocumentValue::Multi(values)) if !values.is_empty() => {
+                    DocumentValueOptions::Many(
+                        values.into_iter().filter_map(to_document_value).collect(),
+                    )
+                },
+                _ => continue,
+            };
+
+            let name = field_mappings.get(&name).cloned().unwrap_or(name);
+            payload.insert(name, value);
+        }
+
+        DocumentPayload(payload)
+    }
+}
+
+/// Converts a stored value back into the value it was given as.
+///
+/// Bytes and pre-tokenized values cannot be given in documents so they
+/// are dropped.
+fn to_document_value(value: Value) -> Option<DocumentValue> {
+    match value {
+        Value::Str(v) => Some(DocumentValue::Text(v)),
+        Value::U64(v) => Some(DocumentValue::U64(v)),
+        Value::I64(v) => Some(DocumentValue::I64(v)),
+        Value::F64(v) => Some(DocumentValue::F64(v)),
+        Value::Date(v) => Some(DocumentValue::Datetime(v)),
+        Value::Facet(v) => Some(DocumentValue::Text(v.to_path_string())),
+        _ => None,
+    }
 }
 
 mod document_id_serializer {
@@ -1063,6 +2060,75 @@ mod test_context_builder {
         Ok(())
     }
 
+    #[test]
+    fn test_tenant_storage_dir_expect_ok() -> Result<()> {
+        let declaration = |tenant_id: &str| {
+            serde_json::from_value::<IndexDeclaration>(serde_json::json!({
+                "name": "test",
+                "tenant_id": tenant_id,
+
+                // Reader context
+                "reader_threads": 1,
+                "max_concurrency": 1,
+
+                "storage_type": "filesystem",
+                "storage_path": "./tenant-storage",
+                "fields": {
+                    "title": {
+                        "type": "text",
+                        "stored": true
+                    },
+                },
+            }))
+        };
+
+        let dir = declaration("tenant-a")?
+            .storage_dir()?
+            .expect("storage dir");
+        let expected = Path::new("./tenant-storage")
+            .join("tenant-a")
+            .join(cr32_hash("test").to_string());
+        assert_eq!(dir, expected);
+
+        assert!(declaration("../tenant-a")?.validate().is_err());
+        assert!(declaration("")?.validate().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_searches_expect_ok() -> Result<()> {
+        let declaration = |log_searches: serde_json::Value| {
+            serde_json::from_value::<IndexDeclaration>(serde_json::json!({
+                "name": "test",
+                "log_searches": log_searches,
+
+                // Reader context
+                "reader_threads": 1,
+                "max_concurrency": 1,
+
+                "storage_type": "memory",
+                "fields": {
+                    "title": {
+                        "type": "text",
+                        "stored": true
+                    },
+                },
+            }))
+        };
+
+        assert_eq!(
+            declaration(serde_json::json!(false))?.log_searches(),
+            Some(false)
+        );
+        assert_eq!(declaration(serde_json::Value::Null)?.log_searches(), None);
+
+        let ctx = declaration(serde_json::json!(true))?.create_context()?;
+        assert_eq!(ctx.log_searches, Some(true));
+
+        Ok(())
+    }
+
     #[test]
     fn test_non_string_search_fields_expect_err() -> Result<()> {
         let dec = serde_json::from_value::<IndexDeclaration>(serde_json::json!({
@@ -1249,4 +2315,120 @@ mod test_context_builder {
 
         Ok(())
     }
+
+    #[test]
+    fn test_default_storage_type_expect_ok() -> Result<()> {
+        let mut dec = serde_json::from_value::<IndexDeclaration>(serde_json::json!({
+            "name": "test",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 64_000_000,
+            "writer_threads": 1,
+
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true
+                },
+            },
+        }))?;
+
+        assert!(dec.create_context().is_err());
+
+        dec.resolve_storage(Some(StorageType::TempDir), None);
+        assert!(dec.create_context().is_ok());
+
+        dec.storage_type = Some(StorageType::FileSystem);
+        dec.storage_path = Some("".to_string());
+        assert!(dec.create_context().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_renamed_keeps_storage_expect_ok() -> Result<()> {
+        let dec = serde_json::from_value::<IndexDeclaration>(serde_json::json!({
+            "name": "products_v1",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 64_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "filesystem",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true
+                },
+            },
+        }))?;
+
+        let renamed = dec.renamed("products");
+        assert_eq!(renamed.name(), "products");
+        assert_eq!(renamed.storage_dir()?, dec.storage_dir()?);
+
+        let renamed_again = renamed.renamed("products_v2");
+        assert_eq!(renamed_again.storage_dir()?, dec.storage_dir()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_shares_storage_with_expect_ok() -> Result<()> {
+        let dec = serde_json::from_value::<IndexDeclaration>(serde_json::json!({
+            "name": "products_v1",
+
+            // Reader context
+            "reader_threads": 1,
+            "max_concurrency": 1,
+
+            // Writer context
+            "writer_buffer": 64_000_000,
+            "writer_threads": 1,
+
+            "storage_type": "filesystem",
+            "fields": {
+                "title": {
+                    "type": "text",
+                    "stored": true
+                },
+            },
+        }))?;
+
+        let renamed = dec.renamed("products");
+        assert!(dec.with_name("products_v1").shares_storage_with(&renamed));
+        assert!(!dec.with_name("products_v2").shares_storage_with(&renamed));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_duplicate_fields_expect_err() {
+        let raw = r#"{
+            "name": "test",
+            "max_concurrency": 1,
+            "writer_buffer": 3000000,
+            "storage_type": "memory",
+            "fields": {
+                "title": {"type": "text", "stored": true},
+                "count": {"type": "u64", "stored": true},
+                "title": {"type": "string", "stored": true}
+            }
+        }"#;
+
+        let err = serde_json::from_str::<IndexDeclaration>(raw)
+            .err()
+            .expect("duplicate field error");
+        assert!(err.to_string().contains(
+            r#"field "title" is declared more than once, at positions 1 and 3"#
+        ));
+    }
 }
@@ -0,0 +1,263 @@
+use std::cmp::Reverse;
+
+use anyhow::{anyhow, Result};
+use hashbrown::HashMap;
+use levenshtein_automata::{Distance, LevenshteinAutomatonBuilder, DFA, SINK_STATE};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tantivy::schema::{Field, FieldType, Schema};
+use tantivy::Searcher;
+use tantivy_fst::Automaton;
+
+use crate::corrections::max_edit_distance;
+use crate::schema::SchemaContext;
+
+/// The automaton builders for each supported maximum edit distance.
+///
+/// These are expensive to create so are built once and shared.
+static AUTOMATON_BUILDERS: Lazy<[LevenshteinAutomatonBuilder; 2]> = Lazy::new(|| {
+    [
+        LevenshteinAutomatonBuilder::new(1, false),
+        LevenshteinAutomatonBuilder::new(2, false),
+    ]
+});
+
+/// Builds the automaton matching the terms within the maximum edit distance
+/// of the given word.
+fn build_dfa(word: &str) -> DFA {
+    AUTOMATON_BUILDERS[max_edit_distance(word) - 1].build_dfa(word)
+}
+
+/// Gets the edit distance between the automaton's word and the given term
+/// if it is within the maximum edit distance.
+fn dfa_distance(dfa: &DFA, term: &[u8]) -> Option<usize> {
+    match dfa.eval(term) {
+        Distance::Exact(distance) => Some(distance as usize),
+        Distance::AtLeast(_) => None,
+    }
+}
+
+/// Streams the terms of a term dictionary matched by a levenshtein automaton.
+struct LevenshteinDfa<'a>(&'a DFA);
+
+impl<'a> Automaton for LevenshteinDfa<'a> {
+    type State = u32;
+
+    fn start(&self) -> Self::State {
+        self.0.initial_state()
+    }
+
+    fn is_match(&self, state: &Self::State) -> bool {
+        matches!(self.0.distance(*state), Distance::Exact(_))
+    }
+
+    fn can_match(&self, state: &Self::State) -> bool {
+        *state != SINK_STATE
+    }
+
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+        self.0.transition(*state, byte)
+    }
+}
+
+/// A term completing a given prefix.
+#[derive(Debug, Serialize)]
+pub struct Suggestion {
+    /// The indexed term.
+    pub(crate) term: String,
+
+    /// The amount of documents containing the term.
+    ///
+    /// This includes any deleted documents which have not yet been merged
+    /// out of the index.
+    pub(crate) doc_freq: u32,
+}
+
+/// Gets the terms of the field starting with the given prefix, ranked by
+/// the amount of documents containing them.
+///
+/// Unless the field uses the raw tokenizer the prefix is lowercased to
+/// match the indexed terms.
+pub(crate) fn suggest_terms(
+    ctx: &SchemaContext,
+    searcher: &Searcher,
+    field: &str,
+    prefix: &str,
+    limit: usize,
+) -> Result<Vec<Suggestion>> {
+    let schema = searcher.schema();
+    let (field, is_raw) = get_suggest_field(schema, ctx.resolve_field(field))?;
+
+    let prefix = if is_raw {
+        prefix.to_string()
+    } else {
+        prefix.to_lowercase()
+    };
+
+    let mut doc_freqs: HashMap<Vec<u8>, u32> = HashMap::new();
+    for segment_reader in searcher.segment_readers() {
+        let inverted_index = segment_reader.inverted_index(field)?;
+        let mut stream = inverted_index
+            .terms()
+            .range()
+            .ge(prefix.as_bytes())
+            .into_stream()?;
+
+        while stream.advance() {
+            let key = stream.key();
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+
+            *doc_freqs.entry(key.to_vec()).or_default() += stream.value().doc_freq;
+        }
+    }
+
+    let mut suggestions: Vec<Suggestion> = doc_freqs
+        .into_iter()
+        .map(|(term, doc_freq)| Suggestion {
+            term: String::from_utf8_lossy(&term).into_owned(),
+            doc_freq,
+        })
+        .collect();
+
+    suggestions.sort_by(|a, b| {
+        b.doc_freq
+            .cmp(&a.doc_freq)
+            .then_with(|| a.term.cmp(&b.term))
+    });
+    suggestions.truncate(limit);
+
+    Ok(suggestions)
+}
+
+/// A query with each unknown word replaced by the closest indexed term.
+#[derive(Debug, Serialize)]
+pub struct SpellCheck {
+    /// The corrected query.
+    pub(crate) query: String,
+
+    /// The words of the query which were corrected.
+    pub(crate) corrections: Vec<Correction>,
+}
+
+/// A single corrected word of a query.
+#[derive(Debug, Serialize)]
+pub struct Correction {
+    /// The original word.
+    pub(crate) word: String,
+
+    /// The indexed term replacing the word.
+    pub(crate) suggestion: String,
+
+    /// The edit distance between the word and the suggestion.
+    pub(crate) distance: usize,
+
+    /// The amount of documents containing the suggestion.
+    pub(crate) doc_freq: u32,
+}
+
+/// Corrects each word of the query with the closest term in the field's
+/// term dictionary.
+///
+/// The closest term is the one with the smallest edit distance, ties are
+/// broken by the amount of documents containing the term. Words which are
+/// already indexed or have no term within the maximum edit distance are
+/// left as they are.
+///
+/// Only the terms within the maximum edit distance of each word are read
+/// from the term dictionary.
+pub(crate) fn spell_check(
+    ctx: &SchemaContext,
+    searcher: &Searcher,
+    field: &str,
+    query: &str,
+) -> Result<SpellCheck> {
+    let schema = searcher.schema();
+    let (field, is_raw) = get_suggest_field(schema, ctx.resolve_field(field))?;
+
+    let words: Vec<String> = if is_raw {
+        vec![query.to_string()]
+    } else {
+        query.split_whitespace().map(|w| w.to_lowercase()).collect()
+    };
+
+    // The best (term, distance, doc freq) of each word.
+    let mut best: Vec<Option<(String, usize, u32)>> = Vec::with_capacity(words.len());
+    for word in words.iter() {
+        let dfa = build_dfa(word);
+
+        let mut doc_freqs: HashMap<Vec<u8>, u32> = HashMap::new();
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(field)?;
+            let mut stream = inverted_index
+                .terms()
+                .search(LevenshteinDfa(&dfa))
+                .into_stream()?;
+
+            while stream.advance() {
+                *doc_freqs.entry(stream.key().to_vec()).or_default() +=
+                    stream.value().doc_freq;
+            }
+        }
+
+        let closest = doc_freqs
+            .into_iter()
+            .filter_map(|(term, doc_freq)| {
+                let distance = dfa_distance(&dfa, &term)?;
+                Some((distance, Reverse(doc_freq), term))
+            })
+            .min()
+            .map(|(distance, Reverse(doc_freq), term)| {
+                (
+                    String::from_utf8_lossy(&term).into_owned(),
+                    distance,
+                    doc_freq,
+                )
+            });
+
+        best.push(closest);
+    }
+
+    let mut corrected = Vec::with_capacity(words.len());
+    let mut corrections = vec![];
+    for (word, best) in words.into_iter().zip(best) {
+        match best {
+            Some((suggestion, distance, doc_freq)) if distance > 0 => {
+                corrected.push(suggestion.clone());
+                corrections.push(Correction {
+                    word,
+                    suggestion,
+                    distance,
+                    doc_freq,
+                });
+            },
+            _ => corrected.push(word),
+        }
+    }
+
+    Ok(SpellCheck {
+        query: corrected.join(" "),
+        corrections,
+    })
+}
+
+/// Gets the field to suggest terms from, this must be an indexed text field.
+///
+/// The field is returned alongside if it uses the raw tokenizer.
+fn get_suggest_field(schema: &Schema, name: &str) -> Result<(Field, bool)> {
+    let field = schema
+        .get_field(name)
+        .ok_or_else(|| anyhow!("no field exists with name: {:?}", name))?;
+
+    match schema.get_field_entry(field).field_type() {
+        FieldType::Str(opts) => match opts.get_indexing_options() {
+            Some(indexing) => Ok((field, indexing.tokenizer() == "raw")),
+            None => Err(anyhow!("suggest field {:?} is not indexed", name)),
+        },
+        _ => Err(anyhow!(
+            "suggest field {:?} must be a text or string field",
+            name
+        )),
+    }
+}
@@ -59,7 +59,29 @@ impl SynonymsManager {
         relations: &[String],
     ) -> Result<HashMap<String, Box<[String]>>> {
         let guard = self.synonyms.load();
-        let current_mapping = guard.as_ref();
+        Self::merge_synonyms(guard.as_ref(), relations)
+    }
+
+    /// Parses a given set of synonym relations into a new dictionary,
+    /// ignoring any existing synonyms.
+    pub fn parse_replacement_synonyms(
+        &self,
+        relations: &[String],
+    ) -> Result<HashMap<String, Box<[String]>>> {
+        Self::merge_synonyms(&HashMap::default(), relations)
+    }
+
+    /// Parses the given synonym relations merging them into a copy of the
+    /// given mapping.
+    ///
+    /// A `word(s):related_item(s)` relation is one way, only the words on
+    /// the left expand to the related items. A relation without a `:` e.g.
+    /// `foo,bar,baz` is a set of equivalent words which all expand to each
+    /// other.
+    fn merge_synonyms(
+        current_mapping: &HashMap<String, Box<[String]>>,
+        relations: &[String],
+    ) -> Result<HashMap<String, Box<[String]>>> {
         let mut new_mapping: HashMap<String, HashSet<String>> =
             HashMap::with_capacity(current_mapping.len());
         for (key, relations) in current_mapping {
@@ -70,13 +92,17 @@ impl SynonymsManager {
         }
 
         for relation in relations {
-            let (left, right) = relation.split_once(':').ok_or_else(|| {
-                anyhow!(
-                    "invalid synonym relation defined. synonyms must follow the \
-                    `word(s):related_item(s)` format, Got {:?}",
-                    relation,
-                )
-            })?;
+            let (left, right) = match relation.split_once(':') {
+                Some(split) => split,
+                None if relation.contains(',') => (relation.as_str(), relation.as_str()),
+                None => {
+                    return Err(anyhow!(
+                        "invalid synonym relation defined. synonyms must follow the \
+                        `word(s):related_item(s)` or `word,word(s)` format, Got {:?}",
+                        relation,
+                    ))
+                },
+            };
 
             let left = left.to_lowercase();
             let right = right.to_lowercase();
@@ -191,6 +217,23 @@ impl PersistentSynonymsManager {
         Ok(())
     }
 
+    /// Replaces all synonyms with the given relations.
+    ///
+    /// The new dictionary is swapped in at once so queries never see a
+    /// partially replaced dictionary.
+    #[instrument(name = "synonyms", skip(self))]
+    pub fn replace_all(&self, relations: &[String]) -> Result<()> {
+        let new_synonyms = self.manager.parse_replacement_synonyms(relations)?;
+        self.conn.store_structure(Self::KEYSPACE, &new_synonyms)?;
+
+        let new_len = new_synonyms.len();
+        self.manager.synonyms.store(Arc::new(new_synonyms));
+
+        info!("replaced the dictionary with {} synonyms", new_len);
+
+        Ok(())
+    }
+
     /// Remove all synonyms
     #[instrument(name = "synonyms", skip(self))]
     pub fn clear_all(&self) -> Result<()> {
@@ -1,29 +1,30 @@
-use std::mem;
-use std::path::Path;
+use std::ops::Bound;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::{fmt, mem};
 
 use anyhow::{anyhow, Error, Result};
+use chrono::Utc;
 use crossbeam::channel::{self, RecvTimeoutError};
 use crossbeam::queue::SegQueue;
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 use sysinfo::SystemExt;
-use tantivy::schema::{Field, Schema};
-use tantivy::{IndexWriter, Opstamp, TantivyError, Term};
+use tantivy::collector::{Count, TopDocs};
+use tantivy::fastfield::FastFieldReader;
+use tantivy::query::{Query, RangeQuery, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption, Schema, Type, Value};
+use tantivy::{DocSet, IndexWriter, Opstamp, SegmentId, TantivyError, Term, TERMINATED};
 use tokio::sync::oneshot;
-use tokio::time::Duration;
+use tokio::time::{Duration, Instant};
 
 use crate::corrections::SymSpellCorrectionManager;
-use crate::helpers::{cr32_hash, Validate};
-use crate::schema::{SchemaContext, PRIMARY_KEY};
+use crate::helpers::Validate;
+use crate::schema::{DedupMode, SchemaContext, PRIMARY_KEY};
 use crate::stop_words::{PersistentStopWordManager, StopWordManager};
 use crate::storage::StorageBackend;
-use crate::structures::{
-    DocumentPayload,
-    IndexContext,
-    INDEX_STORAGE_SUB_PATH,
-    ROOT_PATH,
-};
+use crate::structures::{DocumentHit, DocumentPayload, IndexContext};
 use crate::synonyms::{PersistentSynonymsManager, SynonymsManager};
 use crate::DocumentId;
 
@@ -34,6 +35,33 @@ type WaitersQueue = Arc<SegQueue<oneshot::Sender<()>>>;
 type ShutdownWaker = async_channel::Sender<()>;
 type ShutdownReceiver = async_channel::Receiver<()>;
 
+/// Returned when the writer's operation queue has remained full for longer
+/// than the allowed blocking period.
+///
+/// The operation is not submitted to the writer when this is returned.
+#[derive(Debug)]
+pub struct WriterQueueFull;
+
+impl fmt::Display for WriterQueueFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the writer operation queue is full, try again later")
+    }
+}
+
+impl std::error::Error for WriterQueueFull {}
+
+/// Returned by the writer when updating a document which does not exist.
+#[derive(Debug)]
+pub(crate) struct DocumentNotFound;
+
+impl fmt::Display for DocumentNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("no document exists with the given id")
+    }
+}
+
+impl std::error::Error for DocumentNotFound {}
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub(crate) struct WriterContext {
     /// The amount of bytes to allocate to the writer buffer.
@@ -48,6 +76,46 @@ pub(crate) struct WriterContext {
     /// then the system will automatically commit and index them. In Seconds.
     #[serde(default)]
     auto_commit: usize,
+
+    /// The amount of times a failed commit is retried before the error is
+    /// returned.
+    #[serde(default = "defaults::default_commit_retries")]
+    commit_retries: usize,
+
+    /// The delay before the first commit retry in milliseconds.
+    ///
+    /// This is doubled after each failed attempt.
+    #[serde(default = "defaults::default_commit_retry_backoff")]
+    commit_retry_backoff: u64,
+
+    /// The window in milliseconds commit requests are coalesced within.
+    ///
+    /// If set, a commit waits up to this long for any further commits
+    /// before committing the index once and resolving all of them together.
+    /// Defaults to `0` which commits on every request.
+    #[serde(default)]
+    commit_coalesce_window: u64,
+
+    /// Merges the index's segments down to a target count once the writer
+    /// has been idle for a while.
+    ///
+    /// Defaults to leaving segments to tantivy's merge policy.
+    #[serde(default)]
+    auto_merge: Option<AutoMerge>,
+}
+
+/// Merges an index's committed segments while the writer is idle.
+///
+/// Merging only starts once no operations have been sent to the writer for
+/// `idle_secs`, so it does not compete with ongoing ingest.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub(crate) struct AutoMerge {
+    /// The amount of segments the index is merged down to.
+    target_segments: usize,
+
+    /// The amount of seconds the writer must be idle for before merging.
+    #[serde(default = "defaults::default_auto_merge_idle")]
+    idle_secs: u64,
 }
 
 mod defaults {
@@ -65,6 +133,22 @@ mod defaults {
     pub const HEAP_SIZE_MIN: usize = ((MARGIN_IN_BYTES as u32) * 3u32) as usize;
     pub const HEAP_SIZE_MAX: usize = u32::MAX as usize - MARGIN_IN_BYTES;
 
+    /// The default amount of times to retry a failed commit.
+    pub fn default_commit_retries() -> usize {
+        3
+    }
+
+    /// The default delay before retrying a failed commit in milliseconds.
+    pub fn default_commit_retry_backoff() -> u64 {
+        100
+    }
+
+    /// The default amount of seconds the writer must be idle for before
+    /// automatically merging segments.
+    pub fn default_auto_merge_idle() -> u64 {
+        60
+    }
+
     /// The default amount of writer threads to use if left out of
     /// the index creation payload.
     pub fn default_writer_threads() -> usize {
@@ -134,7 +218,7 @@ impl WriterContext {
         Ok(Self {
             writer_threads: num_threads,
             writer_buffer: buffer,
-            auto_commit: self.auto_commit,
+            ..*self
         })
     }
 }
@@ -147,6 +231,20 @@ impl Validate for WriterContext {
             ));
         }
 
+        if let Some(auto_merge) = self.auto_merge {
+            if auto_merge.target_segments == 0 {
+                return Err(Error::msg(
+                    "auto merge target segments must be at least 1.",
+                ));
+            }
+
+            if auto_merge.idle_secs == 0 {
+                return Err(Error::msg(
+                    "auto merge idle time must be at least 1 second.",
+                ));
+            }
+        }
+
         Ok(())
     }
 }
@@ -169,6 +267,9 @@ pub(super) enum WriterOp {
     /// Removes all stopwords.
     ClearStopWords,
 
+    /// Replaces all stopwords with the given set.
+    ReplaceStopWords(Vec<String>),
+
     /// Adds a set of synonyms.
     AddSynonyms(Vec<String>),
 
@@ -178,18 +279,28 @@ pub(super) enum WriterOp {
     /// Removes all synonyms.
     ClearSynonyms,
 
+    /// Replaces all synonyms with the given set of relations.
+    ReplaceSynonyms(Vec<String>),
+
     /// Adds a document to the index.
     AddDocument(DocumentPayload),
 
     /// Adds multiple documents to the index.
     AddManyDocuments(Vec<DocumentPayload>),
 
+    /// Merges the given fields into the document with the given id,
+    /// keeping it's id.
+    UpdateDocument(DocumentId, DocumentPayload),
+
     /// Deletes any documents matching the given term.
     DeleteManyDocuments(Vec<DocumentId>),
 
     /// Removes all documents from the index.
     DeleteAll,
 
+    /// Deletes and commits any documents older than the index's document ttl.
+    DeleteExpired,
+
     /// A simple Ping to check if the worker is alive still after creation.
     __Ping,
 
@@ -208,12 +319,51 @@ pub struct IndexWriterWorker {
     reader: crate::reader::Reader,
     using_fast_fuzzy: bool,
     pk_field: Field,
+    dedup_field: Option<Field>,
+    pending_dedup_terms: HashSet<Term>,
+
+    /// The latest version of each document added or deleted by id since
+    /// the last commit, `None` if the document was deleted.
+    ///
+    /// Updates merge with these before falling back to committed documents.
+    uncommitted_documents: HashMap<DocumentId, Option<DocumentPayload>>,
+    deleted_all: bool,
+    reader_outdated: bool,
+    document_ttl: Option<(Field, Duration)>,
     fuzzy_fields: Vec<Field>,
     waiters: WaitersQueue,
     schema: Schema,
     schema_ctx: SchemaContext,
     writer: IndexWriter,
     auto_commit: u64,
+    commit_retries: usize,
+    commit_retry_backoff: Duration,
+    commit_coalesce_window: Duration,
+
+    /// The waiters of the commits waiting to be coalesced or retried and
+    /// the time the underlying commit is due.
+    pending_commits: Vec<oneshot::Sender<Result<()>>>,
+    commit_deadline: Option<Instant>,
+
+    /// The amount of times the waiting commit has failed, this is only
+    /// non-zero while a failed commit is waiting to be retried.
+    commit_attempt: usize,
+
+    /// The automatic merge settings and if the segments may need merging,
+    /// this is set by every commit.
+    auto_merge: Option<AutoMerge>,
+    merge_pending: bool,
+
+    /// When the last operation sent by a user was received.
+    ///
+    /// Auto commits and merges wait for the writer to be idle from this,
+    /// the writer's own background operations are not counted so they do
+    /// not keep holding them off.
+    last_op_at: Instant,
+
+    /// Tells the index's warmer thread to warm up the reader, this is only
+    /// set if the index is warmed up after each commit.
+    warmer: Option<channel::Sender<()>>,
     rx: OpReceiver,
     shutdown: ShutdownWaker,
     corrections: SymSpellCorrectionManager,
@@ -232,20 +382,63 @@ impl IndexWriterWorker {
         let mut op_since_last_commit = false;
         loop {
             while let Ok((op, waker)) = self.rx.try_recv() {
-                op_since_last_commit = true;
-                self.handle_message(op, waker);
+                op_since_last_commit |= self.handle_received(op, waker);
             }
 
-            // Wake up waiters once a message has been removed.
+            // Wake up waiters once a message has been removed, skipping
+            // any which have already timed out.
             while let Some(waiter) = self.waiters.pop() {
-                let _ = waiter.send(());
+                if !waiter.is_closed() {
+                    let _ = waiter.send(());
+                }
+            }
+
+            if let Some(deadline) = self.commit_deadline {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                match self.rx.recv_timeout(remaining) {
+                    Err(RecvTimeoutError::Timeout) => {
+                        self.flush_pending_commits();
+                        op_since_last_commit = false;
+                    },
+                    Err(RecvTimeoutError::Disconnected) => {
+                        info!("writer actor channel dropped, shutting down...");
+                        self.flush_pending_commits();
+                        break;
+                    },
+                    Ok((op, waker)) => {
+                        op_since_last_commit |= self.handle_received(op, waker);
+                    },
+                }
+
+                continue;
             }
 
             if (self.auto_commit == 0) | !op_since_last_commit {
+                if let Some(auto_merge) = self.auto_merge.filter(|_| self.merge_pending)
+                {
+                    let idle_until =
+                        self.last_op_at + Duration::from_secs(auto_merge.idle_secs);
+                    let remaining = idle_until.saturating_duration_since(Instant::now());
+                    match self.rx.recv_timeout(remaining) {
+                        Err(RecvTimeoutError::Timeout) => {
+                            self.merge_segments(auto_merge.target_segments);
+                            self.merge_pending = false;
+                        },
+                        Err(RecvTimeoutError::Disconnected) => {
+                            info!("writer actor channel dropped, shutting down...");
+                            break;
+                        },
+                        Ok((op, waker)) => {
+                            op_since_last_commit |= self.handle_received(op, waker);
+                        },
+                    }
+
+                    continue;
+                }
+
                 info!("parking writer until new events present");
                 if let Ok((op, waker)) = self.rx.recv() {
-                    op_since_last_commit = true;
-                    self.handle_message(op, waker);
+                    op_since_last_commit |= self.handle_received(op, waker);
                 } else {
                     info!("writer actor channel dropped, shutting down...");
                     break;
@@ -254,12 +447,14 @@ impl IndexWriterWorker {
                 continue;
             }
 
-            match self.rx.recv_timeout(Duration::from_secs(self.auto_commit)) {
+            let commit_at = self.last_op_at + Duration::from_secs(self.auto_commit);
+            let remaining = commit_at.saturating_duration_since(Instant::now());
+            match self.rx.recv_timeout(remaining) {
                 Err(RecvTimeoutError::Timeout) => {
                     info!("running auto commit");
 
                     // We know we wont shutdown.
-                    let _ = self.handle_message(WriterOp::Commit, None);
+                    self.handle_message(WriterOp::Commit, None);
                     op_since_last_commit = false;
                 },
                 Err(RecvTimeoutError::Disconnected) => {
@@ -267,7 +462,7 @@ impl IndexWriterWorker {
                     break;
                 },
                 Ok((op, waker)) => {
-                    self.handle_message(op, waker);
+                    self.handle_received(op, waker);
                 },
             }
         }
@@ -282,6 +477,23 @@ impl IndexWriterWorker {
         info!("shutdown complete!");
     }
 
+    /// Handles an operation received from the writer's channel, returning
+    /// if it was sent by a user rather than the writer's background tasks.
+    fn handle_received(
+        &mut self,
+        op: WriterOp,
+        waker: Option<oneshot::Sender<Result<()>>>,
+    ) -> bool {
+        let is_background = matches!(op, WriterOp::DeleteExpired);
+        if !is_background {
+            self.last_op_at = Instant::now();
+        }
+
+        self.handle_message(op, waker);
+
+        !is_background
+    }
+
     #[instrument(name = "writer-message-handler", level = "trace", skip(self, waker))]
     fn handle_message(
         &mut self,
@@ -289,11 +501,43 @@ impl IndexWriterWorker {
         waker: Option<oneshot::Sender<Result<()>>>,
     ) {
         info!("ready to handling operations!");
+
+        if let WriterOp::Commit = op {
+            // Commits sent while a failed commit waits to be retried are
+            // resolved by the retry.
+            if self.commit_attempt > 0 {
+                self.pending_commits.extend(waker);
+                return;
+            }
+
+            if !self.commit_coalesce_window.is_zero() {
+                if let Some(waker) = waker {
+                    let window = self.commit_coalesce_window;
+                    self.pending_commits.push(waker);
+                    self.commit_deadline
+                        .get_or_insert_with(|| Instant::now() + window);
+                    return;
+                }
+            }
+
+            // Auto commits include the changes of any coalesced commits.
+            self.pending_commits.extend(waker);
+            self.commit_pending();
+            return;
+        }
+
+        // A rollback or shutdown must not discard the changes of any commits
+        // already waiting to be coalesced.
+        if matches!(op, WriterOp::Rollback | WriterOp::__Shutdown) {
+            self.flush_pending_commits();
+        }
+
         match self.handle_op(op) {
-            Err(e) => {
-                if let Some(w) = waker {
+            Err(e) => match waker {
+                Some(w) => {
                     let _ = w.send(Err(e));
-                }
+                },
+                None => error!("failed to handle operation: {:?}", e),
             },
             _ => {
                 if let Some(w) = waker {
@@ -303,14 +547,265 @@ impl IndexWriterWorker {
         }
     }
 
+    /// Commits the index for any commits waiting to be coalesced or
+    /// retried.
+    fn flush_pending_commits(&mut self) {
+        if self.pending_commits.is_empty() && self.commit_attempt == 0 {
+            self.commit_deadline = None;
+            return;
+        }
+
+        self.commit_pending();
+    }
+
+    /// Commits the index once for all of the waiting commits, resolving
+    /// each of their waiters with the outcome.
+    ///
+    /// If the commit fails and retries remain the waiters are kept and the
+    /// commit is retried after an exponential backoff, the writer carries on
+    /// handling operations in the meantime.
+    fn commit_pending(&mut self) {
+        self.commit_deadline = None;
+
+        let waiters = mem::take(&mut self.pending_commits);
+        info!(waiting = waiters.len(), "committing waiting commits");
+
+        match self.commit() {
+            Ok(transaction_id) => {
+                self.commit_attempt = 0;
+                debug!(
+                    "[ TRANSACTION {} ] completed operation COMMIT",
+                    transaction_id
+                );
+
+                for waiter in waiters {
+                    let _ = waiter.send(Ok(()));
+                }
+            },
+            Err(e) if self.commit_attempt < self.commit_retries => {
+                let backoff = self
+                    .commit_retry_backoff
+                    .saturating_mul(2u32.saturating_pow(self.commit_attempt as u32));
+
+                self.commit_attempt += 1;
+                warn!(
+                    "commit failed, retrying in {:?} (attempt {}/{}): {}",
+                    backoff, self.commit_attempt, self.commit_retries, e
+                );
+
+                self.pending_commits = waiters;
+                self.commit_deadline = Some(Instant::now() + backoff);
+            },
+            Err(e) => {
+                error!(
+                    "commit failed after {} retries: {:?}",
+                    self.commit_attempt, e
+                );
+                self.commit_attempt = 0;
+
+                let msg = e.to_string();
+                for waiter in waiters {
+                    let _ = waiter.send(Err(Error::msg(msg.clone())));
+                }
+            },
+        }
+    }
+
     fn handle_remove_doc(&mut self, id: DocumentId) -> Opstamp {
+        self.uncommitted_documents.insert(id, None);
+
         let term = Term::from_field_u64(self.pk_field, id);
         self.writer.delete_term(term)
     }
 
-    fn handle_add_document(&mut self, document: DocumentPayload) -> Result<Opstamp> {
-        let document = document.parse_into_document(&self.schema, &self.schema_ctx)?;
-        self.writer.add_document(document).map_err(Error::from)
+    /// Forgets the documents changed since the last commit, once they are
+    /// either committed or rolled back.
+    fn clear_uncommitted_documents(&mut self) {
+        self.uncommitted_documents.clear();
+        self.deleted_all = false;
+    }
+
+    /// Adds the document to the index.
+    ///
+    /// If the document is a duplicate of an existing document and the
+    /// index skips duplicates `None` is returned.
+    fn handle_add_document(
+        &mut self,
+        document: DocumentPayload,
+    ) -> Result<Option<Opstamp>> {
+        // Documents with an id field can be updated before being committed.
+        let payload = self.schema_ctx.id_field().map(|_| document.clone());
+        let (document, oversized) =
+            document.parse_into_document(&self.schema, &self.schema_ctx)?;
+
+        if oversized > 0 {
+            warn!(
+                oversized_terms = oversized,
+                policy = ?self.schema_ctx.oversized_terms(),
+                "document contained values too large to index as a single term"
+            );
+        }
+
+        // Ids derived from the id field are stable so any existing document
+        // with the same id is replaced.
+        if let Some(payload) = payload {
+            if let Some(Value::U64(id)) = document.get_first(self.pk_field) {
+                self.handle_remove_doc(*id);
+                self.uncommitted_documents.insert(*id, Some(payload));
+            }
+        }
+
+        let dedup_term = self.dedup_field.and_then(|field| {
+            document
+                .get_first(field)
+                .and_then(|value| dedup_term(field, value))
+        });
+
+        if let Some(term) = dedup_term {
+            match self.schema_ctx.dedup_mode() {
+                DedupMode::Replace => {
+                    self.writer.delete_term(term);
+                },
+                DedupMode::Skip => {
+                    if self.is_duplicate(&term)? {
+                        return Ok(None);
+                    }
+
+                    self.pending_dedup_terms.insert(term);
+                },
+            }
+        }
+
+        self.writer
+            .add_document(document)
+            .map(Some)
+            .map_err(Error::from)
+    }
+
+    /// Merges the fields into the document with the given id, replacing
+    /// the document with the merged document.
+    ///
+    /// The fields are merged with the latest version of the document, so
+    /// any changes since the last commit are kept. The new document keeps
+    /// the same id and is not checked for duplicates, the previous version
+    /// of the document would otherwise always be a duplicate of it.
+    fn handle_update_document(
+        &mut self,
+        id: DocumentId,
+        fields: DocumentPayload,
+    ) -> Result<Opstamp> {
+        let existing = match self.uncommitted_documents.get(&id) {
+            Some(document) => document.clone(),
+            None if self.deleted_all => None,
+            None => self.get_committed_document(id)?,
+        };
+
+        let mut document = existing.ok_or(DocumentNotFound)?;
+        document.merge(fields);
+        document.apply_pipeline(self.schema_ctx.ingest_pipeline());
+        document.apply_compound_fields(self.schema_ctx.compound_fields());
+        document.validate(0, &self.schema, &self.schema_ctx)?;
+
+        let (parsed, _) = document.clone().parse_into_document_with_id(
+            id,
+            &self.schema,
+            &self.schema_ctx,
+        )?;
+
+        self.handle_remove_doc(id);
+        let transaction_id = self.writer.add_document(parsed)?;
+        self.uncommitted_documents.insert(id, Some(document));
+
+        Ok(transaction_id)
+    }
+
+    /// Gets the committed document with the given id.
+    fn get_committed_document(
+        &mut self,
+        id: DocumentId,
+    ) -> Result<Option<DocumentPayload>> {
+        // The reader reloads in the background after a commit, so it must
+        // be reloaded to see the latest committed version of the document.
+        if self.reader_outdated {
+            self.reader.force_reload()?;
+            self.reader_outdated = false;
+        }
+
+        let searcher = self.reader.get_searcher();
+        let query = TermQuery::new(
+            Term::from_field_u64(self.pk_field, id),
+            IndexRecordOption::Basic,
+        );
+
+        let addr = match searcher.search(&query, &TopDocs::with_limit(1))?.first() {
+            Some((_, addr)) => *addr,
+            None => return Ok(None),
+        };
+
+        let doc = searcher.doc(addr)?;
+        let hit = DocumentHit::from_tantivy_document(
+            &self.schema_ctx,
+            id,
+            self.schema.to_named_doc(&doc),
+            None,
+        );
+
+        Ok(Some(hit.into_payload(&HashMap::new())))
+    }
+
+    /// Checks if a document with the given dedup term has been added,
+    /// either since the last commit or to the committed documents.
+    fn is_duplicate(&self, term: &Term) -> Result<bool> {
+        if self.pending_dedup_terms.contains(term) {
+            return Ok(true);
+        }
+
+        let searcher = self.reader.get_searcher();
+        let query = TermQuery::new(term.clone(), IndexRecordOption::Basic);
+        let count = searcher.search(&query, &Count)?;
+
+        Ok(count > 0)
+    }
+
+    /// Deletes every committed document whose ttl field is older than the
+    /// index's document ttl, returning the amount of documents deleted.
+    fn delete_expired(&mut self) -> Result<usize> {
+        let (field, ttl) = match self.document_ttl {
+            Some(document_ttl) => document_ttl,
+            None => return Ok(0),
+        };
+
+        let cutoff = Utc::now() - chrono::Duration::from_std(ttl)?;
+        let query = RangeQuery::new_term_bounds(
+            field,
+            Type::Date,
+            &Bound::Unbounded,
+            &Bound::Excluded(Term::from_field_date(field, &cutoff)),
+        );
+
+        let searcher = self.reader.get_searcher();
+        let weight = query.weight(&searcher, false)?;
+
+        let mut expired = vec![];
+        for segment_reader in searcher.segment_readers() {
+            let ids = segment_reader.fast_fields().u64(self.pk_field)?;
+            let mut scorer = weight.scorer(segment_reader, 1.0)?;
+
+            let mut doc = scorer.doc();
+            while doc != TERMINATED {
+                if !segment_reader.is_deleted(doc) {
+                    expired.push(ids.get(doc));
+                }
+
+                doc = scorer.advance();
+            }
+        }
+
+        for id in expired.iter() {
+            self.handle_remove_doc(*id);
+        }
+
+        Ok(expired.len())
     }
 
     #[instrument(name = "writer-op-handler", level = "trace", skip_all)]
@@ -327,22 +822,37 @@ impl IndexWriterWorker {
                 return Ok(());
             },
             WriterOp::__Ping => return Ok(()),
-            WriterOp::Commit => (self.commit()?, "COMMIT"),
-            WriterOp::Rollback => (self.writer.rollback()?, "ROLLBACK"),
+            WriterOp::Commit => unreachable!("commits are resolved by handle_message"),
+            WriterOp::Rollback => {
+                self.pending_dedup_terms.clear();
+                self.clear_uncommitted_documents();
+                (self.writer.rollback()?, "ROLLBACK")
+            },
             WriterOp::AddDocument(document) => {
-                (self.handle_add_document(document)?, "ADD-DOCUMENT")
+                match self.handle_add_document(document)? {
+                    Some(transaction_id) => (transaction_id, "ADD-DOCUMENT"),
+                    None => {
+                        debug!("skipped adding duplicate document");
+                        return Ok(());
+                    },
+                }
             },
             WriterOp::AddManyDocuments(documents) => {
                 for document in documents {
-                    let transaction_id = self.handle_add_document(document)?;
-                    debug!(
-                        "[ TRANSACTION {} ] completed operation ADD-DOCUMENT",
-                        transaction_id
-                    );
+                    match self.handle_add_document(document)? {
+                        Some(transaction_id) => debug!(
+                            "[ TRANSACTION {} ] completed operation ADD-DOCUMENT",
+                            transaction_id
+                        ),
+                        None => debug!("skipped adding duplicate document"),
+                    }
                 }
 
                 return Ok(());
             },
+            WriterOp::UpdateDocument(id, fields) => {
+                (self.handle_update_document(id, fields)?, "UPDATE-DOCUMENT")
+            },
             WriterOp::DeleteManyDocuments(document_ids) => {
                 for id in document_ids {
                     let transaction_id = self.handle_remove_doc(id);
@@ -354,32 +864,66 @@ impl IndexWriterWorker {
 
                 return Ok(());
             },
-            WriterOp::DeleteAll => (self.writer.delete_all_documents()?, "DELETE-ALL"),
+            WriterOp::DeleteAll => {
+                self.pending_dedup_terms.clear();
+                self.clear_uncommitted_documents();
+                self.deleted_all = true;
+                (self.writer.delete_all_documents()?, "DELETE-ALL")
+            },
+            WriterOp::DeleteExpired => {
+                let deleted = self.delete_expired()?;
+                if deleted == 0 {
+                    return Ok(());
+                }
+
+                // The commit includes the changes of any commits waiting to
+                // be coalesced or retried, so they are resolved by it.
+                info!(deleted = deleted, "deleting expired documents");
+                self.commit_pending();
+                return Ok(());
+            },
             WriterOp::AddStopWords(words) => {
                 self.stop_words.add_stop_words(words);
                 self.stop_words.commit()?;
+                self.reader.clear_filter_cache();
                 return Ok(());
             },
             WriterOp::RemoveStopWords(words) => {
                 self.stop_words.remove_stop_words(words);
                 self.stop_words.commit()?;
+                self.reader.clear_filter_cache();
                 return Ok(());
             },
             WriterOp::ClearStopWords => {
                 self.stop_words.clear_stop_words();
                 self.stop_words.commit()?;
+                self.reader.clear_filter_cache();
+                return Ok(());
+            },
+            WriterOp::ReplaceStopWords(words) => {
+                self.stop_words.replace_stop_words(words);
+                self.stop_words.commit()?;
+                self.reader.clear_filter_cache();
                 return Ok(());
             },
             WriterOp::RemoveSynonyms(relations) => {
                 self.synonyms.remove_many_word_synonyms(&relations)?;
+                self.reader.clear_filter_cache();
                 return Ok(());
             },
             WriterOp::AddSynonyms(words) => {
                 self.synonyms.parse_many_synonyms(&words)?;
+                self.reader.clear_filter_cache();
                 return Ok(());
             },
             WriterOp::ClearSynonyms => {
                 self.synonyms.clear_all()?;
+                self.reader.clear_filter_cache();
+                return Ok(());
+            },
+            WriterOp::ReplaceSynonyms(relations) => {
+                self.synonyms.replace_all(&relations)?;
+                self.reader.clear_filter_cache();
                 return Ok(());
             },
         };
@@ -392,16 +936,97 @@ impl IndexWriterWorker {
         Ok(())
     }
 
+    /// Commits the index.
+    ///
+    /// Failed commits sent by users are retried by `commit_pending`.
     fn commit(&mut self) -> Result<Opstamp> {
         let op = self.writer.commit()?;
 
+        self.merge_pending = true;
+        self.clear_uncommitted_documents();
+        self.reader_outdated = true;
+
+        // Skipping duplicates looks up the committed documents, so the
+        // reader must see this commit before the pending terms are dropped.
+        if !self.pending_dedup_terms.is_empty() {
+            self.reader.force_reload()?;
+            self.pending_dedup_terms.clear();
+        }
+
         if self.using_fast_fuzzy {
             self.calculate_frequency_dictionary()?;
         }
 
+        // The reader sees the commit straight away while the warm up runs on
+        // it's own thread, a warm up already waiting covers this commit too.
+        if let Some(ref warmer) = self.warmer {
+            self.reader.force_reload()?;
+            let _ = warmer.try_send(());
+        }
+
         Ok(op)
     }
 
+    /// Merges the smallest committed segments together so the index has at
+    /// most `target` segments.
+    ///
+    /// The merge is left to finish in the background, the reader is reloaded
+    /// once it completes. Failing to merge is not fatal, the segments are
+    /// left as they are until the next attempt.
+    #[instrument(name = "auto-merge", level = "info", skip(self))]
+    fn merge_segments(&mut self, target: usize) {
+        let mut segments = match self.writer.index().searchable_segment_metas() {
+            Ok(segments) => segments,
+            Err(e) => {
+                warn!("failed to get segments to merge: {}", e);
+                return;
+            },
+        };
+
+        if segments.len() <= target {
+            return;
+        }
+
+        segments.sort_by_key(|segment| segment.num_docs());
+        let segment_ids: Vec<SegmentId> = segments
+            .iter()
+            .take(segments.len() - target + 1)
+            .map(|segment| segment.id())
+            .collect();
+
+        info!(
+            "merging {} of {} segments down to {}",
+            segment_ids.len(),
+            segments.len(),
+            target,
+        );
+
+        // The merge runs on tantivy's merge threads, the writer carries on
+        // handling operations rather than waiting for it to finish.
+        let merge = self.writer.merge(&segment_ids);
+        let reader = self.reader.clone();
+        let index_name = self.index_name.clone();
+        let res = std::thread::Builder::new()
+            .name(format!("auto-merge-{}", index_name))
+            .spawn(move || {
+                if let Err(e) = merge.wait() {
+                    warn!(index = %index_name, "failed to merge segments: {}", e);
+                    return;
+                }
+
+                if let Err(e) = reader.force_reload() {
+                    warn!(
+                        index = %index_name,
+                        "failed to reload reader after merging segments: {}", e
+                    );
+                }
+            });
+
+        if let Err(e) = res {
+            warn!("failed to watch the segment merge: {}", e);
+        }
+    }
+
     #[instrument(name = "fast-fuzzy-frequencies", level = "info", skip_all)]
     fn calculate_frequency_dictionary(&mut self) -> Result<()> {
         info!("generating frequency dictionary from committed documents...");
@@ -433,6 +1058,15 @@ impl IndexWriterWorker {
             }
         }
 
+        let num_docs = searcher.num_docs();
+        if map.is_empty() && num_docs > 0 {
+            warn!(
+                "generated an empty frequency dictionary while the index has {} documents, \
+                 check the fuzzy search fields are indexed text fields",
+                num_docs,
+            );
+        }
+
         self.corrections.adjust_index_frequencies(&map);
 
         info!(
@@ -461,6 +1095,10 @@ fn start_writer(
     schema: Schema,
     schema_ctx: SchemaContext,
     auto_commit: usize,
+    commit_retries: usize,
+    commit_retry_backoff: u64,
+    commit_coalesce_window: u64,
+    auto_merge: Option<AutoMerge>,
     using_fast_fuzzy: bool,
     fuzzy_fields: Vec<Field>,
     writer: IndexWriter,
@@ -475,11 +1113,52 @@ fn start_writer(
         .get_field(PRIMARY_KEY)
         .ok_or_else(|| anyhow!("No primary key field in schema. This is a bug."))?;
 
+    let dedup_field = match schema_ctx.dedup_field() {
+        Some(name) => Some(
+            schema
+                .get_field(name)
+                .ok_or_else(|| anyhow!("no field exists with name: {:?}", name))?,
+        ),
+        None => None,
+    };
+
+    let document_ttl = match schema_ctx.document_ttl() {
+        Some((name, ttl)) => Some((
+            schema
+                .get_field(name)
+                .ok_or_else(|| anyhow!("no field exists with name: {:?}", name))?,
+            ttl,
+        )),
+        None => None,
+    };
+
+    let warmer = if reader.warm_on_reload() {
+        Some(start_warmer(&name, reader.clone())?)
+    } else {
+        None
+    };
+
     let mut worker = IndexWriterWorker {
         reader,
         pk_field,
+        dedup_field,
+        pending_dedup_terms: HashSet::new(),
+        uncommitted_documents: HashMap::new(),
+        deleted_all: false,
+        reader_outdated: false,
+        document_ttl,
         index_name: name,
         auto_commit: auto_commit as u64,
+        commit_retries,
+        commit_retry_backoff: Duration::from_millis(commit_retry_backoff),
+        commit_coalesce_window: Duration::from_millis(commit_coalesce_window),
+        pending_commits: vec![],
+        commit_deadline: None,
+        commit_attempt: 0,
+        auto_merge,
+        merge_pending: true,
+        last_op_at: Instant::now(),
+        warmer,
         waiters,
         using_fast_fuzzy,
         fuzzy_fields,
@@ -502,15 +1181,102 @@ fn start_writer(
     Ok(())
 }
 
+/// The maximum amount of time between checks for expired documents.
+const MAX_EXPIRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Starts a thread periodically telling the writer to delete any expired
+/// documents.
+///
+/// The thread stops once the writer has shutdown.
+fn start_expiry_reaper(name: &str, ttl: Duration, op_sender: OpSender) -> Result<()> {
+    let interval = ttl.min(MAX_EXPIRY_INTERVAL);
+
+    std::thread::Builder::new()
+        .name(format!("{}-expiry-reaper", name))
+        .spawn(move || loop {
+            std::thread::sleep(interval);
+
+            if op_sender.send((WriterOp::DeleteExpired, None)).is_err() {
+                break;
+            }
+        })
+        .map_err(|_e| {
+            Error::msg(format!(
+                "failed to spawn expiry reaper thread for index {}",
+                name
+            ))
+        })?;
+
+    Ok(())
+}
+
+/// Starts a thread warming up the reader each time it is told to.
+///
+/// The thread stops once the writer has shutdown.
+fn start_warmer(
+    name: &str,
+    reader: crate::reader::Reader,
+) -> Result<channel::Sender<()>> {
+    let (tx, rx) = channel::bounded::<()>(1);
+
+    std::thread::Builder::new()
+        .name(format!("{}-warmer", name))
+        .spawn(move || {
+            while rx.recv().is_ok() {
+                if let Err(e) = reader.warm_up() {
+                    warn!("failed to warm up index after commit: {}", e);
+                }
+            }
+        })
+        .map_err(|_e| {
+            Error::msg(format!("failed to spawn warmer thread for index {}", name))
+        })?;
+
+    Ok(tx)
+}
+
+/// Gets the term matching the given dedup field value.
+fn dedup_term(field: Field, value: &Value) -> Option<Term> {
+    match value {
+        Value::Str(v) => Some(Term::from_field_text(field, v)),
+        Value::U64(v) => Some(Term::from_field_u64(field, *v)),
+        Value::I64(v) => Some(Term::from_field_i64(field, *v)),
+        _ => None,
+    }
+}
+
 /// A simple wrapper handler around a set of queues and a worker.
 ///
 /// This manages creating the waiters and scheduling the operations
 /// in a new thread.
 pub(crate) struct Writer {
     index_name: String,
+    storage_dir: Option<PathBuf>,
     op_sender: OpSender,
     shutdown_waiter: ShutdownReceiver,
     writer_waiters: WaitersQueue,
+
+    /// The amount of operations currently waiting for space in the queue.
+    ///
+    /// Waiters which timed out stay in `writer_waiters` until the worker
+    /// next wakes them, so they are counted separately.
+    blocked_operations: AtomicUsize,
+}
+
+/// Counts an operation as blocked until the guard is dropped.
+struct BlockedGuard<'a>(&'a AtomicUsize);
+
+impl<'a> BlockedGuard<'a> {
+    fn new(blocked: &'a AtomicUsize) -> Self {
+        blocked.fetch_add(1, Ordering::Relaxed);
+        Self(blocked)
+    }
+}
+
+impl<'a> Drop for BlockedGuard<'a> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
 impl Writer {
@@ -554,6 +1320,10 @@ impl Writer {
             let using_fast_fuzzy = ctx.query_ctx.use_fast_fuzzy;
             let fuzzy_fields = ctx.fuzzy_search_fields().clone();
             let auto_commit = ctx.writer_ctx.auto_commit;
+            let commit_retries = ctx.writer_ctx.commit_retries;
+            let commit_retry_backoff = ctx.writer_ctx.commit_retry_backoff;
+            let commit_coalesce_window = ctx.writer_ctx.commit_coalesce_window;
+            let auto_merge = ctx.writer_ctx.auto_merge;
 
             move || {
                 start_writer(
@@ -566,6 +1336,10 @@ impl Writer {
                     schema,
                     schema_ctx,
                     auto_commit,
+                    commit_retries,
+                    commit_retry_backoff,
+                    commit_coalesce_window,
+                    auto_merge,
                     using_fast_fuzzy,
                     fuzzy_fields,
                     writer,
@@ -593,11 +1367,17 @@ impl Writer {
             info!("worker is okay, startup successful!");
         }
 
+        if let Some((_, ttl)) = ctx.schema_ctx.document_ttl() {
+            start_expiry_reaper(&ctx.name, ttl, op_sender.clone())?;
+        }
+
         Ok(Self {
             index_name,
+            storage_dir: ctx.storage_dir.clone(),
             op_sender,
             shutdown_waiter,
             writer_waiters: waiters,
+            blocked_operations: AtomicUsize::new(0),
         })
     }
 
@@ -605,8 +1385,24 @@ impl Writer {
     ///
     /// If there is space in the queue this will complete immediately
     /// otherwise this will wait until it's woken up again.
-    #[instrument(name = "writer-message-emitter", skip(self), fields(index = %self.index_name))]
     pub(crate) async fn send_op(&self, op: WriterOp) -> anyhow::Result<()> {
+        self.send_op_with_timeout(op, None).await
+    }
+
+    /// Sends a message to the writer worker
+    ///
+    /// If there is space in the queue this will complete immediately
+    /// otherwise this will wait until it's woken up again.
+    ///
+    /// If a `block_timeout` is given and the queue remains full for longer
+    /// than the timeout, a `WriterQueueFull` error is returned instead.
+    #[instrument(name = "writer-message-emitter", skip(self), fields(index = %self.index_name))]
+    pub(crate) async fn send_op_with_timeout(
+        &self,
+        op: WriterOp,
+        block_timeout: Option<Duration>,
+    ) -> anyhow::Result<()> {
+        let deadline = block_timeout.map(|timeout| Instant::now() + timeout);
         let (waker, waker_waiter) = oneshot::channel();
         let mut payload: OpPayload = (op, Some(waker));
         loop {
@@ -624,7 +1420,15 @@ impl Writer {
 
             let (resolve, waiter) = oneshot::channel();
             self.writer_waiters.push(resolve);
-            let _ = waiter.await;
+
+            let _blocked = BlockedGuard::new(&self.blocked_operations);
+            if let Some(deadline) = deadline {
+                if tokio::time::timeout_at(deadline, waiter).await.is_err() {
+                    return Err(Error::new(WriterQueueFull));
+                }
+            } else {
+                let _ = waiter.await;
+            }
         }
 
         waker_waiter.await??;
@@ -632,6 +1436,19 @@ impl Writer {
         Ok(())
     }
 
+    /// The amount of operations waiting in the queue to be applied.
+    ///
+    /// This does not include any operations blocked waiting for space in
+    /// the queue.
+    pub(crate) fn queued_operations(&self) -> usize {
+        self.op_sender.len()
+    }
+
+    /// The amount of operations blocked waiting for space in the queue.
+    pub(crate) fn blocked_operations(&self) -> usize {
+        self.blocked_operations.load(Ordering::Relaxed)
+    }
+
     #[instrument(name = "writer-shutdown", skip(self), fields(index = %self.index_name))]
     pub(crate) async fn shutdown(&self) -> anyhow::Result<()> {
         self.send_op(WriterOp::__Shutdown).await?;
@@ -645,14 +1462,10 @@ impl Writer {
     pub(crate) async fn destroy(&self) -> anyhow::Result<()> {
         self.shutdown().await?;
 
-        let dir = format!(
-            "{}/{}/{}",
-            ROOT_PATH,
-            INDEX_STORAGE_SUB_PATH,
-            cr32_hash(&self.index_name)
-        );
-        if Path::new(&dir).exists() {
-            tokio::fs::remove_dir_all(dir).await?;
+        if let Some(ref dir) = self.storage_dir {
+            if dir.exists() {
+                tokio::fs::remove_dir_all(dir).await?;
+            }
         }
 
         Ok(())
@@ -0,0 +1,22 @@
+use std::process::Command;
+
+fn main() {
+    // Tell Cargo to rerun this build script when the checked out commit changes.
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs");
+
+    let git_hash = Command::new("git")
+        .args(&["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=LNX_GIT_HASH={}", git_hash);
+    println!(
+        "cargo:rustc-env=LNX_BUILD_TIMESTAMP={}",
+        chrono::Utc::now().to_rfc3339()
+    );
+}
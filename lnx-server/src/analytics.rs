@@ -0,0 +1,107 @@
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use engine::DocumentId;
+use serde::Serialize;
+
+/// The amount of events buffered before any new events are dropped.
+const EVENT_BUFFER_SIZE: usize = 4096;
+
+/// A single event recorded by the search analytics.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum AnalyticsEvent {
+    /// A search made against an index.
+    Search {
+        index: String,
+        request_id: String,
+        timestamp: DateTime<Utc>,
+
+        /// The query of the search payload as it was given.
+        query: serde_json::Value,
+        mode: &'static str,
+        count: usize,
+        latency_ms: f64,
+    },
+
+    /// A hit of a past search which was selected by a user.
+    Feedback {
+        index: String,
+
+        /// The id of the search request the hit was returned by.
+        request_id: String,
+        timestamp: DateTime<Utc>,
+        document_id: DocumentId,
+
+        /// The position of the hit within the search results, starting at 0.
+        position: usize,
+    },
+}
+
+/// Records search analytics events to a log file as NDJSON.
+///
+/// Events are written by a background thread so recording never blocks a
+/// request, if the writer falls behind new events are dropped.
+#[derive(Clone)]
+pub struct AnalyticsSink {
+    events: SyncSender<AnalyticsEvent>,
+}
+
+impl AnalyticsSink {
+    /// Opens the analytics log at the given path, appending to it if it
+    /// already exists.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| {
+                anyhow!("failed to open search analytics log {:?}: {}", path, e)
+            })?;
+
+        let (events, rx) = mpsc::sync_channel(EVENT_BUFFER_SIZE);
+        std::thread::Builder::new()
+            .name("search-analytics".to_string())
+            .spawn(move || write_events(BufWriter::new(file), rx))?;
+
+        Ok(Self { events })
+    }
+
+    /// Records the given event.
+    pub fn record(&self, event: AnalyticsEvent) {
+        match self.events.try_send(event) {
+            Ok(()) => {},
+            Err(TrySendError::Full(_)) => {
+                warn!("search analytics log is falling behind, dropping event");
+            },
+            Err(TrySendError::Disconnected(_)) => {
+                error!("search analytics writer has stopped, dropping event");
+            },
+        }
+    }
+}
+
+/// Writes each received event as a line of JSON, flushing once no more
+/// events are pending.
+fn write_events<W: Write>(mut writer: W, rx: Receiver<AnalyticsEvent>) {
+    while let Ok(event) = rx.recv() {
+        let mut pending = Some(event);
+        while let Some(event) = pending {
+            if let Err(e) = serde_json::to_writer(&mut writer, &event) {
+                error!("failed to write search analytics event: {:?}", e);
+            } else if let Err(e) = writer.write_all(b"\n") {
+                error!("failed to write search analytics event: {:?}", e);
+            }
+
+            pending = rx.try_recv().ok();
+        }
+
+        if let Err(e) = writer.flush() {
+            error!("failed to flush search analytics log: {:?}", e);
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+
 use anyhow::Context;
 use bincode::Options;
 use hyper::{Body, Request, Response};
@@ -57,6 +59,58 @@ macro_rules! get_or_400 {
     }};
 }
 
+/// Gets the percent decoded value of the given query parameter from the
+/// request uri.
+///
+/// Parameters given without a value (e.g. `?purge`) resolve to an empty string.
+pub fn get_query_param<'a>(req: &'a LnxRequest, key: &str) -> Option<Cow<'a, str>> {
+    req.uri().query()?.split('&').find_map(|pair| {
+        let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+
+        if percent_decode(k) == key {
+            Some(percent_decode(v))
+        } else {
+            None
+        }
+    })
+}
+
+/// Decodes a percent encoded query string component, `+` is decoded as a
+/// space.
+///
+/// Invalid escape sequences are left as they are.
+fn percent_decode(raw: &str) -> Cow<str> {
+    if !raw.contains(|c| c == '%' || c == '+') {
+        return Cow::Borrowed(raw);
+    }
+
+    let hex = |b: u8| (b as char).to_digit(16).map(|v| v as u8);
+
+    let bytes = raw.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                    (Some(high), Some(low)) => {
+                        decoded.push(high << 4 | low);
+                        i += 3;
+                        continue;
+                    },
+                    _ => decoded.push(b'%'),
+                }
+            },
+            b => decoded.push(b),
+        }
+
+        i += 1;
+    }
+
+    Cow::Owned(String::from_utf8_lossy(&decoded).into_owned())
+}
+
 #[inline]
 pub async fn atomic_store<T: Serialize + Sync + Send + 'static + Sized>(
     db: sled::Db,
@@ -80,3 +134,18 @@ pub async fn atomic_store<T: Serialize + Sync + Send + 'static + Sized>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_decode_expect_ok() {
+        assert_eq!(percent_decode("hello"), "hello");
+        assert_eq!(percent_decode("hello%20world"), "hello world");
+        assert_eq!(percent_decode("hello+world"), "hello world");
+        assert_eq!(percent_decode("caf%C3%A9"), "café");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%2"), "%zz%2");
+    }
+}
@@ -0,0 +1,388 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Error, Result};
+use engine::structures::IndexDeclaration;
+use serde_json::{json, Map, Value};
+
+/// The type of field inferred from a set of document values.
+///
+/// Types are ordered so conflicting values widen to the later type,
+/// integers become floats and any value mixed with text becomes text.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum InferredType {
+    I64,
+    F64,
+    Text,
+}
+
+impl InferredType {
+    fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(_) => Some(Self::Text),
+            Value::Number(n) if n.is_f64() => Some(Self::F64),
+            Value::Number(_) => Some(Self::I64),
+            _ => None,
+        }
+    }
+
+    /// The inferred type of an existing field declaration.
+    ///
+    /// Only the types which can be inferred are returned, fields declared
+    /// with any other type are never widened.
+    fn of_declaration(declaration: &Value) -> Option<Self> {
+        match declaration.get("type")?.as_str()? {
+            "i64" => Some(Self::I64),
+            "f64" => Some(Self::F64),
+            "text" => Some(Self::Text),
+            _ => None,
+        }
+    }
+
+    fn as_declaration(&self, multi: bool) -> Value {
+        match self {
+            Self::I64 => json!({"type": "i64", "indexed": true, "multi": multi}),
+            Self::F64 => json!({"type": "f64", "indexed": true, "multi": multi}),
+            Self::Text => json!({"type": "text", "multi": multi}),
+        }
+    }
+}
+
+/// Infers the field declarations of the given documents.
+///
+/// The documents are either a single JSON object or an array of objects,
+/// fields given as an array are multi-value. Fields whose values cannot be
+/// inferred, e.g. `null`s and empty arrays, are left out.
+fn infer_fields(documents: &Value) -> BTreeMap<String, Value> {
+    let documents = match documents {
+        Value::Array(documents) => documents.iter().collect(),
+        document => vec![document],
+    };
+
+    let mut inferred: BTreeMap<&str, (InferredType, bool)> = BTreeMap::new();
+    for document in documents {
+        let document = match document.as_object() {
+            Some(document) => document,
+            None => continue,
+        };
+
+        for (name, value) in document {
+            let (kind, multi) = match value {
+                Value::Array(values) => {
+                    let kind = values.iter().filter_map(InferredType::of).max();
+                    (kind, true)
+                },
+                value => (InferredType::of(value), false),
+            };
+
+            let kind = match kind {
+                Some(kind) => kind,
+                None => continue,
+            };
+
+            let entry = inferred.entry(name).or_insert((kind, multi));
+            entry.0 = entry.0.max(kind);
+            entry.1 |= multi;
+        }
+    }
+
+    inferred
+        .into_iter()
+        .map(|(name, (kind, multi))| (name.to_string(), kind.as_declaration(multi)))
+        .collect()
+}
+
+/// Creates the declaration of a new index with a schema inferred from
+/// the given documents.
+///
+/// The index infers the fields of any documents added later on.
+pub fn infer_declaration(name: &str, documents: &Value) -> Result<IndexDeclaration> {
+    let fields = infer_fields(documents);
+    if fields.is_empty() {
+        return Err(Error::msg(
+            "cannot infer an index schema from documents without any fields.",
+        ));
+    }
+
+    let declaration = serde_json::from_value(json!({
+        "name": name,
+        "max_concurrency": 1,
+        "fields": fields,
+        "infer_new_fields": true,
+    }))?;
+
+    Ok(declaration)
+}
+
+/// Checks if the documents change the schema of the index with the
+/// given declaration, or require the index to be created.
+///
+/// This lets documents which fit an existing schema skip inference.
+pub fn needs_inference(
+    declaration: Option<&IndexDeclaration>,
+    documents: &Value,
+) -> Result<bool> {
+    match declaration {
+        None => Ok(true),
+        Some(declaration) if declaration.infer_new_fields() => {
+            Ok(extend_declaration(declaration, documents)?.is_some())
+        },
+        Some(_) => Ok(false),
+    }
+}
+
+/// Creates a copy of the declaration with the fields of the documents
+/// which are not already in the schema added.
+///
+/// Existing inferred fields are widened when the documents contain wider
+/// values, e.g. an `i64` field given a float becomes an `f64` field.
+///
+/// Returns `None` if the schema already fits every field.
+pub fn extend_declaration(
+    declaration: &IndexDeclaration,
+    documents: &Value,
+) -> Result<Option<IndexDeclaration>> {
+    let mut value = serde_json::to_value(declaration)?;
+    let fields = match value.get_mut("fields").and_then(Value::as_object_mut) {
+        Some(fields) => fields,
+        None => return Ok(None),
+    };
+
+    let mut changed = Map::new();
+    for (name, inferred) in infer_fields(documents) {
+        let existing = match fields.get(&name) {
+            None => {
+                changed.insert(name, inferred);
+                continue;
+            },
+            Some(existing) => existing,
+        };
+
+        let (kind, new_kind) = match (
+            InferredType::of_declaration(existing),
+            InferredType::of_declaration(&inferred),
+        ) {
+            (Some(kind), Some(new_kind)) => (kind, new_kind),
+            _ => continue,
+        };
+
+        let is_multi =
+            |field: &Value| field.get("multi").and_then(Value::as_bool).unwrap_or(false);
+        let multi = is_multi(existing);
+        let new_multi = multi | is_multi(&inferred);
+
+        if new_kind > kind || new_multi != multi {
+            changed.insert(name, kind.max(new_kind).as_declaration(new_multi));
+        }
+    }
+
+    if changed.is_empty() {
+        return Ok(None);
+    }
+
+    fields.extend(changed);
+
+    Ok(Some(serde_json::from_value(value)?))
+}
+
+/// The largest integer every smaller integer can be represented exactly
+/// as a `f64` up to.
+const MAX_EXACT_F64_INTEGER: u64 = 1 << 53;
+
+/// Converts the integer values of the documents' inferred `f64` fields into
+/// floats so they match the widened schema.
+///
+/// Integers which cannot be represented exactly as a `f64` are rejected
+/// rather than silently losing precision.
+pub fn widen_documents(
+    declaration: &IndexDeclaration,
+    documents: &mut Value,
+) -> Result<()> {
+    if !declaration.infer_new_fields() {
+        return Ok(());
+    }
+
+    let declared = serde_json::to_value(declaration)?;
+    let fields = match declared.get("fields").and_then(Value::as_object) {
+        Some(fields) => fields,
+        None => return Ok(()),
+    };
+
+    let documents: Vec<&mut Value> = match documents {
+        Value::Array(documents) => documents.iter_mut().collect(),
+        document => vec![document],
+    };
+
+    for document in documents {
+        let document = match document.as_object_mut() {
+            Some(document) => document,
+            None => continue,
+        };
+
+        for (name, data) in document.iter_mut() {
+            let is_float = fields
+                .get(name)
+                .and_then(InferredType::of_declaration)
+                .map(|kind| kind == InferredType::F64)
+                .unwrap_or_default();
+
+            if !is_float {
+                continue;
+            }
+
+            match data {
+                Value::Array(values) => {
+                    for value in values.iter_mut() {
+                        widen_integer(name, value)?;
+                    }
+                },
+                value => widen_integer(name, value)?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts the value into a float if it is an integer.
+fn widen_integer(field: &str, value: &mut Value) -> Result<()> {
+    let magnitude = match value {
+        Value::Number(n) if n.is_u64() => n.as_u64(),
+        Value::Number(n) if n.is_i64() => n.as_i64().map(i64::unsigned_abs),
+        _ => None,
+    };
+
+    let magnitude = match magnitude {
+        Some(magnitude) => magnitude,
+        None => return Ok(()),
+    };
+
+    if magnitude > MAX_EXACT_F64_INTEGER {
+        return Err(anyhow!(
+            "field {:?} contains the integer {} which cannot be stored exactly as a f64 value",
+            field,
+            value,
+        ));
+    }
+
+    let float = value.as_f64().expect("integers convert to f64");
+    *value = json!(float);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field_type(declaration: &IndexDeclaration, field: &str) -> Option<String> {
+        let value = serde_json::to_value(declaration).ok()?;
+        value["fields"][field]["type"].as_str().map(String::from)
+    }
+
+    #[test]
+    fn test_infer_declaration_expect_ok() -> Result<()> {
+        let declaration = infer_declaration(
+            "test",
+            &json!([
+                {"title": "Hello", "count": 1, "tags": ["a", "b"]},
+                {"title": "World", "count": 2.5, "missing": null},
+            ]),
+        )?;
+
+        assert!(declaration.infer_new_fields());
+        assert_eq!(field_type(&declaration, "title").as_deref(), Some("text"));
+        assert_eq!(field_type(&declaration, "count").as_deref(), Some("f64"));
+        assert_eq!(field_type(&declaration, "tags").as_deref(), Some("text"));
+        assert_eq!(field_type(&declaration, "missing"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_infer_declaration_expect_err() {
+        let res = infer_declaration("test", &json!({"missing": null}));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_extend_declaration_expect_ok() -> Result<()> {
+        let declaration = infer_declaration("test", &json!({"title": "Hello"}))?;
+
+        let res = extend_declaration(&declaration, &json!({"title": "World"}))?;
+        assert!(res.is_none());
+        assert!(!needs_inference(
+            Some(&declaration),
+            &json!({"title": "World"})
+        )?);
+
+        let documents = json!({"title": "World", "count": 1});
+        assert!(needs_inference(Some(&declaration), &documents)?);
+
+        let extended = extend_declaration(&declaration, &documents)?.expect("extend");
+        assert_eq!(field_type(&extended, "title").as_deref(), Some("text"));
+        assert_eq!(field_type(&extended, "count").as_deref(), Some("i64"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extend_declaration_widens_fields_expect_ok() -> Result<()> {
+        let declaration = infer_declaration("test", &json!({"count": 1, "price": 1.5}))?;
+
+        let extended =
+            extend_declaration(&declaration, &json!({"count": 2.5, "price": 3}))?
+                .expect("extend");
+        assert_eq!(field_type(&extended, "count").as_deref(), Some("f64"));
+        assert_eq!(field_type(&extended, "price").as_deref(), Some("f64"));
+
+        let extended =
+            extend_declaration(&extended, &json!({"price": "free"}))?.expect("extend");
+        assert_eq!(field_type(&extended, "price").as_deref(), Some("text"));
+
+        let extended = extend_declaration(&extended, &json!({"count": [1.0, 2.0]}))?
+            .expect("extend");
+        let value = serde_json::to_value(&extended)?;
+        assert_eq!(value["fields"]["count"]["multi"], json!(true));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_widen_documents_expect_ok() -> Result<()> {
+        let declaration = infer_declaration("test", &json!({"price": 1.5, "count": 1}))?;
+
+        let mut documents = json!([{"price": 3, "count": 2}, {"price": [4, 5.5]}]);
+        widen_documents(&declaration, &mut documents)?;
+
+        assert!(documents[0]["price"].is_f64());
+        assert!(documents[0]["count"].is_u64());
+        assert!(documents[1]["price"][0].is_f64());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_widen_documents_expect_err() -> Result<()> {
+        let declaration = infer_declaration("test", &json!({"price": 1.5}))?;
+
+        let mut documents = json!({"price": (1u64 << 53) + 1});
+        assert!(widen_documents(&declaration, &mut documents).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_needs_inference_expect_ok() -> Result<()> {
+        let documents = json!({"title": "Hello"});
+        assert!(needs_inference(None, &documents)?);
+
+        let declaration: IndexDeclaration = serde_json::from_value(json!({
+            "name": "test",
+            "max_concurrency": 1,
+            "fields": {"title": {"type": "text"}},
+        }))?;
+        assert!(!needs_inference(Some(&declaration), &json!({"count": 1}))?);
+
+        Ok(())
+    }
+}
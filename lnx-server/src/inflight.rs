@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use hashbrown::HashMap;
+use parking_lot::Mutex;
+
+/// Bounds the amount of requests each client can have in flight at once.
+#[derive(Clone)]
+pub struct InflightLimiter {
+    /// The maximum amount of in flight requests per client.
+    max_per_client: usize,
+
+    /// The amount of requests in flight for each client.
+    clients: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl InflightLimiter {
+    pub fn new(max_per_client: usize) -> Self {
+        Self {
+            max_per_client,
+            clients: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Attempts to reserve an in flight slot for the given client.
+    ///
+    /// This returns `None` if the client is already at its limit, otherwise
+    /// the slot is held until the returned permit is dropped.
+    pub fn try_acquire(&self, client: String) -> Option<InflightPermit> {
+        let mut clients = self.clients.lock();
+        let inflight = clients.entry(client.clone()).or_default();
+        if *inflight >= self.max_per_client {
+            return None;
+        }
+
+        *inflight += 1;
+
+        Some(InflightPermit {
+            client,
+            clients: self.clients.clone(),
+        })
+    }
+}
+
+/// A reserved in flight slot of a client, released on drop.
+pub struct InflightPermit {
+    client: String,
+    clients: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl Drop for InflightPermit {
+    fn drop(&mut self) {
+        let mut clients = self.clients.lock();
+        if let Some(inflight) = clients.get_mut(&self.client) {
+            *inflight -= 1;
+
+            // Clients are removed once idle so the map does not grow forever.
+            if *inflight == 0 {
+                clients.remove(&self.client);
+            }
+        }
+    }
+}
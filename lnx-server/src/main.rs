@@ -1,6 +1,9 @@
+mod analytics;
 mod auth;
 mod error;
 mod helpers;
+mod inference;
+mod inflight;
 mod responders;
 mod routes;
 mod snapshot;
@@ -16,20 +19,26 @@ use std::time::Duration;
 use anyhow::{anyhow, Context, Result};
 use bincode::Options;
 use clap::Parser;
-use engine::structures::{IndexDeclaration, ROOT_PATH};
+use engine::structures::{IndexDeclaration, StorageType, ROOT_PATH};
 use engine::Engine;
 use hyper::Server;
 use mimalloc::MiMalloc;
+use opentelemetry::{sdk, KeyValue};
 use routerify::RouterService;
-use tracing::Level;
+use tracing::{Level, Subscriber};
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::filter::EnvFilter;
 use tracing_subscriber::fmt::writer::MakeWriterExt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
 
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 
+use crate::analytics::AnalyticsSink;
 use crate::auth::AuthManager;
+use crate::inflight::InflightLimiter;
 use crate::snapshot::{create_snapshot, load_snapshot};
 use crate::state::State;
 
@@ -79,10 +88,25 @@ struct Settings {
     #[clap(long, env)]
     log_directory: Option<String>,
 
+    /// An optional OTLP collector endpoint to export traces to.
+    ///
+    /// If set, spans from the search and write paths are exported via gRPC
+    /// alongside the existing logging. e.g. `http://localhost:4317`
+    #[clap(long, env)]
+    otlp_endpoint: Option<String>,
+
     /// If enabled each search request wont be logged.
     #[clap(long, env)]
     silent_search: bool,
 
+    /// The file search analytics are recorded to as NDJSON.
+    ///
+    /// If set each search's query, mode, result count and latency is
+    /// recorded alongside any result feedback sent to
+    /// `POST /indexes/:index/_feedback`. If not set no analytics are kept.
+    #[clap(long, env)]
+    search_analytics_log: Option<String>,
+
     /// The host to bind to (normally: '127.0.0.1' or '0.0.0.0'.)
     #[clap(long, short, default_value = "127.0.0.1", env)]
     host: String,
@@ -100,6 +124,87 @@ struct Settings {
     #[clap(long, env, hide_env_values = true)]
     super_user_key: Option<String>,
 
+    /// The maximum time in milliseconds an ingest request will wait for space
+    /// in an index's writer queue.
+    ///
+    /// If the queue stays full for longer than this, the request is rejected
+    /// with a `503 Service Unavailable` and a `Retry-After` header rather than
+    /// waiting indefinitely.
+    #[clap(long, env)]
+    ingest_block_timeout: Option<u64>,
+
+    /// Runs the server as a read only node.
+    ///
+    /// Any request modifying an index or the engine is rejected with a
+    /// `403 Forbidden`, searches and document reads are served as normal.
+    /// This allows read replicas to be placed behind a load balancer while
+    /// writes are directed at a primary node.
+    #[clap(long, env)]
+    read_only_node: bool,
+
+    /// Creates indexes which do not exist when documents are added to them.
+    ///
+    /// The index's schema is inferred from the first documents, strings
+    /// become text fields and numbers become i64 or f64 fields. Fields of
+    /// later documents which are not in the schema are added by re-creating
+    /// the index. This is intended for prototyping, any token able to add
+    /// documents can create indexes.
+    #[clap(long, env)]
+    auto_create_indexes: bool,
+
+    /// The maximum amount of requests a single client can have in flight
+    /// at once.
+    ///
+    /// Clients are identified by their access token, or by their IP address
+    /// if authorization is disabled. Requests beyond the limit are rejected
+    /// with a `429 Too Many Requests`. If not set clients are not limited.
+    #[clap(long, env)]
+    max_inflight_per_client: Option<usize>,
+
+    /// The maximum number of indexes the server can have at once.
+    ///
+    /// Creating an index beyond the limit is rejected with a
+    /// `507 Insufficient Storage`, each index runs it's own writer and
+    /// search threads. Existing indexes are always loaded, logging a warning
+    /// if they already exceed the limit. If not set indexes are not limited.
+    #[clap(long, env)]
+    max_indexes: Option<usize>,
+
+    /// The maximum time in seconds to wait for in-flight requests to finish
+    /// when shutting down.
+    ///
+    /// Once a shutdown signal is received no new connections are accepted,
+    /// after the in-flight requests have finished or the timeout has passed
+    /// any pending writes are committed before the engine is shutdown.
+    #[clap(long, default_value = "30", env)]
+    shutdown_timeout: u64,
+
+    /// The storage type used by indexes which do not specify one.
+    ///
+    /// One of `memory`, `tempdir` or `filesystem`, if not set indexes must
+    /// always specify their own storage type.
+    #[clap(long, env)]
+    default_storage_type: Option<StorageType>,
+
+    /// The directory `filesystem` indexes are stored within if they do not
+    /// specify their own `storage_path`.
+    ///
+    /// Defaults to `./index/index-storage`, note that snapshots only contain
+    /// data stored within `./index`.
+    ///
+    /// Indexes remember the path they were created with, those created before
+    /// this option existed keep using `./index/index-storage`.
+    #[clap(long, env)]
+    index_storage_path: Option<String>,
+
+    /// The number of threads to use for a search pool shared across all indexes.
+    ///
+    /// By default each index spawns it's own set of search threads based on it's
+    /// `max_concurrency` and `reader_threads`, if set, all indexes share this
+    /// pool instead while still being limited by their `max_concurrency`.
+    #[clap(long, env)]
+    search_threads: Option<usize>,
+
     /// The number of threads to use for the tokio runtime.
     ///
     /// If this is not set, the number of logical cores on the machine is used.
@@ -145,14 +250,41 @@ fn main() {
         },
     };
 
-    let _guard = setup_logger(
-        settings.log_level,
-        &settings.log_directory,
-        !settings.disable_asni_logs,
-        settings.pretty_logs,
-        settings.json_logs,
-        settings.verbose_logs,
-    );
+    let threads = settings.runtime_threads.unwrap_or_else(num_cpus::get);
+    let maybe_runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(threads)
+        .enable_all()
+        .build();
+
+    let runtime = match maybe_runtime {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("error during runtime creation: {:?}", e);
+            return;
+        },
+    };
+
+    // The OTLP exporter spawns it's background tasks onto the runtime.
+    let maybe_guard = {
+        let _enter = runtime.enter();
+        setup_logger(
+            settings.log_level,
+            &settings.log_directory,
+            !settings.disable_asni_logs,
+            settings.pretty_logs,
+            settings.json_logs,
+            settings.verbose_logs,
+            &settings.otlp_endpoint,
+        )
+    };
+
+    let _guard = match maybe_guard {
+        Ok(guard) => guard,
+        Err(e) => {
+            eprintln!("error during logger setup: {:?}", e);
+            return;
+        },
+    };
 
     if let Some(snapshot) = settings.load_snapshot {
         if let Err(e) = load_snapshot(Path::new(&snapshot)) {
@@ -166,24 +298,16 @@ fn main() {
         return;
     }
 
-    let threads = settings.runtime_threads.unwrap_or_else(num_cpus::get);
-    info!("starting runtime with {} threads", threads);
-    let maybe_runtime = tokio::runtime::Builder::new_multi_thread()
-        .worker_threads(threads)
-        .enable_all()
-        .build();
-
-    let result = match maybe_runtime {
-        Ok(runtime) => runtime.block_on(start(settings)),
-        Err(e) => {
-            error!("error during runtime creation: {:?}", e);
-            return;
-        },
-    };
+    let otlp_enabled = settings.otlp_endpoint.is_some();
 
-    if let Err(e) = result {
+    info!("starting runtime with {} threads", threads);
+    if let Err(e) = runtime.block_on(start(settings)) {
         error!("error during lnx runtime: {:?}", e);
     }
+
+    if otlp_enabled {
+        opentelemetry::global::shutdown_tracer_provider();
+    }
 }
 
 fn setup_logger(
@@ -193,7 +317,8 @@ fn setup_logger(
     pretty: bool,
     json: bool,
     verbose: bool,
-) -> Option<WorkerGuard> {
+    otlp_endpoint: &Option<String>,
+) -> Result<Option<WorkerGuard>> {
     if std::env::var_os("RUST_LOG").is_none() {
         std::env::set_var("RUST_LOG", format!("{},compress=off,tantivy=info", level));
     }
@@ -212,27 +337,65 @@ fn setup_logger(
         let fmt = fmt.with_writer(std::io::stdout.and(non_blocking));
 
         if pretty {
-            fmt.pretty().init();
+            init_subscriber(fmt.pretty().finish(), otlp_endpoint)?;
         } else if json {
-            fmt.json().init();
+            init_subscriber(fmt.json().finish(), otlp_endpoint)?;
         } else {
-            fmt.compact().init();
+            init_subscriber(fmt.compact().finish(), otlp_endpoint)?;
         }
 
-        Some(guard)
+        Ok(Some(guard))
     } else {
         if pretty {
-            fmt.pretty().init();
+            init_subscriber(fmt.pretty().finish(), otlp_endpoint)?;
         } else if json {
-            fmt.json().init();
+            init_subscriber(fmt.json().finish(), otlp_endpoint)?;
         } else {
-            fmt.compact().init();
+            init_subscriber(fmt.compact().finish(), otlp_endpoint)?;
         }
 
-        None
+        Ok(None)
     }
 }
 
+/// Sets the given subscriber as the global default, exporting spans to the
+/// OTLP collector if an endpoint is given.
+fn init_subscriber<S>(subscriber: S, otlp_endpoint: &Option<String>) -> Result<()>
+where
+    S: Subscriber + for<'span> LookupSpan<'span> + Send + Sync + 'static,
+{
+    let otlp = match otlp_endpoint {
+        Some(endpoint) => {
+            let tracer = setup_otlp_tracer(endpoint)?;
+            Some(tracing_opentelemetry::layer().with_tracer(tracer))
+        },
+        None => None,
+    };
+
+    subscriber.with(otlp).init();
+
+    Ok(())
+}
+
+/// Creates a tracer which batch exports spans to the given OTLP endpoint.
+///
+/// This must be called within the context of the tokio runtime.
+fn setup_otlp_tracer(endpoint: &str) -> Result<sdk::trace::Tracer> {
+    let exporter = opentelemetry_otlp::new_exporter()
+        .tonic()
+        .with_endpoint(endpoint);
+
+    let resource = sdk::Resource::new(vec![KeyValue::new("service.name", "lnx")]);
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(exporter)
+        .with_trace_config(sdk::trace::config().with_resource(resource))
+        .install_batch(opentelemetry::runtime::Tokio)?;
+
+    Ok(tracer)
+}
+
 /// Parses the config and sets up logging
 fn setup() -> Result<Settings> {
     let config: Settings = Settings::parse();
@@ -259,12 +422,23 @@ async fn start(settings: Settings) -> Result<()> {
         }
     }
 
+    if let Some(threads) = settings.search_threads {
+        info!("starting shared search pool with {} threads", threads);
+        engine::init_shared_executor_pool(threads)?;
+    }
+
     let state = create_state(&settings).await?;
     let router = routes::get_router(state.clone());
     let service = RouterService::new(router).unwrap();
 
     let address: SocketAddr = format!("{}:{}", &settings.host, settings.port).parse()?;
-    let server = Server::bind(&address).serve(service);
+    let (stop_server, stop_signal) = tokio::sync::oneshot::channel::<()>();
+    let server = Server::bind(&address)
+        .serve(service)
+        .with_graceful_shutdown(async {
+            let _ = stop_signal.await;
+        });
+    tokio::pin!(server);
 
     let (major_minor_version, _) = env!("CARGO_PKG_VERSION")
         .rsplit_once('.')
@@ -282,17 +456,42 @@ async fn start(settings: Settings) -> Result<()> {
         major_minor_version
     );
 
-    tokio::select! {
+    let got_signal = tokio::select! {
         _r = wait_for_signal() => {
             info!("got shutdown signal, preparing shutdown");
+            true
         },
-        r = server => {
+        r = &mut server => {
             if let Err(e) = r {
                 error!("server error: {:?}", e)
             };
+            false
+        }
+    };
+
+    if got_signal {
+        let shutdown_timeout = Duration::from_secs(settings.shutdown_timeout);
+        info!(
+            "waiting up to {:?} for in-flight requests to finish...",
+            shutdown_timeout
+        );
+
+        let _ = stop_server.send(());
+        match tokio::time::timeout(shutdown_timeout, &mut server).await {
+            Ok(Err(e)) => error!("server error: {:?}", e),
+            Err(_) => warn!(
+                "in-flight requests did not finish within {:?}, continuing shutdown",
+                shutdown_timeout
+            ),
+            Ok(Ok(())) => {},
         }
     }
 
+    info!("committing pending writes...");
+    if let Err(e) = state.engine.commit_all().await {
+        error!("failed to commit pending writes: {:?}", e);
+    }
+
     info!("shutting down engine...");
     state.engine.shutdown().await?;
 
@@ -331,17 +530,45 @@ async fn create_state(settings: &Settings) -> Result<State> {
         .open()
         .map_err(|e| anyhow!("failed to open database due to error {}", e))?;
 
-    let engine = load_existing_indexes(&db)
+    let engine = Engine::with_storage_defaults(
+        settings.default_storage_type,
+        settings.index_storage_path.clone(),
+    )
+    .with_max_indexes(settings.max_indexes);
+    load_existing_indexes(&db, &engine)
         .await
         .map_err(|e| anyhow!("failed to load existing indexes due to error {}", e))?;
     let auth = setup_authentication(&db, settings)
         .map_err(|e| anyhow!("failed to load authentication data due to error {}", e))?;
 
-    Ok(State::new(engine, db, auth, !settings.silent_search))
+    let ingest_block_timeout = settings.ingest_block_timeout.map(Duration::from_millis);
+
+    let analytics = match settings.search_analytics_log {
+        Some(ref path) => Some(AnalyticsSink::open(Path::new(path))?),
+        None => None,
+    };
+
+    let inflight = match settings.max_inflight_per_client {
+        Some(0) => return Err(anyhow!("max inflight per client must be at least 1")),
+        Some(max) => Some(InflightLimiter::new(max)),
+        None => None,
+    };
+
+    Ok(State::new(
+        engine,
+        db,
+        auth,
+        !settings.silent_search,
+        ingest_block_timeout,
+        settings.read_only_node,
+        settings.auto_create_indexes,
+        inflight,
+        analytics,
+    ))
 }
 
 #[instrument(name = "setup-existing-indexes", level = "info", skip(db))]
-async fn load_existing_indexes(db: &sled::Db) -> Result<Engine> {
+async fn load_existing_indexes(db: &sled::Db, engine: &Engine) -> Result<()> {
     info!("loading existing indexes...");
 
     let existing_indexes: Vec<IndexDeclaration> =
@@ -361,12 +588,21 @@ async fn load_existing_indexes(db: &sled::Db) -> Result<Engine> {
         existing_indexes.len()
     );
 
-    let engine = Engine::default();
+    if let Some(max) = engine.max_indexes() {
+        if existing_indexes.len() > max {
+            warn!(
+                "{} existing indexes exceeds the maximum of {} indexes, no new indexes can be created",
+                existing_indexes.len(),
+                max,
+            );
+        }
+    }
+
     for index in existing_indexes {
-        engine.add_index(index, true).await?;
+        engine.load_index(index).await?;
     }
 
-    Ok(engine)
+    Ok(())
 }
 
 #[instrument(name = "setup-authentication", level = "info", skip(db, settings))]
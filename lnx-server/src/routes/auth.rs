@@ -1,3 +1,4 @@
+use hyper::Method;
 use routerify::ext::RequestExt;
 use serde::Deserialize;
 
@@ -33,7 +34,8 @@ struct CreateTokenPayload {
 pub(crate) async fn check_permissions(req: LnxRequest) -> Result<LnxRequest> {
     let state = req.data::<State>().expect("get state");
 
-    if !state.auth.enabled() {
+    // The version route is public so deployments can be verified.
+    if !state.auth.enabled() || req.uri().path() == "/version" {
         return Ok(req);
     }
 
@@ -54,11 +56,26 @@ pub(crate) async fn check_permissions(req: LnxRequest) -> Result<LnxRequest> {
     let path = req.uri().path();
     if path.starts_with("/auth") {
         required_permissions = permissions::MODIFY_AUTH;
-    } else if path == "/indexes" {
+    } else if path == "/indexes" || path == "/_reindex" {
         required_permissions = permissions::MODIFY_ENGINE;
     } else if path.starts_with("/indexes") {
-        if path.ends_with("/search") {
+        if path.ends_with("/search")
+            || path.ends_with("/search/stream")
+            || path.ends_with("/_validate_query")
+            || path.ends_with("/_feedback")
+            || path.ends_with("/suggest")
+            || path.ends_with("/spellcheck")
+            || req.method() == Method::HEAD
+        {
             required_permissions = permissions::SEARCH_INDEX;
+        } else if path.ends_with("/config")
+            || path.ends_with("/rename")
+            || path.ends_with("/_clone")
+            || path.ends_with("/boosts")
+            || path.ends_with("/_diagnostics")
+            || path.ends_with("/_ranking")
+        {
+            required_permissions = permissions::MODIFY_ENGINE;
         } else if path.ends_with("/stopwords") {
             required_permissions = permissions::MODIFY_STOP_WORDS;
         } else {
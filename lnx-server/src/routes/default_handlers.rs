@@ -1,4 +1,7 @@
 use anyhow::Result;
+use engine::structures::DocumentValidationError;
+use engine::{IndexConflict, IndexLimitReached, IndexReadOnly, PoolSaturated};
+use hyper::header::{HeaderValue, RETRY_AFTER};
 use hyper::{Body, Request, Response};
 
 use crate::error::LnxError;
@@ -31,6 +34,25 @@ pub async fn handle_casting(err: routerify::RouteError) -> Result<Response<Body>
             json_response(401, msg).map_err(anyhow::Error::from)?
         },
         LnxError::AbortRequest(resp) => resp,
+        LnxError::Other(ref e) if e.is::<IndexReadOnly>() || e.is::<IndexConflict>() => {
+            json_response(409, &e.to_string()).map_err(anyhow::Error::from)?
+        },
+        LnxError::Other(ref e) if e.is::<IndexLimitReached>() => {
+            json_response(507, &e.to_string()).map_err(anyhow::Error::from)?
+        },
+        LnxError::Other(ref e) if e.is::<PoolSaturated>() => {
+            let mut resp =
+                json_response(503, &e.to_string()).map_err(anyhow::Error::from)?;
+            resp.headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from_static("1"));
+            resp
+        },
+        LnxError::Other(ref e) if e.is::<DocumentValidationError>() => {
+            let e = e
+                .downcast_ref::<DocumentValidationError>()
+                .expect("downcast validation error");
+            json_response(422, e).map_err(anyhow::Error::from)?
+        },
         LnxError::Other(ref e) if e.source().is_some() => {
             json_response(500, &format!("error handling request: {}", e))
                 .map_err(anyhow::Error::from)?
@@ -1,16 +1,24 @@
 use engine::structures::IndexDeclaration;
+use hashbrown::HashMap;
+use hyper::{Body, Response, StatusCode};
 use routerify::ext::RequestExt;
 use serde::Deserialize;
 
-use crate::helpers::{atomic_store, LnxRequest, LnxResponse};
+use crate::helpers::{atomic_store, get_query_param, LnxRequest, LnxResponse};
 use crate::responders::json_response;
+use crate::routes::index::ensure_index_access;
 use crate::state::State;
-use crate::{get_or_400, json, INDEX_KEYSPACE};
+use crate::{abort, get_or_400, json, INDEX_KEYSPACE};
 
 #[derive(Deserialize)]
 struct IndexCreationPayload {
     #[serde(default)]
     override_if_exists: bool,
+
+    /// Re-creating an existing index with an identical declaration succeeds
+    /// without changing anything, a differing declaration is rejected.
+    #[serde(default)]
+    create_if_not_exists: bool,
     index: IndexDeclaration,
 }
 
@@ -21,10 +29,24 @@ pub async fn create_index(mut req: LnxRequest) -> LnxResponse {
     // In case we need to remove the index due to failed persistence.
     let name = payload.index.name().to_string();
 
-    state
-        .engine
-        .add_index(payload.index, payload.override_if_exists)
-        .await?;
+    if payload.create_if_not_exists {
+        if payload.override_if_exists {
+            return abort!(
+                400,
+                "override_if_exists and create_if_not_exists cannot both be set"
+            );
+        }
+
+        let created = state.engine.add_index_if_not_exists(payload.index).await?;
+        if !created {
+            return json_response(200, "index already exists.");
+        }
+    } else {
+        state
+            .engine
+            .add_index(payload.index, payload.override_if_exists)
+            .await?;
+    }
 
     let indexes = state.engine.get_all_indexes();
     let storage = state.storage.clone();
@@ -42,9 +64,15 @@ pub async fn create_index(mut req: LnxRequest) -> LnxResponse {
     json_response(200, "index created.")
 }
 
+/// Removes the index from the engine.
+///
+/// By default this only detaches the index, leaving its data on disk so it
+/// can be recovered by re-creating the index with the same name.
+/// If `?purge=true` is given the index's persisted data is removed as well.
 pub async fn delete_index(req: LnxRequest) -> LnxResponse {
     let state = req.data::<State>().expect("get state");
     let index = get_or_400!(req.param("index"));
+    let purge = get_query_param(&req, "purge").as_deref() == Some("true");
 
     let indexes: Vec<IndexDeclaration> = state
         .engine
@@ -60,7 +88,173 @@ pub async fn delete_index(req: LnxRequest) -> LnxResponse {
     let buffer = serde_json::to_vec(&indexes)?;
     atomic_store(storage, INDEX_KEYSPACE, buffer).await?;
 
-    state.engine.remove_index(index).await?;
+    if purge {
+        state.engine.remove_index(index).await?;
+        json_response(200, "index deleted")
+    } else {
+        state.engine.detach_index(index).await?;
+        json_response(200, "index detached")
+    }
+}
+
+#[derive(Deserialize)]
+struct ReindexPayload {
+    /// The index documents are copied from.
+    source: String,
+
+    /// The index documents are copied into.
+    dest: String,
+
+    /// Source field names mapped to the name they are given in the
+    /// destination index.
+    #[serde(default)]
+    field_mappings: HashMap<String, String>,
+}
+
+/// Copies every document of the source index into the destination index.
+///
+/// Fields are renamed according to the `field_mappings`, documents which do
+/// not match the destination's schema are skipped and listed in the
+/// response rather than aborting the copy.
+pub async fn reindex(mut req: LnxRequest) -> LnxResponse {
+    let payload: ReindexPayload = json!(req.body_mut());
+    ensure_index_access(&req, &[&payload.source, &payload.dest])?;
+
+    let state = req.data::<State>().expect("get state");
+
+    if payload.source == payload.dest {
+        return abort!(400, "the source and destination index must differ");
+    }
+
+    let source = get_or_400!(
+        state.engine.get_index(&payload.source),
+        "source index does not exist"
+    );
+    let dest = get_or_400!(
+        state.engine.get_index(&payload.dest),
+        "destination index does not exist"
+    );
+
+    let report = dest.reindex_from(&source, payload.field_mappings).await?;
+
+    json_response(200, &report)
+}
+
+#[derive(Deserialize)]
+struct IndexRenamePayload {
+    name: String,
+}
+
+/// Renames the index without re-creating it.
+///
+/// The index keeps the data stored under its original name, if the new
+/// declarations cannot be persisted the rename is reverted.
+pub async fn rename_index(mut req: LnxRequest) -> LnxResponse {
+    let payload: IndexRenamePayload = json!(req.body_mut());
+    ensure_index_access(&req, &[&payload.name])?;
+
+    let state = req.data::<State>().expect("get state");
+    let index = get_or_400!(req.param("index"));
+
+    state.engine.rename_index(index, &payload.name)?;
+
+    let indexes = state.engine.get_all_indexes();
+    let storage = state.storage.clone();
+
+    // This kinda sucks that we have to do this due to Bincode not enjoying
+    // the IndexDeclaration struct.
+    let buffer = serde_json::to_vec(&indexes)?;
+    let res = atomic_store(storage, INDEX_KEYSPACE, buffer).await;
+
+    if res.is_err() {
+        state.engine.rename_index(&payload.name, index)?;
+        res?;
+    }
+
+    json_response(200, "index renamed.")
+}
+
+#[derive(Deserialize)]
+struct IndexClonePayload {
+    new_name: String,
+}
+
+/// Creates a new empty index with the same declaration as the index.
+///
+/// Only the configuration is copied, the new index gets it's own storage
+/// and none of the documents. If the new declarations cannot be persisted
+/// the new index is removed again.
+pub async fn clone_index(mut req: LnxRequest) -> LnxResponse {
+    let payload: IndexClonePayload = json!(req.body_mut());
+    ensure_index_access(&req, &[&payload.new_name])?;
+
+    let state = req.data::<State>().expect("get state");
+    let index = get_or_400!(req.param("index"));
+
+    let declaration =
+        get_or_400!(state.engine.get_declaration(index), "index does not exist");
+
+    state
+        .engine
+        .add_index(declaration.with_name(&payload.new_name), false)
+        .await?;
+
+    let indexes = state.engine.get_all_indexes();
+    let storage = state.storage.clone();
+
+    // This kinda sucks that we have to do this due to Bincode not enjoying
+    // the IndexDeclaration struct.
+    let buffer = serde_json::to_vec(&indexes)?;
+    let res = atomic_store(storage, INDEX_KEYSPACE, buffer).await;
+
+    if res.is_err() {
+        state.engine.remove_index(&payload.new_name).await?;
+        res?;
+    }
+
+    json_response(200, "index cloned.")
+}
+
+/// Replaces the field boosts of the index without re-creating it.
+///
+/// The body is a map of field names to their boost factor, only text and
+/// string fields can be boosted. If the new declarations cannot be
+/// persisted the previous boosts are restored.
+pub async fn update_boosts(mut req: LnxRequest) -> LnxResponse {
+    let payload: HashMap<String, f32> = json!(req.body_mut());
+    let state = req.data::<State>().expect("get state");
+    let index = get_or_400!(req.param("index"));
+
+    let previous = state.engine.update_boost_fields(index, payload.clone())?;
+
+    let indexes = state.engine.get_all_indexes();
+    let storage = state.storage.clone();
+
+    // This kinda sucks that we have to do this due to Bincode not enjoying
+    // the IndexDeclaration struct.
+    let buffer = serde_json::to_vec(&indexes)?;
+    let res = atomic_store(storage, INDEX_KEYSPACE, buffer).await;
+
+    if res.is_err() {
+        state.engine.update_boost_fields(index, previous)?;
+        res?;
+    }
+
+    json_response(200, &payload)
+}
+
+/// Checks if the index exists.
+///
+/// This returns an empty `200 OK` if the index is loaded by the engine,
+/// otherwise an empty `404 Not Found`.
+pub async fn index_exists(req: LnxRequest) -> LnxResponse {
+    let state = req.data::<State>().expect("get state");
+    let index = get_or_400!(req.param("index"));
+
+    let mut resp = Response::new(Body::empty());
+    if !state.engine.has_index(index) {
+        *resp.status_mut() = StatusCode::NOT_FOUND;
+    }
 
-    json_response(200, "index deleted")
+    Ok(resp)
 }
@@ -1,26 +1,70 @@
 use std::collections::BTreeMap;
-use std::time::Instant;
-
-use engine::structures::{DocumentOptions, DocumentValueOptions};
-use engine::{DocumentId, Index, QueryPayload, QueryResults};
+use std::convert::TryInto;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use engine::structures::{
+    DocumentOptions,
+    DocumentPayload,
+    DocumentValue,
+    DocumentValueOptions,
+};
+use engine::{
+    DocumentId,
+    ExportLimits,
+    Index,
+    QueryPayload,
+    QueryResults,
+    WriterQueueFull,
+};
+use hyper::header::{HeaderValue, CONTENT_TYPE, RETRY_AFTER};
+use hyper::Body;
 use routerify::ext::RequestExt;
 use serde::{Deserialize, Serialize};
 
+use crate::analytics::AnalyticsEvent;
 use crate::error::{LnxError, Result};
-use crate::helpers::{LnxRequest, LnxResponse};
+use crate::helpers::{atomic_store, get_query_param, LnxRequest, LnxResponse};
 use crate::responders::json_response;
+use crate::routes::request_id::get_request_id;
 use crate::state::State;
-use crate::{get_or_400, json, unauthorized};
+use crate::{
+    abort,
+    bad_request,
+    get_or_400,
+    inference,
+    json,
+    unauthorized,
+    INDEX_KEYSPACE,
+};
 
 pub async fn ensure_index_perms(req: LnxRequest) -> Result<LnxRequest> {
     if !req.uri().path().starts_with("/indexes/") {
         return Ok(req);
     };
 
+    let path = req.uri().path();
+    let index = {
+        let stop: &str = path.strip_prefix("/indexes/").unwrap_or("");
+
+        let mut split = stop.split('/');
+        split.next().unwrap_or(stop).to_string()
+    };
+
+    ensure_index_access(&req, &[&index])?;
+
+    Ok(req)
+}
+
+/// Checks the request's token has access to every one of the given indexes.
+///
+/// This is used by routes which name indexes in their body rather than
+/// their path, these are not covered by `ensure_index_perms`.
+pub(crate) fn ensure_index_access(req: &LnxRequest, indexes: &[&str]) -> Result<()> {
     let state = req.data::<State>().expect("get state");
 
     if !state.auth.enabled() {
-        return Ok(req);
+        return Ok(());
     }
 
     let auth = req.headers().get("Authorization");
@@ -36,19 +80,44 @@ pub async fn ensure_index_perms(req: LnxRequest) -> Result<LnxRequest> {
         Some(v) => v,
     };
 
-    let path = req.uri().path();
-    let index = {
-        let stop: &str = path.strip_prefix("/indexes/").unwrap_or("");
+    if !indexes.iter().all(|index| data.has_access_to_index(index)) {
+        return unauthorized!("invalid token does not have access to this index");
+    }
 
-        let mut split = stop.split('/');
-        split.next().unwrap_or(stop).to_string()
-    };
+    Ok(())
+}
 
-    if !data.has_access_to_index(&index) {
-        return unauthorized!("invalid token does not have access to this index");
+#[derive(Deserialize)]
+struct IndexConfigPayload {
+    /// If the index should reject any document writes.
+    read_only: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct IndexConfig {
+    read_only: bool,
+}
+
+/// Updates the runtime configuration of the index.
+///
+/// Marking the index as read only rejects any document writes and commits
+/// with a `409` while leaving searches available.
+pub async fn update_config(mut req: LnxRequest) -> LnxResponse {
+    let payload: IndexConfigPayload = json!(req.body_mut());
+
+    let state = req.data::<State>().expect("get state");
+    let index = get_or_400!(req.param("index"));
+    let index = get_or_400!(state.engine.get_index(index), "index does not exist");
+
+    if let Some(read_only) = payload.read_only {
+        index.set_read_only(read_only);
     }
 
-    Ok(req)
+    let config = IndexConfig {
+        read_only: index.is_read_only(),
+    };
+
+    json_response(200, &config)
 }
 
 pub async fn commit(req: LnxRequest) -> LnxResponse {
@@ -71,25 +140,190 @@ pub async fn rollback(req: LnxRequest) -> LnxResponse {
     json_response(200, "changed dropped")
 }
 
-pub async fn search_index(mut req: LnxRequest) -> LnxResponse {
-    let payload: QueryPayload = json!(req.body_mut());
+/// Gets the segment level details of the index and its writer queue depth.
+pub async fn get_diagnostics(req: LnxRequest) -> LnxResponse {
+    let state = req.data::<State>().expect("get state");
+    let index = get_or_400!(req.param("index"));
+    let index = get_or_400!(state.engine.get_index(index), "index does not exist");
+
+    let diagnostics = index.diagnostics().await?;
 
+    json_response(200, &diagnostics)
+}
+
+/// Gets the index's search fields along with their boosts and capabilities.
+pub async fn get_ranking(req: LnxRequest) -> LnxResponse {
     let state = req.data::<State>().expect("get state");
     let index = get_or_400!(req.param("index"));
     let index = get_or_400!(state.engine.get_index(index), "index does not exist");
 
+    json_response(200, &index.ranking())
+}
+
+pub async fn search_index(mut req: LnxRequest) -> LnxResponse {
+    let body = hyper::body::to_bytes(req.body_mut()).await?;
+    let payload: QueryPayload = serde_json::from_slice(&body)?;
+
+    let state = req.data::<State>().expect("get state");
+    let index_name = get_or_400!(req.param("index"));
+    let index = get_or_400!(state.engine.get_index(index_name), "index does not exist");
+
+    let empty_as_404 = payload.empty_as_404();
+    let mode = payload.mode();
+
     let start = Instant::now();
     let results: QueryResults = index.search(payload).await?;
+    let elapsed = start.elapsed();
 
-    if state.log_search {
+    if index.log_searches().unwrap_or(state.log_search) {
         info!(
+            request_id = %get_request_id(&req),
             "search took {:?} returning {} results",
-            start.elapsed(),
+            elapsed,
             results.len()
         );
     }
 
-    json_response(200, &results)
+    if let Some(ref analytics) = state.analytics {
+        let query = serde_json::from_slice::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|mut payload| {
+                payload.get_mut("query").map(serde_json::Value::take)
+            })
+            .unwrap_or_default();
+
+        analytics.record(AnalyticsEvent::Search {
+            index: index_name.to_string(),
+            request_id: get_request_id(&req),
+            timestamp: Utc::now(),
+            query,
+            mode,
+            count: results.len(),
+            latency_ms: elapsed.as_secs_f64() * 1000.0,
+        });
+    }
+
+    let status = if empty_as_404 && results.is_empty() {
+        404
+    } else {
+        200
+    };
+
+    json_response(status, &results)
+}
+
+/// Searches the index streaming the hits to the client as NDJSON.
+///
+/// Unlike a normal search the hits are written as they are retrieved so
+/// large limits do not need to be held in memory, hits are always ordered
+/// by relevancy. If the client disconnects the search is stopped.
+pub async fn search_index_stream(mut req: LnxRequest) -> LnxResponse {
+    let payload: QueryPayload = json!(req.body_mut());
+
+    let state = req.data::<State>().expect("get state");
+    let index = get_or_400!(req.param("index"));
+    let index = get_or_400!(state.engine.get_index(index), "index does not exist");
+
+    let mut chunks = index.search_stream(payload).await?;
+    let (mut sender, body) = Body::channel();
+
+    let request_id = get_request_id(&req);
+    tokio::spawn(async move {
+        while let Some(chunk) = chunks.recv().await {
+            let hits = match chunk {
+                Ok(hits) => hits,
+                Err(e) => {
+                    error!(request_id = %request_id, "failed to stream search hits: {:?}", e);
+                    sender.abort();
+                    return;
+                },
+            };
+
+            let mut buffer = vec![];
+            for hit in hits {
+                if let Err(e) = serde_json::to_writer(&mut buffer, &hit) {
+                    error!(request_id = %request_id, "failed to serialize hit: {:?}", e);
+                    sender.abort();
+                    return;
+                }
+                buffer.push(b'\n');
+            }
+
+            // The client has disconnected, dropping the receiver stops the search.
+            if sender.send_data(buffer.into()).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    let mut resp = hyper::Response::new(body);
+    resp.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("application/x-ndjson"),
+    );
+
+    Ok(resp)
+}
+
+#[derive(Deserialize)]
+struct FeedbackPayload {
+    /// The id of the search request which returned the selected hit.
+    request_id: String,
+
+    /// The id of the selected document.
+    document_id: DocumentValue,
+
+    /// The position of the selected hit within the results, starting at 0.
+    position: usize,
+}
+
+/// Records which hit of a past search was selected by a user.
+///
+/// The search is identified by the `X-Request-Id` returned alongside its
+/// results, allowing click through rates to be computed from the search
+/// analytics log.
+pub async fn record_feedback(mut req: LnxRequest) -> LnxResponse {
+    let payload: FeedbackPayload = json!(req.body_mut());
+
+    let state = req.data::<State>().expect("get state");
+    let index = get_or_400!(req.param("index"));
+    if !state.engine.has_index(index) {
+        return bad_request!("index does not exist");
+    }
+
+    let analytics = match state.analytics {
+        Some(ref analytics) => analytics,
+        None => return abort!(400, "search analytics are not enabled on this server"),
+    };
+
+    let document_id: Option<DocumentId> = payload.document_id.try_into().ok();
+    let document_id = get_or_400!(document_id, "validate document id");
+
+    analytics.record(AnalyticsEvent::Feedback {
+        index: index.to_string(),
+        request_id: payload.request_id,
+        timestamp: Utc::now(),
+        document_id,
+        position: payload.position,
+    });
+
+    json_response(200, "feedback recorded")
+}
+
+/// Builds the query of the given search payload without executing it.
+///
+/// The response contains the built query's representation and the fields
+/// it searches, invalid queries are rejected the same way as when searching.
+pub async fn validate_query(mut req: LnxRequest) -> LnxResponse {
+    let payload: QueryPayload = json!(req.body_mut());
+
+    let state = req.data::<State>().expect("get state");
+    let index = get_or_400!(req.param("index"));
+    let index = get_or_400!(state.engine.get_index(index), "index does not exist");
+
+    let explanation = index.explain_query(payload).await?;
+
+    json_response(200, &explanation)
 }
 
 #[derive(Deserialize)]
@@ -117,6 +351,71 @@ pub async fn get_corrected_query_hint(mut req: LnxRequest) -> LnxResponse {
     json_response(200, &payload)
 }
 
+/// The amount of suggestions returned if no `limit` is given.
+const DEFAULT_SUGGEST_LIMIT: usize = 10;
+
+/// Gets the terms of a field completing the given prefix.
+///
+/// The prefix and field are given via the `?q=` and `?field=` query
+/// parameters, `?limit=` sets the maximum amount of terms returned.
+pub async fn suggest(req: LnxRequest) -> LnxResponse {
+    let state = req.data::<State>().expect("get state");
+    let index = get_or_400!(req.param("index"));
+    let index = get_or_400!(state.engine.get_index(index), "index does not exist");
+
+    let prefix = get_or_400!(
+        get_query_param(&req, "q"),
+        "missing the `q` query parameter"
+    );
+    let field = get_or_400!(
+        get_query_param(&req, "field"),
+        "missing the `field` query parameter"
+    );
+
+    let limit = match get_query_param(&req, "limit") {
+        None => DEFAULT_SUGGEST_LIMIT,
+        Some(raw) => match raw.parse::<usize>() {
+            Ok(limit) if limit > 0 => limit,
+            _ => return abort!(400, "limit must be a positive integer"),
+        },
+    };
+
+    let suggestions = index
+        .suggest(field.to_string(), prefix.to_string(), limit)
+        .await?;
+
+    json_response(200, &suggestions)
+}
+
+/// Suggests a corrected version of the query using the terms of a field.
+///
+/// The query and field are given via the `?q=` and `?field=` query
+/// parameters.
+pub async fn spell_check(req: LnxRequest) -> LnxResponse {
+    let state = req.data::<State>().expect("get state");
+    let index = get_or_400!(req.param("index"));
+    let index = get_or_400!(state.engine.get_index(index), "index does not exist");
+
+    let query = get_or_400!(
+        get_query_param(&req, "q"),
+        "missing the `q` query parameter"
+    );
+    let field = get_or_400!(
+        get_query_param(&req, "field"),
+        "missing the `field` query parameter"
+    );
+
+    let spell_check = index
+        .spell_check(field.to_string(), query.to_string())
+        .await?;
+
+    json_response(200, &spell_check)
+}
+
+/// Gets a single document by it's id.
+///
+/// The returned fields of the document can be limited by passing a comma
+/// separated list of field names via the `?fields=` query parameter.
 pub async fn get_document(req: LnxRequest) -> LnxResponse {
     let state = req.data::<State>().expect("get state");
     let index = get_or_400!(req.param("index"));
@@ -125,11 +424,158 @@ pub async fn get_document(req: LnxRequest) -> LnxResponse {
     let raw_doc_id = get_or_400!(req.param("document_id"));
     let document_id = get_or_400!(raw_doc_id.parse::<DocumentId>().ok());
 
-    let document = index.get_document(document_id).await?;
+    let mut document = match index.get_document(document_id).await? {
+        Some(document) => document,
+        None => return abort!(404, "no document exists with the given id"),
+    };
+
+    if let Some(fields) = get_query_param(&req, "fields") {
+        let fields: Vec<&str> = fields.split(',').collect();
+        document.retain_fields(&fields);
+    }
 
     json_response(200, &document)
 }
 
+#[derive(Deserialize)]
+struct MultiGetPayload {
+    /// The ids of the documents to fetch.
+    ids: Vec<DocumentValue>,
+
+    /// An optional set of fields to limit the returned documents to.
+    fields: Option<Vec<String>>,
+}
+
+/// Gets a set of documents by their ids.
+///
+/// The documents are returned in the same order as the given ids with
+/// `null` in place of any document which does not exist.
+pub async fn get_many_documents(mut req: LnxRequest) -> LnxResponse {
+    let payload: MultiGetPayload = json!(req.body_mut());
+
+    let state = req.data::<State>().expect("get state");
+    let index = get_or_400!(req.param("index"));
+    let index = get_or_400!(state.engine.get_index(index), "index does not exist");
+
+    let mut document_ids = Vec::with_capacity(payload.ids.len());
+    for id in payload.ids {
+        let id: Option<DocumentId> = id.try_into().ok();
+        document_ids.push(get_or_400!(id, "validate document id"));
+    }
+
+    let mut documents = index.get_documents(document_ids).await?;
+
+    if let Some(ref fields) = payload.fields {
+        let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+        for document in documents.iter_mut().flatten() {
+            document.retain_fields(&fields);
+        }
+    }
+
+    json_response(200, &documents)
+}
+
+/// The amount of documents read from the index at a time when exporting.
+const DEFAULT_EXPORT_CHUNK_SIZE: usize = 500;
+
+/// Streams every document in the index to the client as NDJSON.
+///
+/// The amount of documents read at a time can be set via the `?chunk_size=`
+/// query parameter, `?fields=` limits the returned fields like when getting
+/// a single document and `?segment=` limits the export to one segment.
+///
+/// The export can be bounded via `?max_rows=` and `?max_duration=` in
+/// seconds, once either is reached the export stops and the response ends.
+/// If the client disconnects the export is stopped.
+pub async fn export_documents(req: LnxRequest) -> LnxResponse {
+    let state = req.data::<State>().expect("get state");
+    let index = get_or_400!(req.param("index"));
+    let index = get_or_400!(state.engine.get_index(index), "index does not exist");
+
+    let chunk_size = match get_query_param(&req, "chunk_size") {
+        None => DEFAULT_EXPORT_CHUNK_SIZE,
+        Some(raw) => match raw.parse::<usize>() {
+            Ok(size) if size > 0 => size,
+            _ => return abort!(400, "chunk_size must be a positive integer"),
+        },
+    };
+
+    let fields: Option<Vec<String>> = get_query_param(&req, "fields")
+        .map(|fields| fields.split(',').map(String::from).collect());
+    let segment = get_query_param(&req, "segment").map(String::from);
+
+    let max_rows = match get_query_param(&req, "max_rows") {
+        None => None,
+        Some(raw) => match raw.parse::<usize>() {
+            Ok(rows) if rows > 0 => Some(rows),
+            _ => return abort!(400, "max_rows must be a positive integer"),
+        },
+    };
+
+    let max_duration = match get_query_param(&req, "max_duration") {
+        None => None,
+        Some(raw) => match raw.parse::<u64>() {
+            Ok(secs) if secs > 0 => Some(Duration::from_secs(secs)),
+            _ => {
+                return abort!(400, "max_duration must be a positive amount of seconds")
+            },
+        },
+    };
+
+    let limits = ExportLimits {
+        max_rows,
+        max_duration,
+    };
+
+    let mut chunks = index.export_documents(chunk_size, segment, limits);
+    let (mut sender, body) = Body::channel();
+
+    let request_id = get_request_id(&req);
+    tokio::spawn(async move {
+        let fields: Option<Vec<&str>> = fields
+            .as_ref()
+            .map(|fields| fields.iter().map(String::as_str).collect());
+
+        while let Some(chunk) = chunks.recv().await {
+            let documents = match chunk {
+                Ok(documents) => documents,
+                Err(e) => {
+                    error!(request_id = %request_id, "failed to export documents: {:?}", e);
+                    sender.abort();
+                    return;
+                },
+            };
+
+            let mut buffer = vec![];
+            for mut document in documents {
+                if let Some(ref fields) = fields {
+                    document.retain_fields(fields);
+                }
+
+                if let Err(e) = serde_json::to_writer(&mut buffer, &document) {
+                    error!(request_id = %request_id, "failed to serialize document: {:?}", e);
+                    sender.abort();
+                    return;
+                }
+                buffer.push(b'\n');
+            }
+
+            // The client has disconnected, dropping the receiver stops the export.
+            if sender.send_data(buffer.into()).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    let mut resp = hyper::Response::new(body);
+    resp.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("application/x-ndjson"),
+    );
+
+    Ok(resp)
+}
+
 pub async fn add_stop_words(mut req: LnxRequest) -> LnxResponse {
     let payload: Vec<String> = json!(req.body_mut());
 
@@ -166,6 +612,19 @@ pub async fn remove_stop_words(mut req: LnxRequest) -> LnxResponse {
     json_response(200, "stop words removed")
 }
 
+pub async fn replace_stop_words(mut req: LnxRequest) -> LnxResponse {
+    let payload: Vec<String> = json!(req.body_mut());
+
+    let state = req.data::<State>().expect("get state");
+    let index = get_or_400!(req.param("index"));
+    let index: Index =
+        get_or_400!(state.engine.get_index(index), "index does not exist");
+
+    let replaced = index.replace_stop_words(payload).await?;
+
+    json_response(200, &replaced)
+}
+
 pub async fn clear_stop_words(req: LnxRequest) -> LnxResponse {
     let state = req.data::<State>().expect("get state");
     let index = get_or_400!(req.param("index"));
@@ -213,6 +672,19 @@ pub async fn remove_synonyms(mut req: LnxRequest) -> LnxResponse {
     json_response(200, "synonyms removed")
 }
 
+pub async fn replace_synonyms(mut req: LnxRequest) -> LnxResponse {
+    let payload: Vec<String> = json!(req.body_mut());
+
+    let state = req.data::<State>().expect("get state");
+    let index = get_or_400!(req.param("index"));
+    let index: Index =
+        get_or_400!(state.engine.get_index(index), "index does not exist");
+
+    let replaced = index.replace_synonyms(payload).await?;
+
+    json_response(200, &replaced)
+}
+
 pub async fn clear_synonyms(req: LnxRequest) -> LnxResponse {
     let state = req.data::<State>().expect("get state");
     let index = get_or_400!(req.param("index"));
@@ -224,15 +696,108 @@ pub async fn clear_synonyms(req: LnxRequest) -> LnxResponse {
     json_response(200, "synonyms cleared")
 }
 
+/// Creates or extends the index's schema from the given documents if
+/// the index's schema is inferred.
+///
+/// Indexes are only created when the server is allowed to automatically
+/// create indexes.
+async fn infer_index_schema(
+    state: &State,
+    name: &str,
+    documents: &serde_json::Value,
+) -> Result<()> {
+    let declaration = state.engine.get_declaration(name);
+    if matches!(declaration, Some(ref declaration) if !declaration.infer_new_fields()) {
+        return Ok(());
+    }
+
+    // Documents which fit the existing schema don't need to wait on
+    // other inferences, the schema is checked again once locked.
+    if !inference::needs_inference(declaration.as_ref(), documents)? {
+        return Ok(());
+    }
+
+    let _guard = state.inference_lock.lock().await;
+
+    let replaced = match state.engine.get_declaration(name) {
+        None => {
+            let declaration = inference::infer_declaration(name, documents)?;
+            state.engine.add_index_if_not_exists(declaration).await?;
+            None
+        },
+        Some(declaration) if declaration.infer_new_fields() => {
+            match inference::extend_declaration(&declaration, documents)? {
+                Some(declaration) => {
+                    Some(state.engine.recreate_index(declaration).await?)
+                },
+                None => return Ok(()),
+            }
+        },
+        Some(_) => return Ok(()),
+    };
+
+    let buffer = serde_json::to_vec(&state.engine.get_all_indexes())?;
+    atomic_store(state.storage.clone(), INDEX_KEYSPACE, buffer).await?;
+
+    // The replaced index's data is only removed once the new declaration
+    // is persisted, otherwise it would be lost on a restart.
+    if let Some(replaced) = replaced {
+        replaced.destroy().await?;
+    }
+
+    Ok(())
+}
+
 pub async fn add_documents(mut req: LnxRequest) -> LnxResponse {
-    let payload: DocumentOptions = json!(req.body_mut());
+    let body = hyper::body::to_bytes(req.body_mut()).await?;
+    let mut payload: DocumentOptions = serde_json::from_slice(&body)?;
 
     let state = req.data::<State>().expect("get state");
     let index = get_or_400!(req.param("index"));
+
+    if state.auto_create_indexes {
+        let mut documents: serde_json::Value = serde_json::from_slice(&body)?;
+        infer_index_schema(state, index, &documents).await?;
+
+        // Integers given to fields inferred as floats are widened to match.
+        if let Some(declaration) = state.engine.get_declaration(index) {
+            inference::widen_documents(&declaration, &mut documents)?;
+            payload = serde_json::from_value(documents)?;
+        }
+    }
+
     let index: Index =
         get_or_400!(state.engine.get_index(index), "index does not exist");
 
-    index.add_documents(payload).await?;
+    let block_timeout = state.ingest_block_timeout;
+    let res = index
+        .add_documents_with_timeout(payload, block_timeout)
+        .await;
+
+    if let Err(ref e) = res {
+        if e.is::<WriterQueueFull>() {
+            let retry_after = block_timeout
+                .map(|timeout| timeout.as_secs().max(1))
+                .unwrap_or(1);
+
+            let mut resp = json_response(503, &e.to_string())?;
+            resp.headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+
+            return Ok(resp);
+        }
+    }
+
+    let oversized = res?;
+    if oversized > 0 {
+        return json_response(
+            200,
+            &format!(
+                "changes registered, {} values were too large to index as a single term",
+                oversized,
+            ),
+        );
+    }
 
     json_response(200, "changes registered")
 }
@@ -278,6 +843,37 @@ pub async fn delete_document(req: LnxRequest) -> LnxResponse {
     )
 }
 
+#[derive(Deserialize)]
+struct DocumentUpdatePayload {
+    /// The fields of the document to replace.
+    doc: DocumentPayload,
+}
+
+/// Updates some of the fields of an existing document.
+///
+/// The stored document is merged with the given fields and re-indexed
+/// under the same id, fields which are not stored must always be given.
+pub async fn update_document(mut req: LnxRequest) -> LnxResponse {
+    let payload: DocumentUpdatePayload = json!(req.body_mut());
+
+    let state = req.data::<State>().expect("get state");
+    let index = get_or_400!(req.param("index"));
+    let index: Index =
+        get_or_400!(state.engine.get_index(index), "index does not exist");
+
+    let document_id = get_or_400!(req.param("document_id"));
+    let document_id = get_or_400!(
+        document_id.parse::<DocumentId>().ok(),
+        "validate document id"
+    );
+
+    if !index.update_document(document_id, payload.doc).await? {
+        return abort!(404, "no document exists with the given id");
+    }
+
+    json_response(200, "document updated.")
+}
+
 pub async fn delete_documents_by_query(mut req: LnxRequest) -> LnxResponse {
     let payload: QueryPayload = json!(req.body_mut());
 
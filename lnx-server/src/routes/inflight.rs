@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use routerify::ext::RequestExt;
+
+use crate::abort;
+use crate::error::Result;
+use crate::helpers::LnxRequest;
+use crate::responders::json_response;
+use crate::state::State;
+
+/// A middleware that rejects requests with a `429` once their client already
+/// has the maximum amount of requests in flight.
+///
+/// Clients are identified by their access token, or by their IP address if
+/// authorization is disabled. The slot is released once the request
+/// completes.
+pub(crate) async fn limit_inflight(req: LnxRequest) -> Result<LnxRequest> {
+    let state = req.data::<State>().expect("get state");
+    let limiter = match state.inflight {
+        Some(ref limiter) => limiter,
+        None => return Ok(req),
+    };
+
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|v| v.to_str().ok())
+        .filter(|_| state.auth.enabled());
+
+    let client = match token {
+        Some(token) => token.to_string(),
+        None => req.remote_addr().ip().to_string(),
+    };
+
+    let permit = match limiter.try_acquire(client) {
+        Some(permit) => permit,
+        None => return abort!(429, "too many requests in flight for this client."),
+    };
+
+    // The permit is dropped alongside the request's context.
+    req.set_context(Arc::new(permit));
+
+    Ok(req)
+}
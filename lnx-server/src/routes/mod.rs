@@ -2,6 +2,10 @@ mod auth;
 mod default_handlers;
 mod engine;
 mod index;
+mod inflight;
+mod read_only;
+mod request_id;
+mod version;
 
 use hyper::Body;
 use routerify::{Middleware, Router};
@@ -12,25 +16,45 @@ use crate::state::State;
 pub fn get_router(state: State) -> Router<Body, LnxError> {
     Router::builder()
         .data(state)
+        .middleware(Middleware::pre(request_id::assign_request_id))
+        .middleware(Middleware::post_with_info(request_id::echo_request_id))
         .middleware(Middleware::pre(auth::check_permissions))
+        .middleware(Middleware::pre(inflight::limit_inflight))
         .middleware(Middleware::pre(index::ensure_index_perms))
+        .middleware(Middleware::pre(read_only::reject_writes))
+        .get("/version", version::get_version)
         .post("/auth", auth::create_token)
         .delete("/auth", auth::revoke_all_tokens)
         .post("/auth/:token/revoke", auth::revoke_token)
         .post("/auth/:token/edit", auth::edit_token)
         .post("/indexes", engine::create_index)
+        .post("/_reindex", engine::reindex)
+        .head("/indexes/:index", engine::index_exists)
         .delete("/indexes/:index", engine::delete_index)
+        .post("/indexes/:index/rename", engine::rename_index)
+        .post("/indexes/:index/_clone", engine::clone_index)
+        .patch("/indexes/:index/config", index::update_config)
+        .patch("/indexes/:index/boosts", engine::update_boosts)
         .post("/indexes/:index/commit", index::commit)
         .post("/indexes/:index/rollback", index::rollback)
+        .get("/indexes/:index/_diagnostics", index::get_diagnostics)
+        .get("/indexes/:index/_ranking", index::get_ranking)
         .post("/indexes/:index/search", index::search_index)
+        .post("/indexes/:index/search/stream", index::search_index_stream)
+        .post("/indexes/:index/_validate_query", index::validate_query)
+        .post("/indexes/:index/_feedback", index::record_feedback)
         .post("/indexes/:index/hint", index::get_corrected_query_hint)
+        .get("/indexes/:index/suggest", index::suggest)
+        .get("/indexes/:index/spellcheck", index::spell_check)
         .post("/indexes/:index/documents", index::add_documents)
         .get("/indexes/:index/stopwords", index::get_stop_words)
         .post("/indexes/:index/stopwords", index::add_stop_words)
+        .put("/indexes/:index/stopwords", index::replace_stop_words)
         .delete("/indexes/:index/stopwords", index::remove_stop_words)
         .delete("/indexes/:index/stopwords/clear", index::clear_stop_words)
         .get("/indexes/:index/synonyms", index::get_synonyms)
         .post("/indexes/:index/synonyms", index::add_synonyms)
+        .put("/indexes/:index/synonyms", index::replace_synonyms)
         .delete("/indexes/:index/synonyms", index::remove_synonyms)
         .delete("/indexes/:index/synonyms/clear", index::clear_synonyms)
         .delete("/indexes/:index/documents", index::delete_documents)
@@ -39,6 +63,8 @@ pub fn get_router(state: State) -> Router<Body, LnxError> {
             index::delete_documents_by_query,
         )
         .delete("/indexes/:index/documents/clear", index::clear_documents)
+        .post("/indexes/:index/documents/_mget", index::get_many_documents)
+        .get("/indexes/:index/documents/_export", index::export_documents)
         .get(
             "/indexes/:index/documents/:document_id",
             index::get_document,
@@ -47,6 +73,10 @@ pub fn get_router(state: State) -> Router<Body, LnxError> {
             "/indexes/:index/documents/:document_id",
             index::delete_document,
         )
+        .post(
+            "/indexes/:index/documents/:document_id/_update",
+            index::update_document,
+        )
         .err_handler(default_handlers::error_handler)
         .any(default_handlers::handle_404)
         .build()
@@ -0,0 +1,53 @@
+use hyper::Method;
+use routerify::ext::RequestExt;
+
+use crate::abort;
+use crate::error::Result;
+use crate::helpers::LnxRequest;
+use crate::responders::json_response;
+use crate::state::State;
+
+/// The `POST` routes which only read from an index.
+const READ_ONLY_POST_ROUTES: &[&str] = &[
+    "/search",
+    "/search/stream",
+    "/_validate_query",
+    "/_feedback",
+    "/hint",
+    "/documents/_mget",
+];
+
+/// A middleware that rejects any index or engine writes with a `403`
+/// when the server is running as a read only node.
+///
+/// Access tokens are local to each node so the auth routes remain
+/// available.
+pub(crate) async fn reject_writes(req: LnxRequest) -> Result<LnxRequest> {
+    let state = req.data::<State>().expect("get state");
+    if !state.read_only_node {
+        return Ok(req);
+    }
+
+    let path = req.uri().path();
+    if path == "/_reindex" {
+        return abort!(403, "this node is read only and does not accept writes.");
+    }
+
+    if !path.starts_with("/indexes") {
+        return Ok(req);
+    }
+
+    let is_read = match *req.method() {
+        Method::GET | Method::HEAD => true,
+        Method::POST => READ_ONLY_POST_ROUTES
+            .iter()
+            .any(|route| path.ends_with(route)),
+        _ => false,
+    };
+
+    if !is_read {
+        return abort!(403, "this node is read only and does not accept writes.");
+    }
+
+    Ok(req)
+}
@@ -0,0 +1,78 @@
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::{Body, Response};
+use routerify::ext::RequestExt;
+use routerify::RequestInfo;
+
+use crate::error::Result;
+use crate::helpers::LnxRequest;
+
+/// The header used to pass and return the id of a request.
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// The maximum length of a request id given by a client.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// The id used to correlate a request across logs and services.
+#[derive(Debug, Clone)]
+pub(crate) struct RequestId(pub(crate) String);
+
+/// Gets the id of the given request.
+///
+/// This is always set by the `assign_request_id` middleware.
+pub(crate) fn get_request_id(req: &LnxRequest) -> String {
+    req.context::<RequestId>()
+        .map(|id| id.0)
+        .unwrap_or_default()
+}
+
+/// A middleware that tags each request with an id.
+///
+/// The id is taken from the `X-Request-Id` header if the client provided a
+/// valid one, otherwise a new UUID is generated.
+pub(crate) async fn assign_request_id(req: LnxRequest) -> Result<LnxRequest> {
+    let given = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty() && v.len() <= MAX_REQUEST_ID_LEN);
+
+    let request_id = match given {
+        Some(id) => id.to_string(),
+        None => uuid::Uuid::new_v4().to_string(),
+    };
+
+    debug!(
+        request_id = %request_id,
+        method = %req.method(),
+        path = %req.uri().path(),
+        "handling request"
+    );
+
+    req.set_context(RequestId(request_id));
+
+    Ok(req)
+}
+
+/// A middleware that echos the id of the request back in the response headers.
+pub(crate) async fn echo_request_id(
+    mut res: Response<Body>,
+    info: RequestInfo,
+) -> Result<Response<Body>> {
+    let request_id = match info.context::<RequestId>() {
+        Some(id) => id.0,
+        None => return Ok(res),
+    };
+
+    debug!(
+        request_id = %request_id,
+        status = res.status().as_u16(),
+        "request completed"
+    );
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        res.headers_mut()
+            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+
+    Ok(res)
+}
@@ -0,0 +1,31 @@
+use serde::Serialize;
+
+use crate::helpers::{LnxRequest, LnxResponse};
+use crate::responders::json_response;
+
+/// The version and build metadata of the running server.
+#[derive(Serialize)]
+struct VersionInfo {
+    /// The crate version of the server.
+    version: &'static str,
+
+    /// The short hash of the commit the server was built from.
+    git_hash: &'static str,
+
+    /// When the server was built as an RFC 3339 timestamp.
+    build_timestamp: &'static str,
+}
+
+/// Gets the version and build metadata of the server.
+///
+/// This does not require authorization so deployment tooling can verify
+/// which build is running.
+pub async fn get_version(_req: LnxRequest) -> LnxResponse {
+    let info = VersionInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_hash: env!("LNX_GIT_HASH"),
+        build_timestamp: env!("LNX_BUILD_TIMESTAMP"),
+    };
+
+    json_response(200, &info)
+}
@@ -1,10 +1,23 @@
+use std::sync::Arc;
+use std::time::Duration;
+
 use engine::Engine;
 
+use crate::analytics::AnalyticsSink;
 use crate::auth::AuthManager;
+use crate::inflight::InflightLimiter;
 
 #[derive(Clone)]
 pub struct State {
     pub log_search: bool,
+    pub ingest_block_timeout: Option<Duration>,
+    pub read_only_node: bool,
+    pub auto_create_indexes: bool,
+
+    /// Held while an index is created or extended from added documents.
+    pub inference_lock: Arc<tokio::sync::Mutex<()>>,
+    pub inflight: Option<InflightLimiter>,
+    pub analytics: Option<AnalyticsSink>,
     pub engine: Engine,
     pub auth: AuthManager,
     pub storage: sled::Db,
@@ -16,9 +29,20 @@ impl State {
         storage: sled::Db,
         auth: AuthManager,
         log_search: bool,
+        ingest_block_timeout: Option<Duration>,
+        read_only_node: bool,
+        auto_create_indexes: bool,
+        inflight: Option<InflightLimiter>,
+        analytics: Option<AnalyticsSink>,
     ) -> Self {
         Self {
             log_search,
+            ingest_block_timeout,
+            read_only_node,
+            auto_create_indexes,
+            inference_lock: Arc::new(tokio::sync::Mutex::new(())),
+            inflight,
+            analytics,
             engine,
             storage,
             auth,